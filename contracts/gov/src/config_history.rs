@@ -0,0 +1,127 @@
+//! Audit log of configuration changes. Only the latest CONFIG_HISTORY_SIZE changes are
+//! kept, older ones are pruned when a new change is recorded.
+
+use cosmwasm_std::{Deps, DepsMut, Env, MessageInfo, StdResult};
+
+use crate::state::{
+    config_change_count_read, config_change_count_store, config_change_store, read_config_changes,
+    read_tmp_poll_id, Config, ConfigChange,
+};
+use anchor_token::gov::{ConfigHistoryResponse, ConfigHistoryResponseItem};
+
+const CONFIG_HISTORY_SIZE: u64 = 100;
+
+/// changed_fields lists the names of the config fields which differ.
+/// Destructuring the config makes a field missing from the list a compile error.
+macro_rules! changed_fields {
+    ($old:expr, $new:expr, $($field:ident),* $(,)?) => {{
+        let Config { $($field: _),* } = $new;
+        let mut fields: Vec<String> = vec![];
+        $(
+            if $old.$field != $new.$field {
+                fields.push(stringify!($field).to_string());
+            }
+        )*
+        fields
+    }};
+}
+
+/// record_config_change adds an entry to the config history if any field changed.
+/// Changes sent by gov itself come from a poll being executed, whose id is kept
+/// for the reply of its messages.
+pub fn record_config_change(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    old: &Config,
+    new: &Config,
+) -> StdResult<()> {
+    let changed_fields = changed_fields!(
+        old,
+        new,
+        owner,
+        anchor_token,
+        quorum,
+        threshold,
+        voting_period,
+        timelock_period,
+        expiration_period,
+        proposal_deposit,
+        snapshot_period,
+        sponsorship_threshold,
+        sponsorship_period,
+        max_execute_msg_size,
+        max_execute_msgs,
+        min_stake_amount,
+        stake_depositors,
+        protocol_addresses,
+        max_spend_per_poll,
+        archive_after,
+        poll_clock,
+        block_time,
+        late_quorum_window,
+        late_quorum_extension,
+        participation_reward_ratio,
+        execution_denylist,
+        epoch_length,
+        max_open_polls_per_creator,
+        min_creator_stake,
+        vote_weight_buckets,
+        resubmit_window,
+        resubmit_deposit,
+        max_resubmit_depth,
+        deposit_cap,
+        atomic_batch,
+        expired_deposit_refund_ratio,
+    );
+    if changed_fields.is_empty() {
+        return Ok(());
+    }
+
+    let poll_id = if info.sender == env.contract.address {
+        read_tmp_poll_id(deps.storage).ok()
+    } else {
+        None
+    };
+
+    let id = config_change_count_read(deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    config_change_store(deps.storage).save(
+        &id.to_be_bytes(),
+        &ConfigChange {
+            id,
+            height: env.block.height,
+            time: env.block.time.seconds(),
+            sender: deps.api.addr_canonicalize(info.sender.as_str())?,
+            poll_id,
+            changed_fields,
+        },
+    )?;
+    if id >= CONFIG_HISTORY_SIZE {
+        config_change_store(deps.storage).remove(&(id - CONFIG_HISTORY_SIZE).to_be_bytes());
+    }
+    config_change_count_store(deps.storage).save(&(id + 1))
+}
+
+pub fn query_config_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ConfigHistoryResponse> {
+    let changes = read_config_changes(deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|change| {
+            Ok(ConfigHistoryResponseItem {
+                id: change.id,
+                height: change.height,
+                time: change.time,
+                sender: deps.api.addr_humanize(&change.sender)?.to_string(),
+                poll_id: change.poll_id,
+                changed_fields: change.changed_fields,
+            })
+        })
+        .collect::<StdResult<Vec<ConfigHistoryResponseItem>>>()?;
+
+    Ok(ConfigHistoryResponse { changes })
+}
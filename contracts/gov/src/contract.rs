@@ -1,25 +1,53 @@
+use crate::config_history::{query_config_history, record_config_change};
+use crate::epoch::{query_epoch_stats, record_epoch_activity, EpochActivity};
 use crate::error::ContractError;
-use crate::staking::{query_staker, stake_voting_tokens, withdraw_voting_tokens};
+use crate::merkle::{self, leaf_hash};
+use crate::migration::{migrate_config, migrate_state};
+use crate::staking::{
+    claim_rewards, continue_unlock, distribute_rewards, query_share_rate_history, query_staker,
+    query_staker_rewards, query_staking_metrics, query_withdraw_status, register_reward_token,
+    stake_voting_tokens, unlock_votes_for_poll, withdraw_voting_tokens, MAX_UNLOCK_VOTERS,
+};
 use crate::state::{
-    bank_read, bank_store, config_read, config_store, poll_indexer_store, poll_read, poll_store,
-    poll_voter_read, poll_voter_store, read_poll_voters, read_polls, read_tmp_poll_id, state_read,
-    state_store, store_tmp_poll_id, Config, ExecuteData, Poll, State,
+    archived_poll_read, archived_poll_store, bank_read, bank_store, config_read, config_store,
+    creator_poll_store, default_vote_weight_buckets, poll_indexer_store, poll_read,
+    poll_sponsor_read, poll_sponsor_store, poll_store, poll_voter_read, poll_voter_store,
+    read_creator_polls, read_poll_ids, read_poll_sponsors, read_poll_voters, read_polls,
+    read_tmp_poll_id, reward_token_read, state_read, state_store, store_tmp_poll_id,
+    vote_leaf_read, vote_leaf_store, vote_node_read, vote_node_store, ArchivedPoll, Config,
+    ExecuteData, Poll, ProtocolAddresses, State, VoteLeaf, DEFAULT_ARCHIVE_AFTER,
+    DEFAULT_BLOCK_TIME, DEFAULT_EPOCH_LENGTH, DEFAULT_EXPIRED_DEPOSIT_REFUND_PERCENT,
+    DEFAULT_MAX_EXECUTE_MSGS, DEFAULT_MAX_EXECUTE_MSG_SIZE, DEFAULT_MAX_RESUBMIT_DEPTH,
 };
 
 use astroport::querier::query_token_balance;
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, from_binary, to_binary, Binary, CanonicalAddr, CosmosMsg, Decimal, Deps, DepsMut, Env,
-    MessageInfo, Reply, Response, StdError, StdResult, SubMsg, Uint128, WasmMsg,
+    attr, from_binary, to_binary, to_vec, Binary, CanonicalAddr, CosmosMsg, Decimal, Deps, DepsMut,
+    Env, MessageInfo, Order, Reply, Response, StdError, StdResult, Storage, SubMsg, Uint128,
+    WasmMsg,
 };
+use cosmwasm_storage::ReadonlyBucket;
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 
 use anchor_token::common::OrderBy;
+use anchor_token::community::{
+    ConfigResponse as CommunityConfigResponse, ExecuteMsg as CommunityExecuteMsg,
+    QueryMsg as CommunityQueryMsg,
+};
 use anchor_token::gov::{
-    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, PollExecuteMsg, PollResponse,
-    PollStatus, PollsResponse, QueryMsg, StateResponse, VoteOption, VoterInfo, VotersResponse,
-    VotersResponseItem,
+    ArchivedPollResponse, ConfigResponse, ConfigUpdate, Cw20HookMsg, ExecuteMsg, ExecutionVerdict,
+    InstantiateMsg, MigrateMsg, PollClock, PollExecuteMsg, PollExecuteMsgsResponse,
+    PollExecuteMsgsResponseItem, PollProgressResponse, PollResponse, PollSpendMsg, PollStatus,
+    PollTallyDigestResponse, PollTallyDigestResponseItem, PollVoteMsg, PollVoteProofResponse,
+    PollWeightDistributionResponse, PollsResponse, ProtocolAddressesMsg, ProtocolAddressesResponse,
+    QueryMsg, SimulatePollExecutionResponse, SimulatePollExecutionResponseItem, SponsorsResponse,
+    SponsorsResponseItem, StateResponse, VoteOption, VoterInfo, VotersResponse, VotersResponseItem,
+    WeightBucketResponse, WeightMode,
 };
 
 const MIN_TITLE_LENGTH: usize = 4;
@@ -31,6 +59,28 @@ const MAX_LINK_LENGTH: usize = 128;
 
 const POLL_EXECUTE_REPLY_ID: u64 = 1;
 
+/// Outcome of counting the votes of a poll
+struct PollTally {
+    quorum: Decimal,
+    quorum_reached: bool,
+    staked_weight: Uint128,
+    status: PollStatus,
+    rejected_reason: &'static str,
+}
+
+/// Maximum number of passed poll ids listed by the state query
+const MAX_POLLS_AWAITING_EXECUTION: u64 = 50;
+
+/// Longest period accepted in the config, about 180 days of 6s blocks
+const MAX_PERIOD: u64 = 2_592_000;
+const MAX_BLOCK_TIME: u64 = 60; // seconds
+const MIN_EPOCH_LENGTH: u64 = 3600; // seconds
+const MAX_OPEN_POLLS_PER_CREATOR: u32 = 100;
+const MAX_VOTE_WEIGHT_BUCKETS: usize = 10;
+const MAX_RESUBMIT_DEPTH: u32 = 5;
+const MAX_TALLY_DIGEST_POLLS: usize = 20;
+const MAX_BATCH_VOTES: usize = 10;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -38,9 +88,6 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
-    validate_quorum(msg.quorum)?;
-    validate_threshold(msg.threshold)?;
-
     let config = Config {
         anchor_token: CanonicalAddr::from(vec![]),
         owner: deps.api.addr_canonicalize(info.sender.as_str())?,
@@ -51,13 +98,44 @@ pub fn instantiate(
         expiration_period: 0u64, // Depricated
         proposal_deposit: msg.proposal_deposit,
         snapshot_period: msg.snapshot_period,
+        sponsorship_threshold: Uint128::zero(),
+        sponsorship_period: 0u64,
+        max_execute_msg_size: DEFAULT_MAX_EXECUTE_MSG_SIZE,
+        max_execute_msgs: DEFAULT_MAX_EXECUTE_MSGS,
+        min_stake_amount: Uint128::zero(),
+        stake_depositors: vec![],
+        protocol_addresses: ProtocolAddresses::default(),
+        max_spend_per_poll: Uint128::zero(),
+        archive_after: DEFAULT_ARCHIVE_AFTER,
+        poll_clock: PollClock::Height,
+        block_time: DEFAULT_BLOCK_TIME,
+        late_quorum_window: 0u64,
+        late_quorum_extension: 0u64,
+        participation_reward_ratio: Decimal::zero(),
+        execution_denylist: vec![],
+        epoch_length: DEFAULT_EPOCH_LENGTH,
+        max_open_polls_per_creator: 0u32,
+        min_creator_stake: Uint128::zero(),
+        vote_weight_buckets: default_vote_weight_buckets(),
+        resubmit_window: 0u64,
+        resubmit_deposit: msg.proposal_deposit,
+        max_resubmit_depth: DEFAULT_MAX_RESUBMIT_DEPTH,
+        deposit_cap: None,
+        atomic_batch: true,
+        expired_deposit_refund_ratio: Decimal::percent(DEFAULT_EXPIRED_DEPOSIT_REFUND_PERCENT),
     };
+    validate_config(&config)?;
 
     let state = State {
         contract_addr: deps.api.addr_canonicalize(env.contract.address.as_str())?,
         poll_count: 0,
         total_share: Uint128::zero(),
         total_deposit: Uint128::zero(),
+        polls_in_progress: 0u64,
+        polls_awaiting_execution: 0u64,
+        participation_pool: Uint128::zero(),
+        participation_rewards: Uint128::zero(),
+        total_principal: Uint128::zero(),
     };
 
     config_store(deps.storage).save(&config)?;
@@ -77,34 +155,35 @@ pub fn execute(
         ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
         ExecuteMsg::ExecutePollMsgs { poll_id } => execute_poll_messages(deps, env, info, poll_id),
         ExecuteMsg::RegisterContracts { anchor_token } => register_contracts(deps, anchor_token),
-        ExecuteMsg::UpdateConfig {
-            owner,
-            quorum,
-            threshold,
-            voting_period,
-            timelock_period,
-            proposal_deposit,
-            snapshot_period,
-        } => update_config(
-            deps,
-            info,
-            owner,
-            quorum,
-            threshold,
-            voting_period,
-            timelock_period,
-            proposal_deposit,
-            snapshot_period,
-        ),
-        ExecuteMsg::WithdrawVotingTokens { amount } => withdraw_voting_tokens(deps, info, amount),
+        ExecuteMsg::UpdateConfig(update) => update_config(deps, env, info, update),
+        ExecuteMsg::WithdrawVotingTokens { amount } => {
+            withdraw_voting_tokens(deps, env, info, amount)
+        }
         ExecuteMsg::CastVote {
             poll_id,
             vote,
             amount,
         } => cast_vote(deps, env, info, poll_id, vote, amount),
+        ExecuteMsg::CastVotes { votes } => cast_votes(deps, env, info, votes),
         ExecuteMsg::EndPoll { poll_id } => end_poll(deps, env, poll_id),
         ExecuteMsg::ExecutePoll { poll_id } => execute_poll(deps, env, poll_id),
         ExecuteMsg::SnapshotPoll { poll_id } => snapshot_poll(deps, env, poll_id),
+        ExecuteMsg::SponsorPoll { poll_id } => sponsor_poll(deps, env, info, poll_id),
+        ExecuteMsg::ContinueUnlock { poll_id } => continue_unlock(deps, poll_id),
+        ExecuteMsg::RegisterRewardToken { token } => register_reward_token(deps, info, token),
+        ExecuteMsg::ClaimRewards { token } => claim_rewards(deps, info, token),
+        ExecuteMsg::ArchivePoll { poll_id, limit } => archive_poll(deps, env, poll_id, limit),
+        ExecuteMsg::UpdateExecutionDenylist { denylist } => {
+            update_execution_denylist(deps, env, info, denylist)
+        }
+        ExecuteMsg::UpdateProtocolAddresses(addresses) => {
+            update_protocol_addresses(deps, env, info, addresses)
+        }
+        ExecuteMsg::AmendPoll {
+            poll_id,
+            description,
+            link,
+        } => amend_poll(deps, info, poll_id, description, link),
     }
 }
 
@@ -137,22 +216,47 @@ pub fn receive_cw20(
     info: MessageInfo,
     cw20_msg: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
-    // only asset contract can execute this message
+    // only asset contract can execute this message, except for
+    // registered reward tokens distributing rewards
     let config: Config = config_read(deps.storage).load()?;
-    if config.anchor_token != deps.api.addr_canonicalize(info.sender.as_str())? {
-        return Err(ContractError::Unauthorized {});
+    let token_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if config.anchor_token != token_raw {
+        if reward_token_read(deps.storage)
+            .may_load(token_raw.as_slice())?
+            .is_none()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        return match from_binary(&cw20_msg.msg) {
+            Ok(Cw20HookMsg::DistributeRewards {}) => {
+                distribute_rewards(deps, info.sender, cw20_msg.amount)
+            }
+            _ => Err(ContractError::DataShouldBeGiven {}),
+        };
     }
 
     match from_binary(&cw20_msg.msg) {
-        Ok(Cw20HookMsg::StakeVotingTokens {}) => {
+        Ok(Cw20HookMsg::StakeVotingTokens { staker }) => {
             let api = deps.api;
-            stake_voting_tokens(deps, api.addr_validate(&cw20_msg.sender)?, cw20_msg.amount)
+            let sender = api.addr_validate(&cw20_msg.sender)?;
+            let staker = match staker {
+                Some(staker) => Some(api.addr_validate(&staker)?),
+                None => None,
+            };
+
+            stake_voting_tokens(deps, env, sender, staker, cw20_msg.amount)
+        }
+        Ok(Cw20HookMsg::ResubmitPoll { poll_id }) => {
+            resubmit_poll(deps, env, cw20_msg.sender, cw20_msg.amount, poll_id)
         }
         Ok(Cw20HookMsg::CreatePoll {
             title,
             description,
             link,
             execute_msgs,
+            spend,
+            weight_mode,
         }) => create_poll(
             deps,
             env,
@@ -162,63 +266,247 @@ pub fn receive_cw20(
             description,
             link,
             execute_msgs,
+            spend,
+            weight_mode,
         ),
         _ => Err(ContractError::DataShouldBeGiven {}),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
 pub fn update_config(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    owner: Option<String>,
-    quorum: Option<Decimal>,
-    threshold: Option<Decimal>,
-    voting_period: Option<u64>,
-    timelock_period: Option<u64>,
-    proposal_deposit: Option<Uint128>,
-    snapshot_period: Option<u64>,
+    update: ConfigUpdate,
 ) -> Result<Response, ContractError> {
     let api = deps.api;
-    config_store(deps.storage).update(|mut config| {
+    let old_config: Config = config_read(deps.storage).load()?;
+    let new_config = config_store(deps.storage).update(|mut config| {
         if config.owner != api.addr_canonicalize(info.sender.as_str())? {
             return Err(ContractError::Unauthorized {});
         }
 
-        if let Some(owner) = owner {
+        if let Some(owner) = update.owner {
             config.owner = api.addr_canonicalize(&owner)?;
         }
 
-        if let Some(quorum) = quorum {
+        if let Some(quorum) = update.quorum {
             config.quorum = quorum;
         }
 
-        if let Some(threshold) = threshold {
+        if let Some(threshold) = update.threshold {
             config.threshold = threshold;
         }
 
-        if let Some(voting_period) = voting_period {
+        if let Some(voting_period) = update.voting_period {
             config.voting_period = voting_period;
         }
 
-        if let Some(timelock_period) = timelock_period {
+        if let Some(timelock_period) = update.timelock_period {
             config.timelock_period = timelock_period;
         }
 
-        if let Some(proposal_deposit) = proposal_deposit {
+        if let Some(proposal_deposit) = update.proposal_deposit {
             config.proposal_deposit = proposal_deposit;
         }
 
-        if let Some(period) = snapshot_period {
+        if let Some(period) = update.snapshot_period {
             config.snapshot_period = period;
         }
 
+        if let Some(sponsorship_threshold) = update.sponsorship_threshold {
+            config.sponsorship_threshold = sponsorship_threshold;
+        }
+
+        if let Some(sponsorship_period) = update.sponsorship_period {
+            config.sponsorship_period = sponsorship_period;
+        }
+
+        if let Some(max_execute_msg_size) = update.max_execute_msg_size {
+            config.max_execute_msg_size = max_execute_msg_size;
+        }
+
+        if let Some(max_execute_msgs) = update.max_execute_msgs {
+            config.max_execute_msgs = max_execute_msgs;
+        }
+
+        if let Some(min_stake_amount) = update.min_stake_amount {
+            config.min_stake_amount = min_stake_amount;
+        }
+
+        if let Some(stake_depositors) = update.stake_depositors {
+            config.stake_depositors = stake_depositors
+                .iter()
+                .map(|depositor| api.addr_canonicalize(depositor))
+                .collect::<StdResult<Vec<CanonicalAddr>>>()?;
+        }
+
+        if let Some(max_spend_per_poll) = update.max_spend_per_poll {
+            config.max_spend_per_poll = max_spend_per_poll;
+        }
+
+        if let Some(archive_after) = update.archive_after {
+            config.archive_after = archive_after;
+        }
+
+        if let Some(poll_clock) = update.poll_clock {
+            config.poll_clock = poll_clock;
+        }
+
+        if let Some(block_time) = update.block_time {
+            config.block_time = block_time;
+        }
+
+        if let Some(late_quorum_window) = update.late_quorum_window {
+            config.late_quorum_window = late_quorum_window;
+        }
+
+        if let Some(late_quorum_extension) = update.late_quorum_extension {
+            config.late_quorum_extension = late_quorum_extension;
+        }
+
+        if let Some(participation_reward_ratio) = update.participation_reward_ratio {
+            config.participation_reward_ratio = participation_reward_ratio;
+        }
+
+        if let Some(epoch_length) = update.epoch_length {
+            config.epoch_length = epoch_length;
+        }
+
+        if let Some(max_open_polls_per_creator) = update.max_open_polls_per_creator {
+            config.max_open_polls_per_creator = max_open_polls_per_creator;
+        }
+
+        if let Some(min_creator_stake) = update.min_creator_stake {
+            config.min_creator_stake = min_creator_stake;
+        }
+
+        if let Some(vote_weight_buckets) = update.vote_weight_buckets {
+            config.vote_weight_buckets = vote_weight_buckets;
+        }
+
+        if let Some(resubmit_window) = update.resubmit_window {
+            config.resubmit_window = resubmit_window;
+        }
+
+        if let Some(resubmit_deposit) = update.resubmit_deposit {
+            config.resubmit_deposit = resubmit_deposit;
+        }
+
+        if let Some(max_resubmit_depth) = update.max_resubmit_depth {
+            config.max_resubmit_depth = max_resubmit_depth;
+        }
+
+        if let Some(deposit_cap) = update.deposit_cap {
+            config.deposit_cap = deposit_cap;
+        }
+
+        if let Some(atomic_batch) = update.atomic_batch {
+            config.atomic_batch = atomic_batch;
+        }
+
+        if let Some(expired_deposit_refund_ratio) = update.expired_deposit_refund_ratio {
+            config.expired_deposit_refund_ratio = expired_deposit_refund_ratio;
+        }
+
+        validate_config(&config)?;
         Ok(config)
     })?;
 
+    // disabling participation rewards returns the undistributed pool to the stakers
+    if new_config.participation_reward_ratio.is_zero() {
+        state_store(deps.storage).update(|mut state| -> StdResult<_> {
+            state.participation_pool = Uint128::zero();
+            Ok(state)
+        })?;
+    }
+    record_config_change(deps, &env, &info, &old_config, &new_config)?;
+
     Ok(Response::new().add_attributes(vec![("action", "update_config")]))
 }
 
+/// update_execution_denylist can only be called by the gov contract itself,
+/// so the owner can not weaken the denylist without a poll
+pub fn update_execution_denylist(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denylist: Vec<String>,
+) -> Result<Response, ContractError> {
+    if env.contract.address != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // denying the gov contract would lock the denylist forever
+    if denylist.contains(&env.contract.address.to_string()) {
+        return Err(invalid_config(
+            "execution_denylist",
+            "without the gov contract",
+        ));
+    }
+
+    let old_config: Config = config_read(deps.storage).load()?;
+    let mut config = old_config.clone();
+    config.execution_denylist = denylist
+        .iter()
+        .map(|contract| deps.api.addr_canonicalize(contract))
+        .collect::<StdResult<Vec<CanonicalAddr>>>()?;
+    config_store(deps.storage).save(&config)?;
+    record_config_change(deps, &env, &info, &old_config, &config)?;
+
+    Ok(Response::new().add_attributes(vec![("action", "update_execution_denylist")]))
+}
+
+/// update_protocol_addresses can only be called by the gov contract itself,
+/// so the owner can not redirect spend polls without a poll
+pub fn update_protocol_addresses(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    addresses: ProtocolAddressesMsg,
+) -> Result<Response, ContractError> {
+    if env.contract.address != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let old_config: Config = config_read(deps.storage).load()?;
+    let mut config = old_config.clone();
+    let registry = &mut config.protocol_addresses;
+    for (address, slot) in [
+        (addresses.community_fund, &mut registry.community_fund),
+        (addresses.collector, &mut registry.collector),
+        (addresses.voting_escrow, &mut registry.voting_escrow),
+        (addresses.gauge_controller, &mut registry.gauge_controller),
+    ] {
+        if let Some(address) = address {
+            *slot = address
+                .map(|address| deps.api.addr_canonicalize(&address))
+                .transpose()?;
+        }
+    }
+    config_store(deps.storage).save(&config)?;
+    record_config_change(deps, &env, &info, &old_config, &config)?;
+
+    Ok(Response::new().add_attributes(vec![("action", "update_protocol_addresses")]))
+}
+
+/// check_execution_denylist returns an error if a message targets a denied contract
+fn check_execution_denylist(
+    deps: Deps,
+    config: &Config,
+    execute_data: &[ExecuteData],
+) -> Result<(), ContractError> {
+    match execute_data
+        .iter()
+        .find(|data| config.execution_denylist.contains(&data.contract))
+    {
+        Some(data) => Err(ContractError::DeniedExecuteTarget(
+            deps.api.addr_humanize(&data.contract)?.to_string(),
+        )),
+        None => Ok(()),
+    }
+}
+
 /// validate_title returns an error if the title is invalid
 fn validate_title(title: &str) -> StdResult<()> {
     if title.len() < MIN_TITLE_LENGTH {
@@ -256,26 +544,253 @@ fn validate_link(link: &Option<String>) -> StdResult<()> {
     }
 }
 
-/// validate_quorum returns an error if the quorum is invalid
-/// (we require 0-1)
-fn validate_quorum(quorum: Decimal) -> StdResult<()> {
-    if quorum > Decimal::one() {
-        Err(StdError::generic_err("quorum must be 0 to 1"))
+/// query_creator_stake returns the staked amount of a poll creator, leaving out
+/// the deposit which is received but not yet reserved
+fn query_creator_stake(
+    deps: Deps,
+    config: &Config,
+    creator: &str,
+    deposit_amount: Uint128,
+) -> Result<Uint128, ContractError> {
+    let state: State = state_read(deps.storage).load()?;
+    if state.total_share.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    let creator_raw = deps.api.addr_canonicalize(creator)?;
+    let token_manager = bank_read(deps.storage)
+        .may_load(creator_raw.as_slice())?
+        .unwrap_or_default();
+    let total_balance = query_token_balance(
+        &deps.querier,
+        deps.api.addr_humanize(&config.anchor_token)?,
+        deps.api.addr_humanize(&state.contract_addr)?,
+    )?
+    .checked_sub(state.reserved_amount() + deposit_amount)?;
+
+    Ok(token_manager
+        .share
+        .multiply_ratio(total_balance, state.total_share))
+}
+
+/// spend_execute_data builds the community fund transfer of a spend poll,
+/// ordered after the other execute messages
+fn spend_execute_data(
+    deps: Deps,
+    config: &Config,
+    execute_msgs: &Option<Vec<PollExecuteMsg>>,
+    spend: PollSpendMsg,
+) -> Result<ExecuteData, ContractError> {
+    let community_fund = match &config.protocol_addresses.community_fund {
+        Some(community_fund) => community_fund.clone(),
+        None => return Err(ContractError::CommunityFundNotSet {}),
+    };
+
+    if spend.amount.is_zero() {
+        return Err(ContractError::InvalidSpendAmount {});
+    }
+
+    if spend.amount > config.max_spend_per_poll {
+        return Err(ContractError::SpendLimitExceeded(
+            config.max_spend_per_poll.u128(),
+        ));
+    }
+
+    deps.api.addr_validate(&spend.recipient)?;
+
+    let order = execute_msgs
+        .iter()
+        .flatten()
+        .map(|msg| msg.order)
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    Ok(ExecuteData {
+        order,
+        contract: community_fund,
+        msg: to_binary(&CommunityExecuteMsg::Spend {
+            recipient: spend.recipient,
+            amount: spend.amount,
+        })?,
+    })
+}
+
+/// validate_config returns an error naming the first invalid field of the config
+/// and its allowed range. Runs on instantiate and after every config update.
+fn validate_config(config: &Config) -> Result<(), ContractError> {
+    validate_ratio("quorum", config.quorum)?;
+    validate_ratio("threshold", config.threshold)?;
+    validate_period("voting_period", config.voting_period)?;
+    validate_period("timelock_period", config.timelock_period)?;
+
+    if config.snapshot_period > config.voting_period {
+        return Err(invalid_config("snapshot_period", "at most voting_period"));
+    }
+
+    // only legacy configs still have an expiration period
+    if config.expiration_period != 0 && config.timelock_period > config.expiration_period {
+        return Err(invalid_config(
+            "timelock_period",
+            "at most expiration_period",
+        ));
+    }
+
+    if config.proposal_deposit.is_zero() {
+        return Err(invalid_config("proposal_deposit", "greater than 0"));
+    }
+
+    if !config.sponsorship_threshold.is_zero() {
+        validate_period("sponsorship_period", config.sponsorship_period)?;
+    }
+
+    if config.block_time == 0 || config.block_time > MAX_BLOCK_TIME {
+        return Err(invalid_config(
+            "block_time",
+            &format!("1 to {} seconds", MAX_BLOCK_TIME),
+        ));
+    }
+
+    if config.late_quorum_window > config.voting_period {
+        return Err(invalid_config(
+            "late_quorum_window",
+            "at most voting_period",
+        ));
+    }
+
+    if config.late_quorum_extension > MAX_PERIOD {
+        return Err(invalid_config(
+            "late_quorum_extension",
+            &format!("at most {} blocks", MAX_PERIOD),
+        ));
+    }
+
+    if config.participation_reward_ratio > Decimal::one() {
+        return Err(invalid_config("participation_reward_ratio", "at most 1"));
+    }
+
+    if config.expired_deposit_refund_ratio > Decimal::one() {
+        return Err(invalid_config("expired_deposit_refund_ratio", "at most 1"));
+    }
+
+    if config.epoch_length < MIN_EPOCH_LENGTH {
+        return Err(invalid_config(
+            "epoch_length",
+            &format!("at least {} seconds", MIN_EPOCH_LENGTH),
+        ));
+    }
+
+    if config.vote_weight_buckets.len() > MAX_VOTE_WEIGHT_BUCKETS
+        || config.vote_weight_buckets.contains(&Uint128::zero())
+        || config
+            .vote_weight_buckets
+            .windows(2)
+            .any(|pair| pair[0] >= pair[1])
+    {
+        return Err(invalid_config(
+            "vote_weight_buckets",
+            &format!(
+                "ascending and above 0, with at most {} boundaries",
+                MAX_VOTE_WEIGHT_BUCKETS
+            ),
+        ));
+    }
+
+    if config.max_open_polls_per_creator > MAX_OPEN_POLLS_PER_CREATOR {
+        return Err(invalid_config(
+            "max_open_polls_per_creator",
+            &format!("at most {}", MAX_OPEN_POLLS_PER_CREATOR),
+        ));
+    }
+
+    if config.resubmit_window > MAX_PERIOD {
+        return Err(invalid_config(
+            "resubmit_window",
+            &format!("at most {} blocks", MAX_PERIOD),
+        ));
+    }
+
+    if config.resubmit_deposit.is_zero() {
+        return Err(invalid_config("resubmit_deposit", "greater than 0"));
+    }
+
+    if config.max_resubmit_depth > MAX_RESUBMIT_DEPTH {
+        return Err(invalid_config(
+            "max_resubmit_depth",
+            &format!("at most {}", MAX_RESUBMIT_DEPTH),
+        ));
+    }
+
+    if config.deposit_cap == Some(Uint128::zero()) {
+        return Err(invalid_config("deposit_cap", "greater than 0"));
+    }
+
+    Ok(())
+}
+
+/// validate_ratio returns an error if the ratio is not in (0, 1].
+/// Catches percentages given where a ratio is expected.
+fn validate_ratio(field: &str, ratio: Decimal) -> Result<(), ContractError> {
+    if ratio.is_zero() || ratio > Decimal::one() {
+        Err(invalid_config(field, "greater than 0 and at most 1"))
     } else {
         Ok(())
     }
 }
 
-/// validate_threshold returns an error if the threshold is invalid
-/// (we require 0-1)
-fn validate_threshold(threshold: Decimal) -> StdResult<()> {
-    if threshold > Decimal::one() {
-        Err(StdError::generic_err("threshold must be 0 to 1"))
+/// validate_period returns an error if the period in blocks is zero or too long
+fn validate_period(field: &str, period: u64) -> Result<(), ContractError> {
+    if period == 0 || period > MAX_PERIOD {
+        Err(invalid_config(
+            field,
+            &format!("1 to {} blocks", MAX_PERIOD),
+        ))
     } else {
         Ok(())
     }
 }
 
+fn invalid_config(field: &str, allowed: &str) -> ContractError {
+    ContractError::InvalidConfig(field.to_string(), allowed.to_string())
+}
+
+/// validate_execute_msgs returns an error if the poll execute messages
+/// are too many, too large or contain duplicates. The spend of a spend poll
+/// counts as one more message.
+fn validate_execute_msgs(
+    deps: Deps,
+    config: &Config,
+    execute_msgs: &[PollExecuteMsg],
+    has_spend: bool,
+) -> Result<(), ContractError> {
+    if execute_msgs.len() as u64 + u64::from(has_spend) > config.max_execute_msgs {
+        return Err(ContractError::TooManyExecuteMsgs(config.max_execute_msgs));
+    }
+
+    let gov_contract = state_read(deps.storage).load()?.contract_addr;
+    let mut seen: Vec<(CanonicalAddr, Vec<u8>)> = vec![];
+    for (index, execute_msg) in execute_msgs.iter().enumerate() {
+        if execute_msg.msg.len() as u64 > config.max_execute_msg_size {
+            return Err(ContractError::ExecuteMsgTooLarge(
+                index as u64,
+                config.max_execute_msg_size,
+            ));
+        }
+
+        // the same call can be written with another address case or JSON formatting
+        let contract = deps.api.addr_canonicalize(&execute_msg.contract)?;
+        let msg = decode_known_msg(config, &gov_contract, &contract, &execute_msg.msg)
+            .map_or_else(|| execute_msg.msg.to_vec(), String::into_bytes);
+        let call = (contract, msg);
+        if seen.contains(&call) {
+            return Err(ContractError::DuplicateExecuteMsg(index as u64));
+        }
+        seen.push(call);
+    }
+
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 /// create a new poll
 pub fn create_poll(
@@ -287,6 +802,8 @@ pub fn create_poll(
     description: String,
     link: Option<String>,
     execute_msgs: Option<Vec<PollExecuteMsg>>,
+    spend: Option<PollSpendMsg>,
+    weight_mode: Option<WeightMode>,
 ) -> Result<Response, ContractError> {
     validate_title(&title)?;
     validate_description(&description)?;
@@ -299,15 +816,67 @@ pub fn create_poll(
         ));
     }
 
-    let mut state: State = state_store(deps.storage).load()?;
-    let poll_id = state.poll_count + 1;
+    validate_execute_msgs(
+        deps.as_ref(),
+        &config,
+        execute_msgs.as_deref().unwrap_or_default(),
+        spend.is_some(),
+    )?;
+
+    let weight_mode = weight_mode.unwrap_or(WeightMode::Linear);
+    if let WeightMode::Capped { max_weight } = weight_mode {
+        if max_weight.is_zero() {
+            return Err(ContractError::InvalidWeightMode {});
+        }
+    }
 
-    // Increase poll count & total deposit amount
-    state.poll_count += 1;
-    state.total_deposit += deposit_amount;
+    // a stake split across addresses gains weight in the non-linear modes,
+    // which must not decide polls executing messages
+    let has_messages = matches!(&execute_msgs, Some(msgs) if !msgs.is_empty());
+    if weight_mode != WeightMode::Linear && (has_messages || spend.is_some()) {
+        return Err(ContractError::WeightModeNotAllowed {});
+    }
+
+    let all_execute_data = poll_execute_data(deps.as_ref(), &config, execute_msgs, spend.clone())?;
+
+    let new_poll = open_poll(
+        deps,
+        &env,
+        &config,
+        &proposer,
+        deposit_amount,
+        title,
+        description,
+        link,
+        all_execute_data,
+        spend,
+        weight_mode,
+        None,
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "create_poll"),
+        ("creator", proposer.as_str()),
+        ("poll_id", &new_poll.id.to_string()),
+        ("end_height", new_poll.end_height.to_string().as_str()),
+    ]))
+}
+
+/// poll_execute_data converts the execute messages of a poll for storage,
+/// followed by the community fund transfer of a spend poll
+fn poll_execute_data(
+    deps: Deps,
+    config: &Config,
+    execute_msgs: Option<Vec<PollExecuteMsg>>,
+    spend: Option<PollSpendMsg>,
+) -> Result<Option<Vec<ExecuteData>>, ContractError> {
+    let spend_data = match spend {
+        Some(spend) => Some(spend_execute_data(deps, config, &execute_msgs, spend)?),
+        None => None,
+    };
 
     let mut data_list: Vec<ExecuteData> = vec![];
-    let all_execute_data = if let Some(exe_msgs) = execute_msgs {
+    let mut all_execute_data = if let Some(exe_msgs) = execute_msgs {
         for msgs in exe_msgs {
             let execute_data = ExecuteData {
                 order: msgs.order,
@@ -321,136 +890,461 @@ pub fn create_poll(
         None
     };
 
-    let sender_address_raw = deps.api.addr_canonicalize(&proposer)?;
-    let new_poll = Poll {
-        id: poll_id,
-        creator: sender_address_raw,
-        status: PollStatus::InProgress,
-        yes_votes: Uint128::zero(),
-        no_votes: Uint128::zero(),
-        end_height: env.block.height + config.voting_period,
-        title,
-        description,
-        link,
-        execute_data: all_execute_data,
-        deposit_amount,
-        total_balance_at_end_poll: None,
-        staked_amount: None,
-    };
-
-    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &new_poll)?;
-    poll_indexer_store(deps.storage, &PollStatus::InProgress)
-        .save(&poll_id.to_be_bytes(), &true)?;
-
-    state_store(deps.storage).save(&state)?;
+    if let Some(spend_data) = spend_data {
+        all_execute_data
+            .get_or_insert_with(Vec::new)
+            .push(spend_data);
+    }
 
-    Ok(Response::new().add_attributes(vec![
-        ("action", "create_poll"),
-        (
-            "creator",
-            deps.api
-                .addr_humanize(&new_poll.creator)?
-                .to_string()
-                .as_str(),
-        ),
-        ("poll_id", &poll_id.to_string()),
-        ("end_height", new_poll.end_height.to_string().as_str()),
-    ]))
+    Ok(all_execute_data)
 }
 
-/*
- * Ends a poll.
- */
-pub fn end_poll(deps: DepsMut, env: Env, poll_id: u64) -> Result<Response, ContractError> {
-    let mut a_poll: Poll = poll_store(deps.storage).load(&poll_id.to_be_bytes())?;
+/// resubmit_poll creates a copy of a poll rejected for missing quorum. Title,
+/// description and link were validated with the original poll, the execute
+/// messages are checked against the current config and denylist again and
+/// the spend is rebuilt for the current community fund.
+pub fn resubmit_poll(
+    mut deps: DepsMut,
+    env: Env,
+    proposer: String,
+    deposit_amount: Uint128,
+    poll_id: u64,
+) -> Result<Response, ContractError> {
+    let config: Config = config_read(deps.storage).load()?;
+    let mut a_poll: Poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(poll) => poll,
+        None => return Err(poll_not_found(deps.as_ref(), poll_id)?),
+    };
 
-    if a_poll.status != PollStatus::InProgress {
-        return Err(ContractError::PollNotInProgress {});
+    if a_poll.creator != deps.api.addr_canonicalize(&proposer)? {
+        return Err(ContractError::Unauthorized {});
     }
 
-    if a_poll.end_height > env.block.height {
-        return Err(ContractError::PollVotingPeriod {});
+    let quorum_failed_at = a_poll
+        .quorum_failed_at
+        .ok_or(ContractError::PollNotResubmittable {})?;
+    if let Some(resubmitted_as) = a_poll.resubmitted_as {
+        return Err(ContractError::PollAlreadyResubmitted(resubmitted_as));
     }
 
-    let no = a_poll.no_votes.u128();
-    let yes = a_poll.yes_votes.u128();
+    // a zero window disables resubmission
+    if env.block.height >= quorum_failed_at + config.resubmit_window {
+        return Err(ContractError::ResubmitWindowExpired {});
+    }
 
-    let tallied_weight = yes + no;
+    if a_poll.resubmit_depth >= config.max_resubmit_depth {
+        return Err(ContractError::ResubmitDepthExceeded(
+            config.max_resubmit_depth,
+        ));
+    }
+
+    let resubmit_deposit = std::cmp::min(config.resubmit_deposit, config.proposal_deposit);
+    if deposit_amount < resubmit_deposit {
+        return Err(ContractError::InsufficientProposalDeposit(
+            resubmit_deposit.u128(),
+        ));
+    }
+
+    let mut execute_msgs = match &a_poll.execute_data {
+        Some(execute_data) => Some(
+            execute_data
+                .iter()
+                .map(|msg| {
+                    Ok(PollExecuteMsg {
+                        order: msg.order,
+                        contract: deps.api.addr_humanize(&msg.contract)?.to_string(),
+                        msg: msg.msg.clone(),
+                    })
+                })
+                .collect::<StdResult<Vec<PollExecuteMsg>>>()?,
+        ),
+        None => None,
+    };
+
+    // the spend is stored as the last message
+    if a_poll.spend.is_some() {
+        if let Some(execute_msgs) = &mut execute_msgs {
+            execute_msgs.pop();
+        }
+    }
+
+    validate_execute_msgs(
+        deps.as_ref(),
+        &config,
+        execute_msgs.as_deref().unwrap_or_default(),
+        a_poll.spend.is_some(),
+    )?;
+
+    let execute_data =
+        poll_execute_data(deps.as_ref(), &config, execute_msgs, a_poll.spend.clone())?;
+
+    let new_poll = open_poll(
+        deps.branch(),
+        &env,
+        &config,
+        &proposer,
+        deposit_amount,
+        a_poll.title.clone(),
+        a_poll.description.clone(),
+        a_poll.link.clone(),
+        execute_data,
+        a_poll.spend.clone(),
+        a_poll.weight_mode.clone(),
+        Some(&a_poll),
+    )?;
+
+    a_poll.resubmitted_as = Some(new_poll.id);
+    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "resubmit_poll"),
+        ("creator", proposer.as_str()),
+        ("poll_id", &new_poll.id.to_string()),
+        ("resubmitted_from", &poll_id.to_string()),
+        ("end_height", new_poll.end_height.to_string().as_str()),
+    ]))
+}
+
+/// open_poll stores a new poll with already validated content, applying the
+/// checks on the creator and the denylist shared by created and resubmitted polls
+#[allow(clippy::too_many_arguments)]
+fn open_poll(
+    mut deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    proposer: &str,
+    deposit_amount: Uint128,
+    title: String,
+    description: String,
+    link: Option<String>,
+    execute_data: Option<Vec<ExecuteData>>,
+    spend: Option<PollSpendMsg>,
+    weight_mode: WeightMode,
+    resubmitted_from: Option<&Poll>,
+) -> Result<Poll, ContractError> {
+    // text polls stay open to anyone paying the deposit
+    let has_messages = matches!(&execute_data, Some(data) if !data.is_empty());
+    if has_messages && !config.min_creator_stake.is_zero() {
+        let creator_stake = query_creator_stake(deps.as_ref(), config, proposer, deposit_amount)?;
+        if creator_stake < config.min_creator_stake {
+            return Err(ContractError::InsufficientCreatorStake(
+                config.min_creator_stake.u128(),
+                (config.min_creator_stake - creator_stake).u128(),
+            ));
+        }
+    }
+
+    record_epoch_activity(
+        deps.branch(),
+        env,
+        deposit_amount,
+        EpochActivity::PollCreated,
+    )?;
+
+    let mut state: State = state_store(deps.storage).load()?;
+    let poll_id = state.poll_count + 1;
+
+    // Increase poll count & total deposit amount
+    state.poll_count += 1;
+    state.total_deposit += deposit_amount;
+
+    if let Some(execute_data) = &execute_data {
+        check_execution_denylist(deps.as_ref(), config, execute_data)?;
+    }
+
+    // when sponsorship is enabled, voting starts only after the poll is sponsored
+    let (status, period) = if config.sponsorship_threshold.is_zero() {
+        (PollStatus::InProgress, config.voting_period)
+    } else {
+        (PollStatus::Pending, config.sponsorship_period)
+    };
+    let end_height = env.block.height + period;
+    let end_time = env.block.time.seconds() + period * config.block_time;
+
+    if status == PollStatus::InProgress {
+        state.polls_in_progress += 1;
+    }
+
+    let sender_address_raw = deps.api.addr_canonicalize(proposer)?;
+    if config.max_open_polls_per_creator > 0 {
+        let open_polls = read_creator_polls(
+            deps.storage,
+            &sender_address_raw,
+            config.max_open_polls_per_creator as usize,
+        )?;
+        if open_polls.len() as u32 >= config.max_open_polls_per_creator {
+            let poll_ids: Vec<String> = open_polls.iter().take(5).map(u64::to_string).collect();
+            return Err(ContractError::TooManyOpenPolls(poll_ids.join(", ")));
+        }
+    }
+    creator_poll_store(deps.storage, &sender_address_raw).save(&poll_id.to_be_bytes(), &true)?;
+
+    let new_poll = Poll {
+        id: poll_id,
+        creator: sender_address_raw,
+        status: status.clone(),
+        yes_votes: Uint128::zero(),
+        no_votes: Uint128::zero(),
+        end_height,
+        title,
+        description,
+        link,
+        execute_data,
+        deposit_amount,
+        total_balance_at_end_poll: None,
+        staked_amount: None,
+        sponsor_weight: Uint128::zero(),
+        unlock_pending: false,
+        amended: false,
+        weight_mode,
+        weighted_yes_votes: Uint128::zero(),
+        weighted_no_votes: Uint128::zero(),
+        end_time,
+        clock: config.poll_clock.clone(),
+        vote_leaf_count: 0,
+        vote_root: None,
+        extended: false,
+        participation_reward: Uint128::zero(),
+        participation_distributed: Uint128::zero(),
+        weight_buckets: config.vote_weight_buckets.clone(),
+        weight_bucket_voters: vec![0u64; config.vote_weight_buckets.len() + 1],
+        last_vote_height: 0,
+        quorum_failed_at: None,
+        resubmitted_from: resubmitted_from.map(|poll| poll.id),
+        resubmitted_as: None,
+        resubmit_depth: resubmitted_from.map_or(0, |poll| poll.resubmit_depth + 1),
+        spend,
+    };
+
+    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &new_poll)?;
+    poll_indexer_store(deps.storage, &status).save(&poll_id.to_be_bytes(), &true)?;
+
+    state_store(deps.storage).save(&state)?;
+
+    Ok(new_poll)
+}
+
+/*
+ * Ends a poll.
+ */
+pub fn end_poll(mut deps: DepsMut, env: Env, poll_id: u64) -> Result<Response, ContractError> {
+    let mut a_poll: Poll = poll_store(deps.storage).load(&poll_id.to_be_bytes())?;
+
+    if a_poll.status == PollStatus::Pending {
+        return expire_poll(deps, env, a_poll);
+    }
+
+    if a_poll.status != PollStatus::InProgress {
+        return Err(ContractError::PollNotInProgress {});
+    }
+
+    let (now, voting_end) = voting_clock(&a_poll, &env);
+    if voting_end > now {
+        return Err(ContractError::PollVotingPeriod {});
+    }
 
-    let mut poll_status = PollStatus::Rejected;
-    let mut rejected_reason = "";
-    let mut passed = false;
+    record_epoch_activity(
+        deps.branch(),
+        &env,
+        Uint128::zero(),
+        EpochActivity::PollEnded,
+    )?;
 
     let mut messages: Vec<CosmosMsg> = vec![];
     let config: Config = config_read(deps.storage).load()?;
     let mut state: State = state_read(deps.storage).load()?;
 
-    let (quorum, staked_weight) = if state.total_share.u128() == 0 {
-        (Decimal::zero(), Uint128::zero())
+    let tally = tally_poll(deps.as_ref(), &config, &state, &a_poll)?;
+    let poll_status = tally.status;
+    let rejected_reason = tally.rejected_reason;
+    let staked_weight = tally.staked_weight;
+    let passed = poll_status == PollStatus::Passed;
+
+    // Refunds deposit only when quorum is reached
+    if tally.quorum_reached && !a_poll.deposit_amount.is_zero() {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps.api.addr_humanize(&config.anchor_token)?.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: deps.api.addr_humanize(&a_poll.creator)?.to_string(),
+                amount: a_poll.deposit_amount,
+            })?,
+        }))
+    }
+
+    // Decrease total deposit amount
+    state.total_deposit = state.total_deposit.checked_sub(a_poll.deposit_amount)?;
+    state.polls_in_progress = state.polls_in_progress.saturating_sub(1);
+
+    // voters of polls reaching quorum share a part of the slashed deposits,
+    // which is handed out while their votes are unlocked
+    if !config.participation_reward_ratio.is_zero() {
+        if tally.quorum_reached {
+            let reward = state.participation_pool * config.participation_reward_ratio;
+            state.participation_pool = state.participation_pool.checked_sub(reward)?;
+            state.participation_rewards += reward;
+            a_poll.participation_reward = reward;
+        } else {
+            state.participation_pool += a_poll.deposit_amount;
+        }
+    }
+
+    if passed {
+        state.polls_awaiting_execution += 1;
+    }
+    state_store(deps.storage).save(&state)?;
+
+    // Update poll indexer
+    poll_indexer_store(deps.storage, &PollStatus::InProgress).remove(&a_poll.id.to_be_bytes());
+    poll_indexer_store(deps.storage, &poll_status).save(&a_poll.id.to_be_bytes(), &true)?;
+
+    // Update poll status
+    if poll_status != PollStatus::Passed {
+        close_creator_poll(deps.storage, &a_poll);
+    }
+    a_poll.status = poll_status;
+    a_poll.total_balance_at_end_poll = Some(staked_weight);
+    if !tally.quorum_reached {
+        a_poll.quorum_failed_at = Some(env.block.height);
+    }
+    let progress =
+        unlock_votes_for_poll(deps.storage, deps.api, &mut a_poll, MAX_UNLOCK_VOTERS, true)?;
+    a_poll.unlock_pending = progress.pending;
+    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
+
+    let mut response = Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "end_poll"),
+        ("poll_id", &poll_id.to_string()),
+        ("rejected_reason", rejected_reason),
+        ("passed", &passed.to_string()),
+    ]);
+
+    if a_poll.unlock_pending {
+        response = response.add_attribute("unlock_pending", "true");
+    }
+
+    if progress.skipped_voters > 0 {
+        response = response.add_attribute("skipped_voters", progress.skipped_voters.to_string());
+    }
+
+    Ok(response)
+}
+
+/// tally_poll computes the outcome of a poll as if it was ended now.
+/// It is shared by end_poll and the progress query so they can not diverge.
+fn tally_poll(
+    deps: Deps,
+    config: &Config,
+    state: &State,
+    a_poll: &Poll,
+) -> Result<PollTally, ContractError> {
+    let no = a_poll.no_votes.u128();
+    let yes = a_poll.yes_votes.u128();
+
+    let tallied_weight = yes + no;
+
+    // legacy polls have no weighted tally, linear weights are the balances themselves
+    let (weighted_yes, weighted_no) = match a_poll.weight_mode {
+        WeightMode::Linear => (yes, no),
+        _ => (
+            a_poll.weighted_yes_votes.u128(),
+            a_poll.weighted_no_votes.u128(),
+        ),
+    };
+
+    let staked_weight = if state.total_share.u128() == 0 {
+        Uint128::zero()
     } else if let Some(staked_amount) = a_poll.staked_amount {
-        (
-            Decimal::from_ratio(tallied_weight, staked_amount),
-            staked_amount,
-        )
+        staked_amount
     } else {
-        let staked_weight = query_token_balance(
+        query_token_balance(
             &deps.querier,
             deps.api.addr_humanize(&config.anchor_token)?,
             deps.api.addr_humanize(&state.contract_addr)?,
         )?
-        .checked_sub(state.total_deposit)?;
+        .checked_sub(state.reserved_amount())?
+    };
 
-        (
-            Decimal::from_ratio(tallied_weight, staked_weight),
-            staked_weight,
-        )
+    // nothing staked at the snapshot or left staked now
+    let quorum = if staked_weight.is_zero() {
+        Decimal::zero()
+    } else {
+        Decimal::from_ratio(tallied_weight, staked_weight)
+    };
+
+    let mut tally = PollTally {
+        quorum,
+        quorum_reached: false,
+        staked_weight,
+        status: PollStatus::Rejected,
+        rejected_reason: "",
     };
 
     if tallied_weight == 0 || quorum < config.quorum {
         // Quorum: More than quorum of the total staked tokens at the end of the voting
         // period need to have participated in the vote.
-        rejected_reason = "Quorum not reached";
+        tally.rejected_reason = "Quorum not reached";
     } else {
-        if Decimal::from_ratio(yes, tallied_weight) > config.threshold {
+        tally.quorum_reached = true;
+        if Decimal::from_ratio(weighted_yes, weighted_yes + weighted_no) > config.threshold {
             //Threshold: More than 50% of the tokens that participated in the vote
             // (after excluding “Abstain” votes) need to have voted in favor of the proposal (“Yes”).
-            poll_status = PollStatus::Passed;
-            passed = true;
+            tally.status = PollStatus::Passed;
         } else {
-            rejected_reason = "Threshold not reached";
+            tally.rejected_reason = "Threshold not reached";
         }
+    }
 
-        // Refunds deposit only when quorum is reached
-        if !a_poll.deposit_amount.is_zero() {
-            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: deps.api.addr_humanize(&config.anchor_token)?.to_string(),
-                funds: vec![],
-                msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient: deps.api.addr_humanize(&a_poll.creator)?.to_string(),
-                    amount: a_poll.deposit_amount,
-                })?,
-            }))
-        }
+    Ok(tally)
+}
+
+/*
+ * Expires a pending poll which was not sponsored in time
+ * and refunds the unslashed part of the deposit.
+ */
+fn expire_poll(deps: DepsMut, env: Env, mut a_poll: Poll) -> Result<Response, ContractError> {
+    if a_poll.end_height >= env.block.height {
+        return Err(ContractError::SponsorshipPeriod {});
+    }
+
+    let config: Config = config_read(deps.storage).load()?;
+    let mut state: State = state_read(deps.storage).load()?;
+
+    // the slashed part of the deposit stays in the contract as staking reward,
+    // or feeds the participation pool when participation rewards are enabled
+    let refund_amount = a_poll.deposit_amount * config.expired_deposit_refund_ratio;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !refund_amount.is_zero() {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps.api.addr_humanize(&config.anchor_token)?.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: deps.api.addr_humanize(&a_poll.creator)?.to_string(),
+                amount: refund_amount,
+            })?,
+        }))
     }
 
     // Decrease total deposit amount
     state.total_deposit = state.total_deposit.checked_sub(a_poll.deposit_amount)?;
+    if !config.participation_reward_ratio.is_zero() {
+        state.participation_pool += a_poll.deposit_amount.checked_sub(refund_amount)?;
+    }
     state_store(deps.storage).save(&state)?;
 
     // Update poll indexer
-    poll_indexer_store(deps.storage, &PollStatus::InProgress).remove(&a_poll.id.to_be_bytes());
-    poll_indexer_store(deps.storage, &poll_status).save(&a_poll.id.to_be_bytes(), &true)?;
+    poll_indexer_store(deps.storage, &PollStatus::Pending).remove(&a_poll.id.to_be_bytes());
+    poll_indexer_store(deps.storage, &PollStatus::Expired).save(&a_poll.id.to_be_bytes(), &true)?;
 
-    // Update poll status
-    a_poll.status = poll_status;
-    a_poll.total_balance_at_end_poll = Some(staked_weight);
-    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
+    close_creator_poll(deps.storage, &a_poll);
+    a_poll.status = PollStatus::Expired;
+    poll_store(deps.storage).save(&a_poll.id.to_be_bytes(), &a_poll)?;
 
     Ok(Response::new().add_messages(messages).add_attributes(vec![
-        ("action", "end_poll"),
-        ("poll_id", &poll_id.to_string()),
-        ("rejected_reason", rejected_reason),
-        ("passed", &passed.to_string()),
+        ("action", "expire_poll"),
+        ("poll_id", &a_poll.id.to_string()),
+        ("refund_amount", &refund_amount.to_string()),
     ]))
 }
 
@@ -465,7 +1359,7 @@ pub fn execute_poll(deps: DepsMut, env: Env, poll_id: u64) -> Result<Response, C
         return Err(ContractError::PollNotPassed {});
     }
 
-    if a_poll.end_height + config.timelock_period > env.block.height {
+    if !period_passed(&a_poll, &env, config.timelock_period, config.block_time) {
         return Err(ContractError::TimelockNotExpired {});
     }
 
@@ -496,9 +1390,27 @@ pub fn execute_poll_messages(
 
     let mut a_poll: Poll = poll_store(deps.storage).load(&poll_id.to_be_bytes())?;
 
+    // the denylist may have changed since the poll was created,
+    // failing here marks the poll as failed
+    let config: Config = config_read(deps.storage).load()?;
+    if let Some(execute_data) = &a_poll.execute_data {
+        check_execution_denylist(deps.as_ref(), &config, execute_data)?;
+    }
+
+    // poll messages run as gov, so they could call back here for this or
+    // any other poll; only a passed poll is executed, and only once
+    if a_poll.status != PollStatus::Passed {
+        return Err(ContractError::PollNotPassed {});
+    }
+
+    let mut state: State = state_store(deps.storage).load()?;
+    state.polls_awaiting_execution = state.polls_awaiting_execution.saturating_sub(1);
+    state_store(deps.storage).save(&state)?;
+
     poll_indexer_store(deps.storage, &PollStatus::Passed).remove(&poll_id.to_be_bytes());
     poll_indexer_store(deps.storage, &PollStatus::Executed).save(&poll_id.to_be_bytes(), &true)?;
 
+    close_creator_poll(deps.storage, &a_poll);
     a_poll.status = PollStatus::Executed;
     poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
 
@@ -527,9 +1439,17 @@ pub fn execute_poll_messages(
 pub fn fail_poll(deps: DepsMut, poll_id: u64) -> Result<Response, ContractError> {
     let mut a_poll: Poll = poll_store(deps.storage).load(&poll_id.to_be_bytes())?;
 
+    // the poll is still counted unless its execution state was kept
+    if a_poll.status == PollStatus::Passed {
+        let mut state: State = state_store(deps.storage).load()?;
+        state.polls_awaiting_execution = state.polls_awaiting_execution.saturating_sub(1);
+        state_store(deps.storage).save(&state)?;
+    }
+
     poll_indexer_store(deps.storage, &PollStatus::Passed).remove(&poll_id.to_be_bytes());
     poll_indexer_store(deps.storage, &PollStatus::Failed).save(&poll_id.to_be_bytes(), &true)?;
 
+    close_creator_poll(deps.storage, &a_poll);
     a_poll.status = PollStatus::Failed;
     poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
 
@@ -544,39 +1464,355 @@ pub fn snapshot_poll(deps: DepsMut, env: Env, poll_id: u64) -> Result<Response,
     let config: Config = config_read(deps.storage).load()?;
     let mut a_poll: Poll = poll_store(deps.storage).load(&poll_id.to_be_bytes())?;
 
-    if a_poll.status != PollStatus::InProgress {
-        return Err(ContractError::PollNotInProgress {});
+    if a_poll.status != PollStatus::InProgress {
+        return Err(ContractError::PollNotInProgress {});
+    }
+
+    let time_to_end = blocks_to_end(&a_poll, &env, config.block_time);
+
+    if time_to_end > config.snapshot_period {
+        return Err(ContractError::SnapshotHeight {});
+    }
+
+    if a_poll.staked_amount.is_some() {
+        return Err(ContractError::SnapshotAlreadyOccurred {});
+    }
+
+    // store the current staked amount for quorum calculation
+    let state: State = state_store(deps.storage).load()?;
+
+    let staked_amount = query_token_balance(
+        &deps.querier,
+        deps.api.addr_humanize(&config.anchor_token)?,
+        deps.api.addr_humanize(&state.contract_addr)?,
+    )?
+    .checked_sub(state.reserved_amount())?;
+
+    a_poll.staked_amount = Some(staked_amount);
+
+    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "snapshot_poll"),
+        attr("poll_id", poll_id.to_string().as_str()),
+        attr("staked_amount", staked_amount.to_string().as_str()),
+    ]))
+}
+
+/// SponsorPoll adds the sender's staked balance to the sponsorship of a pending poll,
+/// locking it until the poll is activated or expired.
+/// Once the sponsorship threshold is met, the voting period of the poll starts.
+pub fn sponsor_poll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: u64,
+) -> Result<Response, ContractError> {
+    let sender_address_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let config: Config = config_read(deps.storage).load()?;
+    let mut state: State = state_read(deps.storage).load()?;
+
+    let mut a_poll: Poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(poll) => poll,
+        None => return Err(ContractError::PollNotFound {}),
+    };
+
+    if a_poll.status != PollStatus::Pending {
+        return Err(ContractError::PollNotPending {});
+    }
+
+    if env.block.height > a_poll.end_height {
+        return Err(ContractError::SponsorshipPeriodExpired {});
+    }
+
+    if poll_sponsor_read(deps.storage, poll_id)
+        .may_load(sender_address_raw.as_slice())?
+        .is_some()
+    {
+        return Err(ContractError::AlreadySponsored {});
+    }
+
+    // sponsorship weight is the staked balance, same as the voting weight
+    let mut token_manager = bank_read(deps.storage)
+        .may_load(sender_address_raw.as_slice())?
+        .unwrap_or_default();
+    let total_balance = query_token_balance(
+        &deps.querier,
+        deps.api.addr_humanize(&config.anchor_token)?,
+        deps.api.addr_humanize(&state.contract_addr)?,
+    )?
+    .checked_sub(state.reserved_amount())?;
+
+    let weight = if state.total_share.is_zero() {
+        Uint128::zero()
+    } else {
+        token_manager
+            .share
+            .multiply_ratio(total_balance, state.total_share)
+    };
+
+    if weight.is_zero() {
+        return Err(ContractError::NothingStaked {});
+    }
+
+    poll_sponsor_store(deps.storage, poll_id).save(sender_address_raw.as_slice(), &weight)?;
+    a_poll.sponsor_weight += weight;
+
+    let activated = a_poll.sponsor_weight >= config.sponsorship_threshold;
+    if activated {
+        state.polls_in_progress += 1;
+        state_store(deps.storage).save(&state)?;
+
+        poll_indexer_store(deps.storage, &PollStatus::Pending).remove(&poll_id.to_be_bytes());
+        poll_indexer_store(deps.storage, &PollStatus::InProgress)
+            .save(&poll_id.to_be_bytes(), &true)?;
+
+        a_poll.status = PollStatus::InProgress;
+        a_poll.end_height = env.block.height + config.voting_period;
+        a_poll.end_time = env.block.time.seconds() + config.voting_period * config.block_time;
+    } else {
+        // the weight stays staked until the poll is activated or expired,
+        // so it cannot be withdrawn and sponsor again from another address
+        token_manager.sponsored_balance.push((poll_id, weight));
+        bank_store(deps.storage).save(sender_address_raw.as_slice(), &token_manager)?;
+    }
+
+    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "sponsor_poll"),
+        ("poll_id", poll_id.to_string().as_str()),
+        ("sponsor", info.sender.as_str()),
+        ("weight", weight.to_string().as_str()),
+        ("activated", activated.to_string().as_str()),
+    ]))
+}
+
+/*
+ * Amend the description and link of a poll before any votes are cast.
+ * Title and execute messages can never be amended.
+ */
+pub fn amend_poll(
+    deps: DepsMut,
+    info: MessageInfo,
+    poll_id: u64,
+    description: String,
+    link: Option<String>,
+) -> Result<Response, ContractError> {
+    validate_description(&description)?;
+    validate_link(&link)?;
+
+    let mut a_poll: Poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(poll) => poll,
+        None => return Err(ContractError::PollNotFound {}),
+    };
+
+    if a_poll.creator != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if a_poll.status != PollStatus::InProgress && a_poll.status != PollStatus::Pending {
+        return Err(ContractError::PollNotInProgress {});
+    }
+
+    if !(a_poll.yes_votes + a_poll.no_votes).is_zero() {
+        return Err(ContractError::PollAlreadyVoted {});
+    }
+
+    let old_description_hash = hex::encode(Sha256::digest(a_poll.description.as_bytes()));
+    let new_description_hash = hex::encode(Sha256::digest(description.as_bytes()));
+
+    a_poll.description = description;
+    a_poll.link = link;
+    a_poll.amended = true;
+    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "amend_poll"),
+        ("poll_id", poll_id.to_string().as_str()),
+        ("old_description_hash", old_description_hash.as_str()),
+        ("new_description_hash", new_description_hash.as_str()),
+    ]))
+}
+
+/// ArchivePoll is permissionless once a finished poll is older than `archive_after` blocks.
+/// Each call clears up to `limit` voters and sponsors, the last one stores the summary.
+pub fn archive_poll(
+    deps: DepsMut,
+    env: Env,
+    poll_id: u64,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config: Config = config_read(deps.storage).load()?;
+    let mut a_poll: Poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(poll) => poll,
+        None => return Err(poll_not_found(deps.as_ref(), poll_id)?),
+    };
+
+    match a_poll.status {
+        PollStatus::Rejected | PollStatus::Executed | PollStatus::Expired | PollStatus::Failed => {}
+        _ => return Err(ContractError::PollNotArchivable {}),
+    }
+
+    if !period_passed(&a_poll, &env, config.archive_after, config.block_time) {
+        return Err(ContractError::PollNotArchivable {});
+    }
+
+    let limit = limit
+        .map(|limit| limit as usize)
+        .unwrap_or(MAX_UNLOCK_VOTERS)
+        .min(MAX_UNLOCK_VOTERS);
+    // the vote tree is removed with the poll, so the votes left are not committed to it
+    a_poll.unlock_pending =
+        unlock_votes_for_poll(deps.storage, deps.api, &mut a_poll, limit, false)?.pending;
+    let archive_pending =
+        a_poll.unlock_pending || remove_poll_records(deps.storage, poll_id, limit)?;
+
+    if archive_pending {
+        poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
+    } else {
+        let execute_hash = match a_poll.execute_data {
+            Some(execute_data) => {
+                let execute_msgs = execute_data
+                    .into_iter()
+                    .map(|msg| {
+                        Ok(PollExecuteMsg {
+                            order: msg.order,
+                            contract: deps.api.addr_humanize(&msg.contract)?.to_string(),
+                            msg: msg.msg,
+                        })
+                    })
+                    .collect::<StdResult<Vec<PollExecuteMsg>>>()?;
+                Some(hex::encode(Sha256::digest(&to_vec(&execute_msgs)?)))
+            }
+            None => None,
+        };
+
+        poll_indexer_store(deps.storage, &a_poll.status).remove(&poll_id.to_be_bytes());
+        poll_store(deps.storage).remove(&poll_id.to_be_bytes());
+        archived_poll_store(deps.storage).save(
+            &poll_id.to_be_bytes(),
+            &ArchivedPoll {
+                id: a_poll.id,
+                status: a_poll.status,
+                yes_votes: a_poll.yes_votes,
+                no_votes: a_poll.no_votes,
+                end_height: a_poll.end_height,
+                execute_hash,
+                last_vote_height: a_poll.last_vote_height,
+            },
+        )?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "archive_poll"),
+        ("poll_id", poll_id.to_string().as_str()),
+        ("archive_pending", archive_pending.to_string().as_str()),
+    ]))
+}
+
+/// remove_poll_records removes up to `limit` sponsors, vote leaves and vote nodes of a poll.
+/// Returns true when records are left.
+fn remove_poll_records(storage: &mut dyn Storage, poll_id: u64, limit: usize) -> StdResult<bool> {
+    let sponsors = read_keys(poll_sponsor_read(storage, poll_id), limit)?;
+    for sponsor in sponsors.iter().take(limit) {
+        poll_sponsor_store(storage, poll_id).remove(sponsor);
+    }
+
+    let vote_leaves = read_keys(vote_leaf_read(storage, poll_id), limit)?;
+    for voter in vote_leaves.iter().take(limit) {
+        vote_leaf_store(storage, poll_id).remove(voter);
+    }
+
+    let vote_nodes = read_keys(vote_node_read(storage, poll_id), limit)?;
+    for node in vote_nodes.iter().take(limit) {
+        vote_node_store(storage, poll_id).remove(node);
+    }
+
+    Ok(sponsors.len() > limit || vote_leaves.len() > limit || vote_nodes.len() > limit)
+}
+
+/// read_keys returns up to `limit` + 1 keys of a bucket
+fn read_keys<T: Serialize + DeserializeOwned>(
+    bucket: ReadonlyBucket<T>,
+    limit: usize,
+) -> StdResult<Vec<Vec<u8>>> {
+    bucket
+        .range(None, None, Order::Ascending)
+        .take(limit + 1)
+        .map(|item| item.map(|(k, _)| k))
+        .collect()
+}
+
+/// poll_not_found tells apart archived polls from polls which never existed
+fn poll_not_found(deps: Deps, poll_id: u64) -> StdResult<ContractError> {
+    match archived_poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(_) => Ok(ContractError::PollArchived {}),
+        None => Ok(ContractError::PollNotFound {}),
+    }
+}
+
+/// close_creator_poll stops counting a poll which reached a final status
+/// against the open polls of its creator
+fn close_creator_poll(storage: &mut dyn Storage, a_poll: &Poll) {
+    creator_poll_store(storage, &a_poll.creator).remove(&a_poll.id.to_be_bytes());
+}
+
+/// voting_clock returns the current position and the end of voting
+/// on the clock the poll was created with
+fn voting_clock(a_poll: &Poll, env: &Env) -> (u64, u64) {
+    match a_poll.clock {
+        PollClock::Height => (env.block.height, a_poll.end_height),
+        PollClock::Time => (env.block.time.seconds(), a_poll.end_time),
     }
+}
 
-    let time_to_end = a_poll.end_height - env.block.height;
+/// period_passed returns whether `blocks` have passed since the end of voting,
+/// converted to seconds from the end time on polls ending by time
+fn period_passed(a_poll: &Poll, env: &Env, blocks: u64, block_time: u64) -> bool {
+    let (now, voting_end) = voting_clock(a_poll, env);
+    let period = match a_poll.clock {
+        PollClock::Height => blocks,
+        PollClock::Time => blocks * block_time,
+    };
+    now >= voting_end + period
+}
 
-    if time_to_end > config.snapshot_period {
-        return Err(ContractError::SnapshotHeight {});
+/// blocks_to_end returns the blocks left until the end of voting,
+/// estimated from the block time on polls ending by time
+fn blocks_to_end(a_poll: &Poll, env: &Env, block_time: u64) -> u64 {
+    match a_poll.clock {
+        PollClock::Height => a_poll.end_height.saturating_sub(env.block.height),
+        PollClock::Time => {
+            a_poll.end_time.saturating_sub(env.block.time.seconds()) / block_time.max(1)
+        }
     }
+}
 
-    if a_poll.staked_amount.is_some() {
-        return Err(ContractError::SnapshotAlreadyOccurred {});
+/// vote_weight returns how much a vote of `amount` counts towards the threshold
+fn vote_weight(weight_mode: &WeightMode, amount: Uint128) -> Uint128 {
+    match weight_mode {
+        WeightMode::Linear => amount,
+        WeightMode::Capped { max_weight } => std::cmp::min(amount, *max_weight),
+        WeightMode::SquareRoot => Uint128::from(integer_sqrt(amount.u128())),
     }
+}
 
-    // store the current staked amount for quorum calculation
-    let state: State = state_store(deps.storage).load()?;
-
-    let staked_amount = query_token_balance(
-        &deps.querier,
-        deps.api.addr_humanize(&config.anchor_token)?,
-        deps.api.addr_humanize(&state.contract_addr)?,
-    )?
-    .checked_sub(state.total_deposit)?;
-
-    a_poll.staked_amount = Some(staked_amount);
+/// integer_sqrt returns the square root of `value` rounded down
+fn integer_sqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
 
-    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
+    // Newton's method, decreasing from above until it settles on the floor
+    let mut x = value;
+    let mut y = value / 2 + 1;
+    while y < x {
+        x = y;
+        y = (x + value / x) / 2;
+    }
 
-    Ok(Response::new().add_attributes(vec![
-        attr("action", "snapshot_poll"),
-        attr("poll_id", poll_id.to_string().as_str()),
-        attr("staked_amount", staked_amount.to_string().as_str()),
-    ]))
+    x
 }
 
 pub fn cast_vote(
@@ -588,80 +1824,271 @@ pub fn cast_vote(
     amount: Uint128,
 ) -> Result<Response, ContractError> {
     let sender_address_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
-    let config = config_read(deps.storage).load()?;
-    let state = state_read(deps.storage).load()?;
+    let config: Config = config_read(deps.storage).load()?;
+    let state: State = state_read(deps.storage).load()?;
+
+    let valid_vote = check_vote(
+        deps.as_ref(),
+        &env,
+        &config,
+        &state,
+        &sender_address_raw,
+        poll_id,
+        amount,
+    )?;
+    let end_height = apply_vote(
+        deps,
+        &env,
+        &config,
+        &state,
+        &sender_address_raw,
+        valid_vote,
+        vote.clone(),
+    )?;
+
+    let mut response = Response::new().add_attributes(vec![
+        ("action", "cast_vote"),
+        ("poll_id", poll_id.to_string().as_str()),
+        ("amount", amount.to_string().as_str()),
+        ("voter", info.sender.as_str()),
+        ("vote_option", vote.to_string().as_str()),
+    ]);
+
+    if let Some(end_height) = end_height {
+        response = response.add_attribute("end_height", end_height.to_string());
+    }
+
+    Ok(response)
+}
+
+/// ValidVote is a vote which passed every check, ready to be applied
+struct ValidVote {
+    poll: Poll,
+    amount: Uint128,
+    total_balance: Uint128, // staked balance, for the quorum snapshot
+}
+
+/// check_vote runs every check of a vote without writing anything
+fn check_vote(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    state: &State,
+    voter: &CanonicalAddr,
+    poll_id: u64,
+    amount: Uint128,
+) -> Result<ValidVote, ContractError> {
     if poll_id == 0 || state.poll_count < poll_id {
         return Err(ContractError::PollNotFound {});
     }
 
-    let mut a_poll: Poll = poll_store(deps.storage).load(&poll_id.to_be_bytes())?;
-    if a_poll.status != PollStatus::InProgress || env.block.height > a_poll.end_height {
+    let a_poll: Poll = poll_read(deps.storage).load(&poll_id.to_be_bytes())?;
+    let (now, voting_end) = voting_clock(&a_poll, env);
+    if a_poll.status != PollStatus::InProgress || now > voting_end {
         return Err(ContractError::PollNotInProgress {});
     }
 
     // Check the voter already has a vote on the poll
     if poll_voter_read(deps.storage, poll_id)
-        .load(sender_address_raw.as_slice())
+        .load(voter.as_slice())
         .is_ok()
     {
         return Err(ContractError::AlreadyVoted {});
     }
 
-    let key = &sender_address_raw.as_slice();
-    let mut token_manager = bank_read(deps.storage).may_load(key)?.unwrap_or_default();
+    let token_manager = bank_read(deps.storage)
+        .may_load(voter.as_slice())?
+        .unwrap_or_default();
 
     // convert share to amount
-    let total_share = state.total_share;
     let total_balance = query_token_balance(
         &deps.querier,
         deps.api.addr_humanize(&config.anchor_token)?,
         deps.api.addr_humanize(&state.contract_addr)?,
     )?
-    .checked_sub(state.total_deposit)?;
+    .checked_sub(state.reserved_amount())?;
 
     if token_manager
         .share
-        .multiply_ratio(total_balance, total_share)
+        .multiply_ratio(total_balance, state.total_share)
         < amount
     {
         return Err(ContractError::InsufficientStaked {});
     }
 
+    Ok(ValidVote {
+        poll: a_poll,
+        amount,
+        total_balance,
+    })
+}
+
+/// apply_vote writes a checked vote to the poll, the voter and the epoch statistics.
+/// Returns the new end height when the vote extended the voting period.
+fn apply_vote(
+    mut deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    state: &State,
+    voter: &CanonicalAddr,
+    valid_vote: ValidVote,
+    vote: VoteOption,
+) -> Result<Option<u64>, ContractError> {
+    let ValidVote {
+        poll: mut a_poll,
+        amount,
+        total_balance,
+    } = valid_vote;
+    let poll_id = a_poll.id;
+
+    record_epoch_activity(
+        deps.branch(),
+        env,
+        Uint128::zero(),
+        EpochActivity::Vote {
+            voter,
+            vote: &vote,
+            amount,
+        },
+    )?;
+
+    // only a vote close to the end of a poll not extended yet can extend it,
+    // the tally before the vote is only needed then
+    let time_to_end = blocks_to_end(&a_poll, env, config.block_time);
+    let may_extend = config.late_quorum_extension > 0
+        && !a_poll.extended
+        && time_to_end < config.late_quorum_window;
+    let quorum_reached_before =
+        may_extend && tally_poll(deps.as_ref(), config, state, &a_poll)?.quorum_reached;
+
     // update tally info
+    let weight = vote_weight(&a_poll.weight_mode, amount);
     if VoteOption::Yes == vote {
         a_poll.yes_votes += amount;
+        a_poll.weighted_yes_votes += weight;
     } else {
         a_poll.no_votes += amount;
+        a_poll.weighted_no_votes += weight;
+    }
+
+    // polls created before the buckets have no voters to count
+    let bucket = a_poll
+        .weight_buckets
+        .iter()
+        .filter(|boundary| amount >= **boundary)
+        .count();
+    if let Some(voters) = a_poll.weight_bucket_voters.get_mut(bucket) {
+        *voters += 1;
     }
+    a_poll.last_vote_height = env.block.height;
 
     let vote_info = VoterInfo {
         vote,
         balance: amount,
     };
+    let mut token_manager = bank_read(deps.storage)
+        .may_load(voter.as_slice())?
+        .unwrap_or_default();
     token_manager
         .locked_balance
         .push((poll_id, vote_info.clone()));
-    bank_store(deps.storage).save(key, &token_manager)?;
+    bank_store(deps.storage).save(voter.as_slice(), &token_manager)?;
 
     // store poll voter && and update poll data
-    poll_voter_store(deps.storage, poll_id).save(sender_address_raw.as_slice(), &vote_info)?;
+    poll_voter_store(deps.storage, poll_id).save(voter.as_slice(), &vote_info)?;
 
     // processing snapshot
-    let time_to_end = a_poll.end_height - env.block.height;
-
     if time_to_end < config.snapshot_period && a_poll.staked_amount.is_none() {
         a_poll.staked_amount = Some(total_balance);
     }
 
+    // extend voting once when this vote reaches quorum close to the end,
+    // so the other side has time to respond
+    let extend = may_extend
+        && !quorum_reached_before
+        && tally_poll(deps.as_ref(), config, state, &a_poll)?.quorum_reached;
+    if extend {
+        a_poll.extended = true;
+        a_poll.end_height += config.late_quorum_extension;
+        a_poll.end_time += config.late_quorum_extension * config.block_time;
+    }
+
     poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
 
-    Ok(Response::new().add_attributes(vec![
-        ("action", "cast_vote"),
-        ("poll_id", poll_id.to_string().as_str()),
-        ("amount", amount.to_string().as_str()),
-        ("voter", info.sender.as_str()),
-        ("vote_option", vote_info.vote.to_string().as_str()),
-    ]))
+    Ok(if extend {
+        Some(a_poll.end_height)
+    } else {
+        None
+    })
+}
+
+/// cast_votes checks every vote before applying any, so a vote skipped by a
+/// non-atomic batch leaves nothing behind. In an atomic batch the first failing
+/// vote fails the transaction, otherwise it is skipped and its error reported.
+pub fn cast_votes(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    votes: Vec<PollVoteMsg>,
+) -> Result<Response, ContractError> {
+    if votes.is_empty() {
+        return Err(ContractError::DataShouldBeGiven {});
+    }
+    if votes.len() > MAX_BATCH_VOTES {
+        return Err(ContractError::TooManyVotes(MAX_BATCH_VOTES));
+    }
+
+    let sender_address_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let config: Config = config_read(deps.storage).load()?;
+    let state: State = state_read(deps.storage).load()?;
+
+    let mut checked = vec![];
+    for (i, vote) in votes.iter().enumerate() {
+        // a poll voted twice in the batch counts as voted for the second vote
+        let result = if votes[..i].iter().any(|other| other.poll_id == vote.poll_id) {
+            Err(ContractError::AlreadyVoted {})
+        } else {
+            check_vote(
+                deps.as_ref(),
+                &env,
+                &config,
+                &state,
+                &sender_address_raw,
+                vote.poll_id,
+                vote.amount,
+            )
+        };
+        match result {
+            Err(err) if config.atomic_batch => return Err(err),
+            result => checked.push(result),
+        }
+    }
+
+    let mut attributes = vec![
+        attr("action", "cast_votes"),
+        attr("voter", info.sender.as_str()),
+    ];
+    for (vote, valid_vote) in votes.into_iter().zip(checked) {
+        let result = match valid_vote {
+            Ok(valid_vote) => {
+                apply_vote(
+                    deps.branch(),
+                    &env,
+                    &config,
+                    &state,
+                    &sender_address_raw,
+                    valid_vote,
+                    vote.vote.clone(),
+                )?;
+                vote.vote.to_string()
+            }
+            Err(err) => format!("failed: {}", err),
+        };
+        attributes.push(attr("poll_id", vote.poll_id.to_string()));
+        attributes.push(attr("result", result));
+    }
+
+    Ok(Response::new().add_attributes(attributes))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -669,8 +2096,13 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractErr
     match msg {
         QueryMsg::Config {} => Ok(to_binary(&query_config(deps)?)?),
         QueryMsg::State {} => Ok(to_binary(&query_state(deps)?)?),
+        QueryMsg::ProtocolAddresses {} => Ok(to_binary(&query_protocol_addresses(deps)?)?),
         QueryMsg::Staker { address } => Ok(to_binary(&query_staker(deps, address)?)?),
+        QueryMsg::WithdrawStatus { address } => {
+            Ok(to_binary(&query_withdraw_status(deps, address)?)?)
+        }
         QueryMsg::Poll { poll_id } => Ok(to_binary(&query_poll(deps, poll_id)?)?),
+        QueryMsg::ArchivedPoll { poll_id } => Ok(to_binary(&query_archived_poll(deps, poll_id)?)?),
         QueryMsg::Polls {
             filter,
             start_after,
@@ -695,6 +2127,47 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractErr
             limit,
             order_by,
         )?)?),
+        QueryMsg::StakingMetrics {} => Ok(to_binary(&query_staking_metrics(deps)?)?),
+        QueryMsg::PollExecuteMsgs { poll_id } => {
+            Ok(to_binary(&query_poll_execute_msgs(deps, poll_id)?)?)
+        }
+        QueryMsg::SimulatePollExecution { poll_id } => {
+            Ok(to_binary(&simulate_poll_execution(deps, poll_id)?)?)
+        }
+        QueryMsg::EpochStats { start_after, limit } => {
+            Ok(to_binary(&query_epoch_stats(deps, start_after, limit)?)?)
+        }
+        QueryMsg::ShareRateHistory { limit } => {
+            Ok(to_binary(&query_share_rate_history(deps, limit)?)?)
+        }
+        QueryMsg::StakerRewards { address } => {
+            Ok(to_binary(&query_staker_rewards(deps, address)?)?)
+        }
+        QueryMsg::PollVoteProof { poll_id, voter } => {
+            Ok(to_binary(&query_poll_vote_proof(deps, poll_id, voter)?)?)
+        }
+        QueryMsg::PollProgress { poll_id } => Ok(to_binary(&query_poll_progress(deps, poll_id)?)?),
+        QueryMsg::ConfigHistory { start_after, limit } => {
+            Ok(to_binary(&query_config_history(deps, start_after, limit)?)?)
+        }
+        QueryMsg::PollWeightDistribution { poll_id } => {
+            Ok(to_binary(&query_poll_weight_distribution(deps, poll_id)?)?)
+        }
+        QueryMsg::PollTallyDigest { poll_ids } => {
+            Ok(to_binary(&query_poll_tally_digest(deps, poll_ids)?)?)
+        }
+        QueryMsg::Sponsors {
+            poll_id,
+            start_after,
+            limit,
+            order_by,
+        } => Ok(to_binary(&query_sponsors(
+            deps,
+            poll_id,
+            start_after,
+            limit,
+            order_by,
+        )?)?),
     }
 }
 
@@ -709,22 +2182,83 @@ fn query_config(deps: Deps) -> Result<ConfigResponse, ContractError> {
         timelock_period: config.timelock_period,
         proposal_deposit: config.proposal_deposit,
         snapshot_period: config.snapshot_period,
+        sponsorship_threshold: config.sponsorship_threshold,
+        sponsorship_period: config.sponsorship_period,
+        max_execute_msg_size: config.max_execute_msg_size,
+        max_execute_msgs: config.max_execute_msgs,
+        min_stake_amount: config.min_stake_amount,
+        stake_depositors: config
+            .stake_depositors
+            .iter()
+            .map(|depositor| Ok(deps.api.addr_humanize(depositor)?.to_string()))
+            .collect::<StdResult<Vec<String>>>()?,
+        max_spend_per_poll: config.max_spend_per_poll,
+        archive_after: config.archive_after,
+        poll_clock: config.poll_clock,
+        block_time: config.block_time,
+        late_quorum_window: config.late_quorum_window,
+        late_quorum_extension: config.late_quorum_extension,
+        participation_reward_ratio: config.participation_reward_ratio,
+        execution_denylist: config
+            .execution_denylist
+            .iter()
+            .map(|contract| Ok(deps.api.addr_humanize(contract)?.to_string()))
+            .collect::<StdResult<Vec<String>>>()?,
+        epoch_length: config.epoch_length,
+        max_open_polls_per_creator: config.max_open_polls_per_creator,
+        min_creator_stake: config.min_creator_stake,
+        vote_weight_buckets: config.vote_weight_buckets,
+        resubmit_window: config.resubmit_window,
+        resubmit_deposit: config.resubmit_deposit,
+        max_resubmit_depth: config.max_resubmit_depth,
+        deposit_cap: config.deposit_cap,
+        atomic_batch: config.atomic_batch,
+        expired_deposit_refund_ratio: config.expired_deposit_refund_ratio,
+    })
+}
+
+fn query_protocol_addresses(deps: Deps) -> Result<ProtocolAddressesResponse, ContractError> {
+    let config: Config = config_read(deps.storage).load()?;
+    let registry = config.protocol_addresses;
+    let humanize = |address: Option<CanonicalAddr>| -> StdResult<Option<String>> {
+        address
+            .map(|address| Ok(deps.api.addr_humanize(&address)?.to_string()))
+            .transpose()
+    };
+
+    Ok(ProtocolAddressesResponse {
+        anchor_token: deps.api.addr_humanize(&config.anchor_token)?.to_string(),
+        community_fund: humanize(registry.community_fund)?,
+        collector: humanize(registry.collector)?,
+        voting_escrow: humanize(registry.voting_escrow)?,
+        gauge_controller: humanize(registry.gauge_controller)?,
     })
 }
 
 fn query_state(deps: Deps) -> Result<StateResponse, ContractError> {
     let state: State = state_read(deps.storage).load()?;
+    let polls_awaiting_execution = read_poll_ids(
+        deps.storage,
+        &PollStatus::Passed,
+        MAX_POLLS_AWAITING_EXECUTION as usize,
+    )?;
+
     Ok(StateResponse {
         poll_count: state.poll_count,
         total_share: state.total_share,
         total_deposit: state.total_deposit,
+        polls_in_progress: state.polls_in_progress,
+        polls_awaiting_execution,
+        truncated: state.polls_awaiting_execution > MAX_POLLS_AWAITING_EXECUTION,
+        participation_pool: state.participation_pool,
+        participation_rewards: state.participation_rewards,
     })
 }
 
 fn query_poll(deps: Deps, poll_id: u64) -> Result<PollResponse, ContractError> {
     let poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
         Some(poll) => Some(poll),
-        None => return Err(ContractError::PollNotFound {}),
+        None => return Err(poll_not_found(deps, poll_id)?),
     }
     .unwrap();
 
@@ -756,9 +2290,345 @@ fn query_poll(deps: Deps, poll_id: u64) -> Result<PollResponse, ContractError> {
         no_votes: poll.no_votes,
         staked_amount: poll.staked_amount,
         total_balance_at_end_poll: poll.total_balance_at_end_poll,
+        amended: poll.amended,
+        weight_mode: poll.weight_mode,
+        end_time: poll.end_time,
+        clock: poll.clock,
+        vote_root: poll.vote_root,
+        extended: poll.extended,
+        resubmitted_from: poll.resubmitted_from,
+        resubmitted_as: poll.resubmitted_as,
+    })
+}
+
+fn query_archived_poll(deps: Deps, poll_id: u64) -> Result<ArchivedPollResponse, ContractError> {
+    let archived_poll = match archived_poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(archived_poll) => archived_poll,
+        None => return Err(ContractError::PollNotFound {}),
+    };
+
+    Ok(ArchivedPollResponse {
+        id: archived_poll.id,
+        status: archived_poll.status,
+        end_height: archived_poll.end_height,
+        yes_votes: archived_poll.yes_votes,
+        no_votes: archived_poll.no_votes,
+        execute_hash: archived_poll.execute_hash,
+    })
+}
+
+fn query_poll_execute_msgs(
+    deps: Deps,
+    poll_id: u64,
+) -> Result<PollExecuteMsgsResponse, ContractError> {
+    let poll: Poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(poll) => poll,
+        None => return Err(ContractError::PollNotFound {}),
+    };
+
+    let config: Config = config_read(deps.storage).load()?;
+    let state: State = state_read(deps.storage).load()?;
+
+    let mut execute_msgs = poll.execute_data.unwrap_or_default();
+    execute_msgs.sort();
+
+    let execute_msgs: StdResult<Vec<PollExecuteMsgsResponseItem>> = execute_msgs
+        .into_iter()
+        .map(|execute_data| {
+            let decoded_msg = decode_known_msg(
+                &config,
+                &state.contract_addr,
+                &execute_data.contract,
+                &execute_data.msg,
+            );
+
+            Ok(PollExecuteMsgsResponseItem {
+                order: execute_data.order,
+                contract: deps.api.addr_humanize(&execute_data.contract)?.to_string(),
+                msg: execute_data.msg,
+                decoded_msg,
+            })
+        })
+        .collect();
+
+    Ok(PollExecuteMsgsResponse {
+        execute_msgs: execute_msgs?,
+    })
+}
+
+/// decode_known_msg returns the JSON form of a message to the gov contract,
+/// the ANC token or the community fund
+fn decode_known_msg(
+    config: &Config,
+    gov_contract: &CanonicalAddr,
+    contract: &CanonicalAddr,
+    msg: &Binary,
+) -> Option<String> {
+    if contract == gov_contract {
+        decode_msg::<ExecuteMsg>(msg)
+    } else if *contract == config.anchor_token {
+        decode_msg::<Cw20ExecuteMsg>(msg)
+    } else if Some(contract) == config.protocol_addresses.community_fund.as_ref() {
+        decode_msg::<CommunityExecuteMsg>(msg)
+    } else {
+        None
+    }
+}
+
+/// decode_msg returns the JSON form of a message
+/// if it parses as the given message type
+fn decode_msg<T: DeserializeOwned + Serialize>(msg: &Binary) -> Option<String> {
+    let decoded: T = from_binary(msg).ok()?;
+    String::from_utf8(to_vec(&decoded).ok()?).ok()
+}
+
+/// simulate_poll_execution runs the checks that need no state change on every
+/// execute message of a poll. Only messages to the gov contract, the ANC token
+/// and the community fund can be checked.
+fn simulate_poll_execution(
+    deps: Deps,
+    poll_id: u64,
+) -> Result<SimulatePollExecutionResponse, ContractError> {
+    let poll: Poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(poll) => poll,
+        None => return Err(poll_not_found(deps, poll_id)?),
+    };
+
+    let config: Config = config_read(deps.storage).load()?;
+    let state: State = state_read(deps.storage).load()?;
+
+    let mut execute_msgs = poll.execute_data.unwrap_or_default();
+    execute_msgs.sort();
+
+    let execute_msgs = execute_msgs
+        .into_iter()
+        .map(|execute_data| {
+            let (contract, result) = match deps.api.addr_humanize(&execute_data.contract) {
+                Ok(contract) => {
+                    let result = check_execute_msg(deps, &config, &state, &execute_data);
+                    (contract.to_string(), result)
+                }
+                Err(_) => (
+                    execute_data.contract.to_string(),
+                    Err("invalid contract address".to_string()),
+                ),
+            };
+
+            let (verdict, reason) = match result {
+                Ok(ExecutionVerdict::Unknown) => (
+                    ExecutionVerdict::Unknown,
+                    Some("not a known protocol contract".to_string()),
+                ),
+                Ok(verdict) => (verdict, None),
+                Err(reason) => (ExecutionVerdict::Invalid, Some(reason)),
+            };
+
+            SimulatePollExecutionResponseItem {
+                order: execute_data.order,
+                contract,
+                verdict,
+                reason,
+            }
+        })
+        .collect();
+
+    Ok(SimulatePollExecutionResponse { execute_msgs })
+}
+
+/// check_execute_msg returns why a message would fail, if it targets a known contract
+fn check_execute_msg(
+    deps: Deps,
+    config: &Config,
+    state: &State,
+    execute_data: &ExecuteData,
+) -> Result<ExecutionVerdict, String> {
+    let not_owner = || "the gov contract is not the owner of the target".to_string();
+
+    if execute_data.contract == state.contract_addr {
+        match parse_msg::<ExecuteMsg>(&execute_data.msg)? {
+            ExecuteMsg::UpdateConfig(..) | ExecuteMsg::RegisterRewardToken { .. }
+                if config.owner != state.contract_addr =>
+            {
+                Err(not_owner())
+            }
+            _ => Ok(ExecutionVerdict::Valid),
+        }
+    } else if execute_data.contract == config.anchor_token {
+        match parse_msg::<Cw20ExecuteMsg>(&execute_data.msg)? {
+            Cw20ExecuteMsg::Transfer { recipient, .. } => validate_addr(deps, &recipient),
+            Cw20ExecuteMsg::Send { contract, .. } => validate_addr(deps, &contract),
+            _ => Ok(ExecutionVerdict::Valid),
+        }
+    } else if Some(&execute_data.contract) == config.protocol_addresses.community_fund.as_ref() {
+        let msg = parse_msg::<CommunityExecuteMsg>(&execute_data.msg)?;
+        let community_config: CommunityConfigResponse = deps
+            .querier
+            .query_wasm_smart(
+                deps.api
+                    .addr_humanize(&execute_data.contract)
+                    .map_err(|err| err.to_string())?,
+                &CommunityQueryMsg::Config {},
+            )
+            .map_err(|err| err.to_string())?;
+
+        let gov_contract = deps
+            .api
+            .addr_humanize(&state.contract_addr)
+            .map_err(|err| err.to_string())?;
+        if community_config.gov_contract != gov_contract.as_str() {
+            return Err(not_owner());
+        }
+
+        match msg {
+            CommunityExecuteMsg::Spend { recipient, amount } => {
+                if amount > community_config.spend_limit {
+                    return Err("amount exceeds the spend limit".to_string());
+                }
+                validate_addr(deps, &recipient)
+            }
+            CommunityExecuteMsg::UpdateConfig { .. } => Ok(ExecutionVerdict::Valid),
+        }
+    } else {
+        Ok(ExecutionVerdict::Unknown)
+    }
+}
+
+fn parse_msg<T: DeserializeOwned>(msg: &Binary) -> Result<T, String> {
+    from_binary(msg).map_err(|err| format!("does not match the target ExecuteMsg: {}", err))
+}
+
+fn validate_addr(deps: Deps, addr: &str) -> Result<ExecutionVerdict, String> {
+    deps.api
+        .addr_validate(addr)
+        .map(|_| ExecutionVerdict::Valid)
+        .map_err(|_| format!("invalid address {}", addr))
+}
+
+fn query_poll_vote_proof(
+    deps: Deps,
+    poll_id: u64,
+    voter: String,
+) -> Result<PollVoteProofResponse, ContractError> {
+    let poll: Poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(poll) => poll,
+        None => return Err(poll_not_found(deps, poll_id)?),
+    };
+
+    let root = match poll.vote_root {
+        Some(root) => root,
+        None => return Err(ContractError::VoteRootNotAvailable {}),
+    };
+
+    let voter_raw = deps.api.addr_canonicalize(&voter)?;
+    let vote_leaf: VoteLeaf =
+        match vote_leaf_read(deps.storage, poll_id).may_load(voter_raw.as_slice())? {
+            Some(vote_leaf) => vote_leaf,
+            None => return Err(ContractError::NotVoted {}),
+        };
+
+    let voter = deps.api.addr_humanize(&voter_raw)?.to_string();
+    let leaf = leaf_hash(&voter, &vote_leaf.vote, vote_leaf.balance);
+    let proof = merkle::proof(deps.storage, poll_id, poll.vote_leaf_count, vote_leaf.index)?;
+
+    Ok(PollVoteProofResponse {
+        voter,
+        vote: vote_leaf.vote,
+        balance: vote_leaf.balance,
+        leaf: hex::encode(leaf),
+        proof: proof.iter().map(hex::encode).collect(),
+        root,
+    })
+}
+
+fn query_poll_progress(deps: Deps, poll_id: u64) -> Result<PollProgressResponse, ContractError> {
+    let poll: Poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(poll) => poll,
+        None => return Err(ContractError::PollNotFound {}),
+    };
+
+    if poll.status != PollStatus::InProgress {
+        return Err(ContractError::PollNotInProgress {});
+    }
+
+    let config: Config = config_read(deps.storage).load()?;
+    let state: State = state_read(deps.storage).load()?;
+    let tally = tally_poll(deps, &config, &state, &poll)?;
+
+    Ok(PollProgressResponse {
+        quorum_required: config.quorum,
+        quorum_current: tally.quorum,
+        threshold_required: config.threshold,
+        yes: poll.yes_votes,
+        no: poll.no_votes,
+        projected_status: tally.status,
     })
 }
 
+fn query_poll_weight_distribution(
+    deps: Deps,
+    poll_id: u64,
+) -> Result<PollWeightDistributionResponse, ContractError> {
+    let poll: Poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(poll) => poll,
+        None => return Err(poll_not_found(deps, poll_id)?),
+    };
+
+    let buckets = poll
+        .weight_bucket_voters
+        .iter()
+        .enumerate()
+        .map(|(i, voters)| WeightBucketResponse {
+            min: if i == 0 {
+                Uint128::zero()
+            } else {
+                poll.weight_buckets[i - 1]
+            },
+            max: poll.weight_buckets.get(i).copied(),
+            voters: *voters,
+        })
+        .collect();
+
+    Ok(PollWeightDistributionResponse { buckets })
+}
+
+fn query_poll_tally_digest(
+    deps: Deps,
+    poll_ids: Vec<u64>,
+) -> Result<PollTallyDigestResponse, ContractError> {
+    if poll_ids.len() > MAX_TALLY_DIGEST_POLLS {
+        return Err(ContractError::TooManyPolls(MAX_TALLY_DIGEST_POLLS));
+    }
+
+    let digests = poll_ids
+        .into_iter()
+        .map(|poll_id| {
+            let key = poll_id.to_be_bytes();
+            if let Some(poll) = poll_read(deps.storage).may_load(&key)? {
+                return Ok(PollTallyDigestResponseItem {
+                    poll_id,
+                    yes: poll.yes_votes,
+                    no: poll.no_votes,
+                    status: poll.status,
+                    last_vote_height: poll.last_vote_height,
+                });
+            }
+
+            match archived_poll_read(deps.storage).may_load(&key)? {
+                Some(archived_poll) => Ok(PollTallyDigestResponseItem {
+                    poll_id,
+                    yes: archived_poll.yes_votes,
+                    no: archived_poll.no_votes,
+                    status: archived_poll.status,
+                    last_vote_height: archived_poll.last_vote_height,
+                }),
+                None => Err(ContractError::PollNotFound {}),
+            }
+        })
+        .collect::<Result<Vec<PollTallyDigestResponseItem>, ContractError>>()?;
+
+    Ok(PollTallyDigestResponse { digests })
+}
+
 fn query_polls(
     deps: Deps,
     filter: Option<PollStatus>,
@@ -799,6 +2669,14 @@ fn query_polls(
                 no_votes: poll.no_votes,
                 staked_amount: poll.staked_amount,
                 total_balance_at_end_poll: poll.total_balance_at_end_poll,
+                amended: poll.amended,
+                weight_mode: poll.weight_mode.clone(),
+                end_time: poll.end_time,
+                clock: poll.clock.clone(),
+                vote_root: poll.vote_root.clone(),
+                extended: poll.extended,
+                resubmitted_from: poll.resubmitted_from,
+                resubmitted_as: poll.resubmitted_as,
             })
         })
         .collect();
@@ -817,7 +2695,7 @@ fn query_voters(
 ) -> Result<VotersResponse, ContractError> {
     let poll: Poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
         Some(poll) => Some(poll),
-        None => return Err(ContractError::PollNotFound {}),
+        None => return Err(poll_not_found(deps, poll_id)?),
     }
     .unwrap();
 
@@ -850,3 +2728,55 @@ fn query_voters(
         voters: voters_response?,
     })
 }
+
+fn query_sponsors(
+    deps: Deps,
+    poll_id: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+    order_by: Option<OrderBy>,
+) -> Result<SponsorsResponse, ContractError> {
+    if poll_read(deps.storage)
+        .may_load(&poll_id.to_be_bytes())?
+        .is_none()
+    {
+        return Err(poll_not_found(deps, poll_id)?);
+    }
+
+    let start_after = if let Some(start_after) = start_after {
+        Some(deps.api.addr_canonicalize(&start_after)?)
+    } else {
+        None
+    };
+    let sponsors = read_poll_sponsors(deps.storage, poll_id, start_after, limit, order_by)?;
+
+    let sponsors_response: StdResult<Vec<SponsorsResponseItem>> = sponsors
+        .iter()
+        .map(|(sponsor, weight)| {
+            Ok(SponsorsResponseItem {
+                sponsor: deps.api.addr_humanize(sponsor)?.to_string(),
+                weight: *weight,
+            })
+        })
+        .collect();
+
+    Ok(SponsorsResponse {
+        sponsors: sponsors_response?,
+    })
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let config = migrate_config(deps.storage)?;
+    validate_config(&config)?;
+    config_store(deps.storage).save(&config)?;
+
+    let balance = query_token_balance(
+        &deps.querier,
+        deps.api.addr_humanize(&config.anchor_token)?,
+        env.contract.address,
+    )?;
+    migrate_state(deps.storage, balance)?;
+
+    Ok(Response::default())
+}
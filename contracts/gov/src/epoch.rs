@@ -0,0 +1,115 @@
+//! Staking and voting statistics per epoch. An epoch is opened by the first action
+//! after the previous one ended, and starts on the boundary of the previous epoch,
+//! so epochs without any activity are skipped.
+
+use astroport::querier::query_token_balance;
+use cosmwasm_std::{CanonicalAddr, Deps, DepsMut, Env, StdResult, Uint128};
+
+use crate::state::{
+    config_read, epoch_stats_store, epoch_voter_read, epoch_voter_store, read_epochs,
+    read_latest_epoch, state_read, Config, EpochStats, State,
+};
+use anchor_token::gov::{EpochStatsResponse, EpochStatsResponseItem, VoteOption};
+
+pub enum EpochActivity<'a> {
+    Stake,
+    Withdraw,
+    PollCreated,
+    PollEnded,
+    Vote {
+        voter: &'a CanonicalAddr,
+        vote: &'a VoteOption,
+        amount: Uint128,
+    },
+}
+
+/// record_epoch_activity adds an action to the current epoch, opening a new one if needed.
+/// `pending` is the ANC received by the action which is not accounted for yet.
+pub fn record_epoch_activity(
+    deps: DepsMut,
+    env: &Env,
+    pending: Uint128,
+    activity: EpochActivity,
+) -> StdResult<()> {
+    let now = env.block.time.seconds();
+    let mut epoch = match read_latest_epoch(deps.storage)? {
+        Some(epoch) if now < epoch.end_time => epoch,
+        latest => {
+            let config: Config = config_read(deps.storage).load()?;
+            let start_time = match latest {
+                Some(latest) => now - (now - latest.end_time) % config.epoch_length,
+                None => now - now % config.epoch_length,
+            };
+
+            EpochStats {
+                start_time,
+                end_time: start_time + config.epoch_length,
+                total_staked: query_total_staked(deps.as_ref(), pending)?,
+                polls_created: 0,
+                polls_ended: 0,
+                yes_votes: Uint128::zero(),
+                no_votes: Uint128::zero(),
+                unique_voters: 0,
+            }
+        }
+    };
+
+    match activity {
+        // staking and withdrawing only move the total staked, taken when the epoch opens
+        EpochActivity::Stake | EpochActivity::Withdraw => {}
+        EpochActivity::PollCreated => epoch.polls_created += 1,
+        EpochActivity::PollEnded => epoch.polls_ended += 1,
+        EpochActivity::Vote {
+            voter,
+            vote,
+            amount,
+        } => {
+            match vote {
+                VoteOption::Yes => epoch.yes_votes += amount,
+                VoteOption::No => epoch.no_votes += amount,
+            }
+
+            let last_epoch = epoch_voter_read(deps.storage).may_load(voter.as_slice())?;
+            if last_epoch != Some(epoch.start_time) {
+                epoch.unique_voters += 1;
+                epoch_voter_store(deps.storage).save(voter.as_slice(), &epoch.start_time)?;
+            }
+        }
+    }
+
+    epoch_stats_store(deps.storage).save(&epoch.start_time.to_be_bytes(), &epoch)
+}
+
+/// query_total_staked saturates rather than fail, statistics must never block an action
+fn query_total_staked(deps: Deps, pending: Uint128) -> StdResult<Uint128> {
+    let config: Config = config_read(deps.storage).load()?;
+    let state: State = state_read(deps.storage).load()?;
+    Ok(query_token_balance(
+        &deps.querier,
+        deps.api.addr_humanize(&config.anchor_token)?,
+        deps.api.addr_humanize(&state.contract_addr)?,
+    )?
+    .saturating_sub(state.reserved_amount() + pending))
+}
+
+pub fn query_epoch_stats(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<EpochStatsResponse> {
+    let epochs = read_epochs(deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|epoch| EpochStatsResponseItem {
+            start_time: epoch.start_time,
+            end_time: epoch.end_time,
+            total_staked: epoch.total_staked,
+            polls_created: epoch.polls_created,
+            polls_ended: epoch.polls_ended,
+            yes_votes: epoch.yes_votes,
+            no_votes: epoch.no_votes,
+            unique_voters: epoch.unique_voters,
+        })
+        .collect();
+
+    Ok(EpochStatsResponse { epochs })
+}
@@ -12,6 +12,9 @@ pub enum ContractError {
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Invalid {0}: must be {1}")]
+    InvalidConfig(String, String),
+
     #[error("Asset mismatch")]
     AssetMismatch {},
 
@@ -24,9 +27,18 @@ pub enum ContractError {
     #[error("Must deposit more than {0} token")]
     InsufficientProposalDeposit(u128),
 
+    #[error("Must stake at least {0} token")]
+    InsufficientStakeAmount(u128),
+
+    #[error("Must stake at least {0} token to create polls with messages, {1} short")]
+    InsufficientCreatorStake(u128, u128),
+
     #[error("Reward deposited is too small")]
     InsufficientReward {},
 
+    #[error("Deposit exceeds the staking cap, {0} token can still be staked")]
+    DepositCapExceeded(u128),
+
     #[error("User does not have enough staked tokens")]
     InsufficientStaked {},
 
@@ -39,6 +51,9 @@ pub enum ContractError {
     #[error("Nothing to withdraw")]
     NothingToWithdraw {},
 
+    #[error("Poll has no votes left to unlock")]
+    NothingToUnlock {},
+
     #[error("Poll does not exist")]
     PollNotFound {},
 
@@ -54,6 +69,9 @@ pub enum ContractError {
     #[error("Poll is not in passed status")]
     PollNotPassed {},
 
+    #[error("Poll cannot be amended after votes are cast")]
+    PollAlreadyVoted {},
+
     #[error("Cannot snapshot at this height")]
     SnapshotHeight {},
 
@@ -66,6 +84,87 @@ pub enum ContractError {
     #[error("Voting period has not expired")]
     PollVotingPeriod {},
 
+    #[error("Poll is not pending sponsorship")]
+    PollNotPending {},
+
+    #[error("User has already sponsored")]
+    AlreadySponsored {},
+
+    #[error("Sponsorship period has not expired")]
+    SponsorshipPeriod {},
+
+    #[error("Sponsorship period has expired")]
+    SponsorshipPeriodExpired {},
+
+    #[error("Poll cannot have more than {0} execute messages")]
+    TooManyExecuteMsgs(u64),
+
+    #[error("Execute message at index {0} exceeds the maximum size of {1} bytes")]
+    ExecuteMsgTooLarge(u64, u64),
+
+    #[error("Execute message at index {0} duplicates an earlier message")]
+    DuplicateExecuteMsg(u64),
+
+    #[error("Community fund is not registered")]
+    CommunityFundNotSet {},
+
+    #[error("Spend amount must be greater than zero")]
+    InvalidSpendAmount {},
+
+    #[error("Cannot spend more than {0} token in a poll")]
+    SpendLimitExceeded(u128),
+
+    #[error("Capped weight mode requires a non-zero max weight")]
+    InvalidWeightMode {},
+
+    #[error("Only text polls can use a weight mode other than linear")]
+    WeightModeNotAllowed {},
+
+    #[error("Cannot register the voting token or an already registered reward token")]
+    InvalidRewardToken {},
+
+    #[error("Reward token is not registered")]
+    RewardTokenNotRegistered {},
+
+    #[error("Nothing to claim")]
+    NothingToClaim {},
+
+    #[error("Poll has been archived")]
+    PollArchived {},
+
+    #[error("Poll cannot be archived yet")]
+    PollNotArchivable {},
+
+    #[error("Votes of the poll have not been committed yet")]
+    VoteRootNotAvailable {},
+
+    #[error("User has not voted on the poll")]
+    NotVoted {},
+
+    #[error("Polls cannot execute messages on {0}")]
+    DeniedExecuteTarget(String),
+
+    #[error("Creator already has too many open polls: {0}")]
+    TooManyOpenPolls(String),
+
+    #[error("Only polls rejected for missing quorum can be resubmitted")]
+    PollNotResubmittable {},
+
+    #[error("Poll has already been resubmitted as poll {0}")]
+    PollAlreadyResubmitted(u64),
+
+    #[error("Resubmit window has expired")]
+    ResubmitWindowExpired {},
+
+    #[error("Poll cannot be resubmitted more than {0} times")]
+    ResubmitDepthExceeded(u32),
+
+    #[error("Cannot cast more than {0} votes at once")]
+    TooManyVotes(usize),
+
+    #[error("Cannot query more than {0} polls at once")]
+    TooManyPolls(usize),
+
     #[error("Invalid Reply Id")]
     InvalidReplyId {},
 }
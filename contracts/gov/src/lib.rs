@@ -1,6 +1,10 @@
 pub mod contract;
 
+mod config_history;
+mod epoch;
 mod error;
+mod merkle;
+mod migration;
 mod staking;
 mod state;
 
@@ -0,0 +1,127 @@
+//! Votes of an ended poll are committed to a sha256 merkle tree with one leaf per voter,
+//! in ascending order of the canonical voter address. Pairs are hashed with the smaller
+//! hash first, and the last node of a level with an odd number of nodes is carried up
+//! unchanged. The tree is built while the votes are unlocked, so only the nodes touched
+//! by the current batch are loaded.
+
+use std::convert::TryInto;
+
+use cosmwasm_std::{Binary, StdError, StdResult, Storage, Uint128};
+use sha2::{Digest, Sha256};
+
+use crate::state::{vote_node_read, vote_node_store};
+use anchor_token::gov::VoteOption;
+
+pub type Hash = [u8; 32];
+
+fn sha256(data: &[u8]) -> Hash {
+    Sha256::digest(data)
+        .as_slice()
+        .try_into()
+        .expect("Wrong length")
+}
+
+pub fn leaf_hash(voter: &str, vote: &VoteOption, balance: Uint128) -> Hash {
+    sha256(format!("{}:{}:{}", voter, vote, balance).as_bytes())
+}
+
+pub fn hash_pair(a: Hash, b: Hash) -> Hash {
+    if a < b {
+        sha256(&[a, b].concat())
+    } else {
+        sha256(&[b, a].concat())
+    }
+}
+
+fn load_node(storage: &dyn Storage, poll_id: u64, level: u8, index: u64) -> StdResult<Hash> {
+    let node: Binary = vote_node_read(storage, poll_id).load(&node_key(level, index))?;
+    node.as_slice()
+        .try_into()
+        .map_err(|_| StdError::generic_err("Invalid merkle node"))
+}
+
+fn save_node(
+    storage: &mut dyn Storage,
+    poll_id: u64,
+    level: u8,
+    index: u64,
+    node: Hash,
+) -> StdResult<()> {
+    vote_node_store(storage, poll_id).save(&node_key(level, index), &Binary::from(node.to_vec()))
+}
+
+fn node_key(level: u8, index: u64) -> Vec<u8> {
+    [&[level][..], &index.to_be_bytes()].concat()
+}
+
+/// append_leaf stores the leaf at `index` and every parent it completes
+pub fn append_leaf(
+    storage: &mut dyn Storage,
+    poll_id: u64,
+    index: u64,
+    leaf: Hash,
+) -> StdResult<()> {
+    let mut node = leaf;
+    let mut index = index;
+    let mut level = 0u8;
+    save_node(storage, poll_id, level, index, node)?;
+
+    while index % 2 == 1 {
+        let left = load_node(storage, poll_id, level, index - 1)?;
+        node = hash_pair(left, node);
+        index /= 2;
+        level += 1;
+        save_node(storage, poll_id, level, index, node)?;
+    }
+
+    Ok(())
+}
+
+/// finalize_root completes the right edge of the tree once all `leaf_count` leaves
+/// are appended and returns the root
+pub fn finalize_root(storage: &mut dyn Storage, poll_id: u64, leaf_count: u64) -> StdResult<Hash> {
+    let mut count = leaf_count;
+    let mut level = 0u8;
+
+    // only the last node of each level can be missing its parent
+    while count > 1 {
+        let last = load_node(storage, poll_id, level, count - 1)?;
+        let parent = if count % 2 == 1 {
+            last
+        } else {
+            hash_pair(load_node(storage, poll_id, level, count - 2)?, last)
+        };
+        save_node(storage, poll_id, level + 1, (count - 1) / 2, parent)?;
+
+        count = count / 2 + count % 2;
+        level += 1;
+    }
+
+    load_node(storage, poll_id, level, 0)
+}
+
+/// proof returns the sibling path of the leaf at `index`, from the leaf up to the root
+pub fn proof(
+    storage: &dyn Storage,
+    poll_id: u64,
+    leaf_count: u64,
+    index: u64,
+) -> StdResult<Vec<Hash>> {
+    let mut siblings = vec![];
+    let mut count = leaf_count;
+    let mut index = index;
+    let mut level = 0u8;
+
+    while count > 1 {
+        let sibling = index ^ 1;
+        if sibling < count {
+            siblings.push(load_node(storage, poll_id, level, sibling)?);
+        }
+
+        count = count / 2 + count % 2;
+        index /= 2;
+        level += 1;
+    }
+
+    Ok(siblings)
+}
@@ -0,0 +1,127 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::state::{
+    creator_poll_store, default_vote_weight_buckets, poll_indexer_read, poll_read, state_store,
+    Config, Poll, ProtocolAddresses, State, DEFAULT_ARCHIVE_AFTER, DEFAULT_BLOCK_TIME,
+    DEFAULT_EPOCH_LENGTH, DEFAULT_EXPIRED_DEPOSIT_REFUND_PERCENT, DEFAULT_MAX_EXECUTE_MSGS,
+    DEFAULT_MAX_EXECUTE_MSG_SIZE, DEFAULT_MAX_RESUBMIT_DEPTH, KEY_CONFIG, KEY_STATE,
+};
+use anchor_token::gov::{PollClock, PollStatus};
+use cosmwasm_std::{CanonicalAddr, Decimal, Order, StdResult, Storage, Uint128};
+use cosmwasm_storage::ReadonlySingleton;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyConfig {
+    pub owner: CanonicalAddr,
+    pub anchor_token: CanonicalAddr,
+    pub quorum: Decimal,
+    pub threshold: Decimal,
+    pub voting_period: u64,
+    pub timelock_period: u64,
+    pub expiration_period: u64,
+    pub proposal_deposit: Uint128,
+    pub snapshot_period: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyState {
+    pub contract_addr: CanonicalAddr,
+    pub poll_count: u64,
+    pub total_share: Uint128,
+    pub total_deposit: Uint128,
+}
+
+fn read_legacy_config(storage: &dyn Storage) -> StdResult<LegacyConfig> {
+    ReadonlySingleton::new(storage, KEY_CONFIG).load()
+}
+
+/// migrate_config builds the config from the legacy config, it is validated before saving
+pub fn migrate_config(storage: &dyn Storage) -> StdResult<Config> {
+    let legacy_config: LegacyConfig = read_legacy_config(storage)?;
+
+    Ok(Config {
+        owner: legacy_config.owner,
+        anchor_token: legacy_config.anchor_token,
+        quorum: legacy_config.quorum,
+        threshold: legacy_config.threshold,
+        voting_period: legacy_config.voting_period,
+        timelock_period: legacy_config.timelock_period,
+        expiration_period: legacy_config.expiration_period,
+        proposal_deposit: legacy_config.proposal_deposit,
+        snapshot_period: legacy_config.snapshot_period,
+        sponsorship_threshold: Uint128::zero(),
+        sponsorship_period: 0u64,
+        max_execute_msg_size: DEFAULT_MAX_EXECUTE_MSG_SIZE,
+        max_execute_msgs: DEFAULT_MAX_EXECUTE_MSGS,
+        min_stake_amount: Uint128::zero(),
+        stake_depositors: vec![],
+        protocol_addresses: ProtocolAddresses::default(),
+        max_spend_per_poll: Uint128::zero(),
+        archive_after: DEFAULT_ARCHIVE_AFTER,
+        poll_clock: PollClock::Height,
+        block_time: DEFAULT_BLOCK_TIME,
+        late_quorum_window: 0u64,
+        late_quorum_extension: 0u64,
+        participation_reward_ratio: Decimal::zero(),
+        execution_denylist: vec![],
+        epoch_length: DEFAULT_EPOCH_LENGTH,
+        max_open_polls_per_creator: 0u32,
+        min_creator_stake: Uint128::zero(),
+        vote_weight_buckets: default_vote_weight_buckets(),
+        resubmit_window: 0u64,
+        resubmit_deposit: legacy_config.proposal_deposit,
+        max_resubmit_depth: DEFAULT_MAX_RESUBMIT_DEPTH,
+        deposit_cap: None,
+        atomic_batch: true,
+        expired_deposit_refund_ratio: Decimal::percent(DEFAULT_EXPIRED_DEPOSIT_REFUND_PERCENT),
+    })
+}
+
+fn read_legacy_state(storage: &dyn Storage) -> StdResult<LegacyState> {
+    ReadonlySingleton::new(storage, KEY_STATE).load()
+}
+
+fn count_polls(storage: &dyn Storage, status: &PollStatus) -> u64 {
+    poll_indexer_read(storage, status)
+        .range(None, None, Order::Ascending)
+        .count() as u64
+}
+
+/// backfill_creator_polls counts the polls in progress before the upgrade
+/// against the open polls of their creators
+fn backfill_creator_polls(storage: &mut dyn Storage) -> StdResult<()> {
+    let poll_keys = poll_indexer_read(storage, &PollStatus::InProgress)
+        .range(None, None, Order::Ascending)
+        .map(|item| item.map(|(k, _)| k))
+        .collect::<StdResult<Vec<Vec<u8>>>>()?;
+
+    for key in poll_keys {
+        let poll: Poll = poll_read(storage).load(&key)?;
+        creator_poll_store(storage, &poll.creator).save(&key, &true)?;
+    }
+
+    Ok(())
+}
+
+/// migrate_state backfills the poll counters and the open polls of each creator
+/// from the poll indexer. The staked principal starts from the staked balance,
+/// as the rewards earned before cannot be told apart.
+pub fn migrate_state(storage: &mut dyn Storage, balance: Uint128) -> StdResult<()> {
+    let legacy_state: LegacyState = read_legacy_state(storage)?;
+    let polls_in_progress = count_polls(storage, &PollStatus::InProgress);
+    let polls_awaiting_execution = count_polls(storage, &PollStatus::Passed);
+    backfill_creator_polls(storage)?;
+
+    state_store(storage).save(&State {
+        contract_addr: legacy_state.contract_addr,
+        poll_count: legacy_state.poll_count,
+        total_share: legacy_state.total_share,
+        total_deposit: legacy_state.total_deposit,
+        polls_in_progress,
+        polls_awaiting_execution,
+        participation_pool: Uint128::zero(),
+        participation_rewards: Uint128::zero(),
+        total_principal: balance.checked_sub(legacy_state.total_deposit)?,
+    })
+}
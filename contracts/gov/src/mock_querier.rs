@@ -1,3 +1,6 @@
+use anchor_token::community::{
+    ConfigResponse as CommunityConfigResponse, QueryMsg as CommunityQueryMsg,
+};
 use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
     from_binary, from_slice, to_binary, Coin, ContractResult, Empty, OwnedDeps, Querier,
@@ -24,6 +27,7 @@ pub fn mock_dependencies(
 pub struct WasmMockQuerier {
     base: MockQuerier<Empty>,
     token_querier: TokenQuerier,
+    community_configs: HashMap<String, CommunityConfigResponse>,
 }
 
 #[derive(Clone, Default)]
@@ -74,6 +78,15 @@ impl Querier for WasmMockQuerier {
 impl WasmMockQuerier {
     pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
         match &request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                if self.community_configs.contains_key(contract_addr) =>
+            {
+                match from_binary(msg).unwrap() {
+                    CommunityQueryMsg::Config {} => SystemResult::Ok(ContractResult::Ok(
+                        to_binary(&self.community_configs[contract_addr]).unwrap(),
+                    )),
+                }
+            }
             QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
                 match from_binary(msg).unwrap() {
                     Cw20QueryMsg::Balance { address } => {
@@ -120,6 +133,7 @@ impl WasmMockQuerier {
         WasmMockQuerier {
             base,
             token_querier: TokenQuerier::default(),
+            community_configs: HashMap::new(),
         }
     }
 
@@ -127,4 +141,9 @@ impl WasmMockQuerier {
     pub fn with_token_balances(&mut self, balances: &[(&String, &[(&String, &Uint128)])]) {
         self.token_querier = TokenQuerier::new(balances);
     }
+
+    pub fn with_community_config(&mut self, community_fund: &str, config: CommunityConfigResponse) {
+        self.community_configs
+            .insert(community_fund.to_string(), config);
+    }
 }
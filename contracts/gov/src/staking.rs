@@ -1,32 +1,73 @@
+use crate::epoch::{record_epoch_activity, EpochActivity};
 use crate::error::ContractError;
+use crate::merkle::{append_leaf, finalize_root, leaf_hash};
 use crate::state::{
-    bank_read, bank_store, config_read, config_store, poll_read, poll_voter_store, state_read,
-    state_store, Config, Poll, State, TokenManager,
+    bank_read, bank_store, config_read, config_store, participation_reward_read,
+    participation_reward_store, poll_read, poll_store, poll_voter_read, poll_voter_store,
+    read_reward_tokens, reward_token_read, reward_token_store, share_rate_count_read,
+    share_rate_count_store, share_rate_read, share_rate_store, staker_reward_read,
+    staker_reward_store, state_read, state_store, vote_leaf_store, Config, Poll, RewardToken,
+    ShareRateCheckpoint, StakerReward, State, TokenManager, VoteLeaf,
 };
 
-use anchor_token::gov::{PollStatus, StakerResponse};
+use anchor_token::gov::{
+    PollClock, PollStatus, ShareRateCheckpointResponse, ShareRateHistoryResponse, StakerResponse,
+    StakerRewardsResponse, StakerRewardsResponseItem, StakingMetricsResponse, VoterInfo,
+    WithdrawLockResponseItem, WithdrawStatusResponse,
+};
 use astroport::querier::query_token_balance;
 use cosmwasm_std::{
-    to_binary, Addr, CanonicalAddr, CosmosMsg, Deps, DepsMut, MessageInfo, Response, StdResult,
-    Storage, Uint128, WasmMsg,
+    to_binary, Addr, Api, CanonicalAddr, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo,
+    Order, Response, StdResult, Storage, Uint128, WasmMsg,
 };
 use cw20::Cw20ExecuteMsg;
 
+/// Maximum number of voters unlocked in a single transaction
+pub const MAX_UNLOCK_VOTERS: usize = 100;
+
+/// Number of share rate checkpoints kept
+const SHARE_RATE_HISTORY_SIZE: u64 = 90;
+/// Minimum time between two share rate checkpoints, in seconds
+const SHARE_RATE_MIN_INTERVAL: u64 = 86400;
+
 pub fn stake_voting_tokens(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     sender: Addr,
+    staker: Option<Addr>,
     amount: Uint128,
 ) -> Result<Response, ContractError> {
     if amount.is_zero() {
         return Err(ContractError::InsufficientFunds {});
     }
 
-    let sender_address_raw = deps.api.addr_canonicalize(sender.as_str())?;
-    let key = &sender_address_raw.as_slice();
+    let config: Config = config_store(deps.storage).load()?;
+
+    // only allowed depositors can stake on behalf of another address
+    if staker.is_some()
+        && !config
+            .stake_depositors
+            .contains(&deps.api.addr_canonicalize(sender.as_str())?)
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let staker_address_raw = deps
+        .api
+        .addr_canonicalize(staker.as_ref().unwrap_or(&sender).as_str())?;
+    let key = &staker_address_raw.as_slice();
+
+    record_epoch_activity(deps.branch(), &env, amount, EpochActivity::Stake)?;
 
     let mut token_manager = bank_read(deps.storage).may_load(key)?.unwrap_or_default();
-    let config: Config = config_store(deps.storage).load()?;
     let mut state: State = state_store(deps.storage).load()?;
+    settle_rewards(deps.storage, &staker_address_raw, token_manager.share)?;
+
+    if amount < config.min_stake_amount {
+        return Err(ContractError::InsufficientStakeAmount(
+            config.min_stake_amount.u128(),
+        ));
+    }
 
     // balance already increased, so subtract deposit amount
     let total_balance = query_token_balance(
@@ -34,7 +75,17 @@ pub fn stake_voting_tokens(
         deps.api.addr_humanize(&config.anchor_token)?,
         deps.api.addr_humanize(&state.contract_addr)?,
     )?
-    .checked_sub(state.total_deposit + amount)?;
+    .checked_sub(state.reserved_amount() + amount)?;
+
+    // rewards do not count toward the cap, and a cap lowered below
+    // the staked principal only blocks new deposits
+    if let Some(deposit_cap) = config.deposit_cap {
+        if state.total_principal + amount > deposit_cap {
+            return Err(ContractError::DepositCapExceeded(
+                deposit_cap.saturating_sub(state.total_principal).u128(),
+            ));
+        }
+    }
 
     let share = if total_balance.is_zero() || state.total_share.is_zero() {
         amount
@@ -42,23 +93,42 @@ pub fn stake_voting_tokens(
         amount.multiply_ratio(state.total_share, total_balance)
     };
 
+    // a deposit too small to mint a share would be donated to the other stakers
+    if share.is_zero() {
+        return Err(ContractError::InsufficientFunds {});
+    }
+
     token_manager.share += share;
     state.total_share += share;
+    state.total_principal += amount;
 
     state_store(deps.storage).save(&state)?;
     bank_store(deps.storage).save(key, &token_manager)?;
+    record_share_rate(
+        deps.storage,
+        &env,
+        total_balance + amount,
+        state.total_share,
+    )?;
 
-    Ok(Response::new().add_attributes(vec![
+    let mut response = Response::new().add_attributes(vec![
         ("action", "staking"),
         ("sender", sender.as_str()),
         ("share", share.to_string().as_str()),
         ("amount", amount.to_string().as_str()),
-    ]))
+    ]);
+
+    if let Some(staker) = staker {
+        response = response.add_attribute("staker", staker.as_str());
+    }
+
+    Ok(response)
 }
 
 // Withdraw amount if not staked. By default all funds will be withdrawn.
 pub fn withdraw_voting_tokens(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     amount: Option<Uint128>,
 ) -> Result<Response, ContractError> {
@@ -66,6 +136,13 @@ pub fn withdraw_voting_tokens(
     let key = sender_address_raw.as_slice();
 
     if let Some(mut token_manager) = bank_read(deps.storage).may_load(key)? {
+        record_epoch_activity(
+            deps.branch(),
+            &env,
+            Uint128::zero(),
+            EpochActivity::Withdraw,
+        )?;
+
         let config: Config = config_store(deps.storage).load()?;
         let mut state: State = state_store(deps.storage).load()?;
 
@@ -76,17 +153,30 @@ pub fn withdraw_voting_tokens(
             deps.api.addr_humanize(&config.anchor_token)?,
             deps.api.addr_humanize(&state.contract_addr)?,
         )?
-        .checked_sub(state.total_deposit)?
+        .checked_sub(state.reserved_amount())?
         .u128();
 
+        // a record left behind by a full withdrawal holds nothing to withdraw
+        if total_share == 0 || total_balance == 0 {
+            return Err(ContractError::NothingStaked {});
+        }
+
+        settle_rewards(deps.storage, &sender_address_raw, token_manager.share)?;
+
         let locked_balance =
             compute_locked_balance(deps.storage, &mut token_manager, &sender_address_raw);
         let locked_share = locked_balance * total_share / total_balance;
         let user_share = token_manager.share.u128();
 
         let withdraw_share = amount
-            .map(|v| std::cmp::max(v.multiply_ratio(total_share, total_balance).u128(), 1u128))
-            .unwrap_or_else(|| user_share - locked_share);
+            .map(|v| v.multiply_ratio(total_share, total_balance).u128())
+            .unwrap_or_else(|| user_share.saturating_sub(locked_share));
+
+        // reject withdrawals which would not burn any share
+        if withdraw_share == 0 {
+            return Err(ContractError::InvalidWithdrawAmount {});
+        }
+
         let withdraw_amount = amount
             .map(|v| v.u128())
             .unwrap_or_else(|| withdraw_share * total_balance / total_share);
@@ -99,8 +189,19 @@ pub fn withdraw_voting_tokens(
 
             bank_store(deps.storage).save(key, &token_manager)?;
 
+            // each share withdrawn takes its part of the principal along
+            state.total_principal = state.total_principal
+                - state
+                    .total_principal
+                    .multiply_ratio(withdraw_share, total_share);
             state.total_share = Uint128::from(total_share - withdraw_share);
             state_store(deps.storage).save(&state)?;
+            record_share_rate(
+                deps.storage,
+                &env,
+                Uint128::from(total_balance - withdraw_amount),
+                state.total_share,
+            )?;
 
             send_tokens(
                 deps,
@@ -115,6 +216,46 @@ pub fn withdraw_voting_tokens(
     }
 }
 
+/// record_share_rate adds a checkpoint to the share rate history,
+/// unless the latest one is less than a day old
+fn record_share_rate(
+    storage: &mut dyn Storage,
+    env: &Env,
+    total_balance: Uint128,
+    total_share: Uint128,
+) -> StdResult<()> {
+    let count = share_rate_count_read(storage)
+        .may_load()?
+        .unwrap_or_default();
+    if count > 0 {
+        let last = share_rate_read(storage)
+            .load(&((count - 1) % SHARE_RATE_HISTORY_SIZE).to_be_bytes())?;
+        if env.block.time.seconds() < last.time + SHARE_RATE_MIN_INTERVAL {
+            return Ok(());
+        }
+    }
+
+    share_rate_store(storage).save(
+        &(count % SHARE_RATE_HISTORY_SIZE).to_be_bytes(),
+        &ShareRateCheckpoint {
+            time: env.block.time.seconds(),
+            total_balance,
+            total_share,
+        },
+    )?;
+    share_rate_count_store(storage).save(&(count + 1))
+}
+
+/// share_rate returns the staked balance per share.
+/// A new share is minted 1:1 with the staked amount when nothing is staked yet.
+fn share_rate(total_balance: Uint128, total_share: Uint128) -> Decimal {
+    if total_share.is_zero() {
+        Decimal::one()
+    } else {
+        Decimal::from_ratio(total_balance, total_share)
+    }
+}
+
 // removes not in-progress poll voter info & unlock tokens
 // and returns the largest locked amount in participated polls.
 fn compute_locked_balance(
@@ -123,9 +264,15 @@ fn compute_locked_balance(
     voter: &CanonicalAddr,
 ) -> u128 {
     token_manager.locked_balance.retain(|(poll_id, _)| {
-        let poll: Poll = poll_read(storage).load(&poll_id.to_be_bytes()).unwrap();
+        // archived polls have unlocked all of their voters
+        let poll: Poll = match poll_read(storage).may_load(&poll_id.to_be_bytes()) {
+            Ok(Some(poll)) => poll,
+            _ => return false,
+        };
 
-        if poll.status != PollStatus::InProgress {
+        // voters of a poll still being unlocked are left for
+        // ContinueUnlock, which commits them to the vote tree
+        if poll.status != PollStatus::InProgress && !poll.unlock_pending {
             // remove voter info from the poll
             poll_voter_store(storage, *poll_id).remove(voter.as_slice());
         }
@@ -133,14 +280,332 @@ fn compute_locked_balance(
         poll.status == PollStatus::InProgress
     });
 
+    // sponsorships are released once the poll is activated or expired
+    token_manager
+        .sponsored_balance
+        .retain(|(poll_id, _)| is_pending(storage, *poll_id));
+
     token_manager
         .locked_balance
         .iter()
         .map(|(_, v)| v.balance.u128())
+        .chain(
+            token_manager
+                .sponsored_balance
+                .iter()
+                .map(|(_, w)| w.u128()),
+        )
         .max()
         .unwrap_or_default()
 }
 
+fn is_pending(storage: &dyn Storage, poll_id: u64) -> bool {
+    match poll_read(storage).may_load(&poll_id.to_be_bytes()) {
+        Ok(Some(poll)) => poll.status == PollStatus::Pending,
+        _ => false,
+    }
+}
+
+pub fn register_reward_token(
+    deps: DepsMut,
+    info: MessageInfo,
+    token: String,
+) -> Result<Response, ContractError> {
+    let config: Config = config_read(deps.storage).load()?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let token_raw = deps.api.addr_canonicalize(&token)?;
+    if token_raw == config.anchor_token
+        || reward_token_read(deps.storage)
+            .may_load(token_raw.as_slice())?
+            .is_some()
+    {
+        return Err(ContractError::InvalidRewardToken {});
+    }
+
+    reward_token_store(deps.storage).save(
+        token_raw.as_slice(),
+        &RewardToken {
+            global_reward_index: Decimal::zero(),
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "register_reward_token"),
+        ("token", token.as_str()),
+    ]))
+}
+
+/// DistributeRewards increases the reward index of a registered token,
+/// so the rewards are shared by the stakers at the time of distribution
+pub fn distribute_rewards(
+    deps: DepsMut,
+    token: Addr,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let state: State = state_read(deps.storage).load()?;
+    if state.total_share.is_zero() {
+        return Err(ContractError::NothingStaked {});
+    }
+
+    let token_raw = deps.api.addr_canonicalize(token.as_str())?;
+    let mut reward_token: RewardToken =
+        reward_token_read(deps.storage).load(token_raw.as_slice())?;
+    reward_token.global_reward_index =
+        reward_token.global_reward_index + Decimal::from_ratio(amount, state.total_share);
+    reward_token_store(deps.storage).save(token_raw.as_slice(), &reward_token)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "distribute_rewards"),
+        ("token", token.as_str()),
+        ("amount", amount.to_string().as_str()),
+    ]))
+}
+
+pub fn claim_rewards(
+    deps: DepsMut,
+    info: MessageInfo,
+    token: Option<String>,
+) -> Result<Response, ContractError> {
+    let staker_address_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let token_manager = bank_read(deps.storage)
+        .may_load(staker_address_raw.as_slice())?
+        .unwrap_or_default();
+
+    let config: Config = config_read(deps.storage).load()?;
+    let mut response = Response::new().add_attribute("action", "claim_rewards");
+
+    // participation rewards are paid in ANC, which is not a registered reward token
+    let (claim_participation, reward_tokens) = match token {
+        Some(token) => {
+            let token_raw = deps.api.addr_canonicalize(&token)?;
+            if token_raw == config.anchor_token {
+                (true, vec![])
+            } else {
+                match reward_token_read(deps.storage).may_load(token_raw.as_slice())? {
+                    Some(reward_token) => (false, vec![(token_raw, reward_token)]),
+                    None => return Err(ContractError::RewardTokenNotRegistered {}),
+                }
+            }
+        }
+        None => (true, read_reward_tokens(deps.storage)?),
+    };
+
+    let participation_reward = participation_reward_read(deps.storage)
+        .may_load(staker_address_raw.as_slice())?
+        .unwrap_or_default();
+    if claim_participation && !participation_reward.is_zero() {
+        participation_reward_store(deps.storage).remove(staker_address_raw.as_slice());
+        state_store(deps.storage).update(|mut state| -> StdResult<_> {
+            state.participation_rewards = state
+                .participation_rewards
+                .checked_sub(participation_reward)?;
+            Ok(state)
+        })?;
+
+        let token = deps.api.addr_humanize(&config.anchor_token)?.to_string();
+        response = response
+            .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: token.clone(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: info.sender.to_string(),
+                    amount: participation_reward,
+                })?,
+                funds: vec![],
+            }))
+            .add_attributes(vec![
+                ("token", token),
+                ("amount", participation_reward.to_string()),
+            ]);
+    }
+
+    for (token_raw, reward_token) in reward_tokens {
+        let mut staker_reward = staker_reward_read(deps.storage, &staker_address_raw)
+            .may_load(token_raw.as_slice())?
+            .unwrap_or_default();
+        compute_staker_reward(&reward_token, &mut staker_reward, token_manager.share)?;
+
+        let amount = staker_reward.pending_reward;
+        if amount.is_zero() {
+            continue;
+        }
+
+        staker_reward.pending_reward = Uint128::zero();
+        staker_reward_store(deps.storage, &staker_address_raw)
+            .save(token_raw.as_slice(), &staker_reward)?;
+
+        let token = deps.api.addr_humanize(&token_raw)?.to_string();
+        response = response
+            .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: token.clone(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: info.sender.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            }))
+            .add_attributes(vec![("token", token), ("amount", amount.to_string())]);
+    }
+
+    if response.messages.is_empty() {
+        return Err(ContractError::NothingToClaim {});
+    }
+
+    Ok(response)
+}
+
+/// settle_rewards moves the rewards earned by the current share of a staker
+/// to their pending rewards. It must run before every change of the share.
+fn settle_rewards(
+    storage: &mut dyn Storage,
+    staker: &CanonicalAddr,
+    share: Uint128,
+) -> StdResult<()> {
+    for (token_raw, reward_token) in read_reward_tokens(storage)? {
+        let mut staker_reward = staker_reward_read(storage, staker)
+            .may_load(token_raw.as_slice())?
+            .unwrap_or_default();
+        compute_staker_reward(&reward_token, &mut staker_reward, share)?;
+        staker_reward_store(storage, staker).save(token_raw.as_slice(), &staker_reward)?;
+    }
+
+    Ok(())
+}
+
+// withdraw reward to pending reward
+fn compute_staker_reward(
+    reward_token: &RewardToken,
+    staker_reward: &mut StakerReward,
+    share: Uint128,
+) -> StdResult<()> {
+    let pending_reward = (share * reward_token.global_reward_index)
+        .checked_sub(share * staker_reward.reward_index)?;
+
+    staker_reward.reward_index = reward_token.global_reward_index;
+    staker_reward.pending_reward += pending_reward;
+    Ok(())
+}
+
+/// UnlockProgress is the outcome of one batch of unlock_votes_for_poll
+pub struct UnlockProgress {
+    /// Voters are left to be unlocked by ContinueUnlock
+    pub pending: bool,
+    /// Voters of the batch without a staker record, so without a locked balance to remove
+    pub skipped_voters: u64,
+}
+
+/// unlock_votes_for_poll removes an ended poll from the locked balances of up to `limit`
+/// of its voters and, with `commit_votes`, commits their votes to the vote tree of the poll.
+pub fn unlock_votes_for_poll(
+    storage: &mut dyn Storage,
+    api: &dyn Api,
+    a_poll: &mut Poll,
+    limit: usize,
+    commit_votes: bool,
+) -> StdResult<UnlockProgress> {
+    let poll_id = a_poll.id;
+    let voters: Vec<(Vec<u8>, VoterInfo)> = poll_voter_read(storage, poll_id)
+        .range(None, None, Order::Ascending)
+        .take(limit + 1)
+        .collect::<StdResult<Vec<(Vec<u8>, VoterInfo)>>>()?;
+
+    let total_votes = a_poll.yes_votes + a_poll.no_votes;
+    let mut skipped_voters = 0u64;
+    for (voter, voter_info) in voters.iter().take(limit) {
+        if !a_poll.participation_reward.is_zero() {
+            let reward = a_poll
+                .participation_reward
+                .multiply_ratio(voter_info.balance, total_votes);
+            participation_reward_store(storage).update(voter, |pending| -> StdResult<_> {
+                Ok(pending.unwrap_or_default() + reward)
+            })?;
+            a_poll.participation_distributed += reward;
+        }
+
+        match bank_read(storage).may_load(voter)? {
+            Some(mut token_manager) => {
+                token_manager
+                    .locked_balance
+                    .retain(|(locked_poll_id, _)| *locked_poll_id != poll_id);
+                bank_store(storage).save(voter, &token_manager)?;
+            }
+            None => skipped_voters += 1,
+        }
+
+        if commit_votes {
+            let voter_address = api.addr_humanize(&CanonicalAddr::from(voter.as_slice()))?;
+            let leaf = leaf_hash(voter_address.as_str(), &voter_info.vote, voter_info.balance);
+            append_leaf(storage, poll_id, a_poll.vote_leaf_count, leaf)?;
+            vote_leaf_store(storage, poll_id).save(
+                voter,
+                &VoteLeaf {
+                    index: a_poll.vote_leaf_count,
+                    vote: voter_info.vote.clone(),
+                    balance: voter_info.balance,
+                },
+            )?;
+            a_poll.vote_leaf_count += 1;
+        }
+
+        poll_voter_store(storage, poll_id).remove(voter);
+    }
+
+    let unlock_pending = voters.len() > limit;
+
+    // rounding leftovers go back to the participation pool,
+    // or to the stakers once participation rewards are disabled
+    if !unlock_pending && a_poll.participation_distributed < a_poll.participation_reward {
+        let remainder = a_poll.participation_reward - a_poll.participation_distributed;
+        let config: Config = config_read(storage).load()?;
+        state_store(storage).update(|mut state| -> StdResult<_> {
+            state.participation_rewards = state.participation_rewards.checked_sub(remainder)?;
+            if !config.participation_reward_ratio.is_zero() {
+                state.participation_pool += remainder;
+            }
+            Ok(state)
+        })?;
+        a_poll.participation_distributed = a_poll.participation_reward;
+    }
+
+    if commit_votes && !unlock_pending && a_poll.vote_root.is_none() && a_poll.vote_leaf_count > 0 {
+        let root = finalize_root(storage, poll_id, a_poll.vote_leaf_count)?;
+        a_poll.vote_root = Some(hex::encode(root));
+    }
+
+    Ok(UnlockProgress {
+        pending: unlock_pending,
+        skipped_voters,
+    })
+}
+
+/// ContinueUnlock is permissionless so that polls with
+/// many voters can be unwound over several transactions
+pub fn continue_unlock(deps: DepsMut, poll_id: u64) -> Result<Response, ContractError> {
+    let mut a_poll: Poll = poll_read(deps.storage).load(&poll_id.to_be_bytes())?;
+    if !a_poll.unlock_pending {
+        return Err(ContractError::NothingToUnlock {});
+    }
+
+    let progress =
+        unlock_votes_for_poll(deps.storage, deps.api, &mut a_poll, MAX_UNLOCK_VOTERS, true)?;
+    a_poll.unlock_pending = progress.pending;
+    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
+
+    let mut response = Response::new().add_attributes(vec![
+        ("action", "continue_unlock"),
+        ("poll_id", poll_id.to_string().as_str()),
+        ("unlock_pending", a_poll.unlock_pending.to_string().as_str()),
+    ]);
+
+    if progress.skipped_voters > 0 {
+        response = response.add_attribute("skipped_voters", progress.skipped_voters.to_string());
+    }
+
+    Ok(response)
+}
+
 fn send_tokens(
     deps: DepsMut,
     asset_token: &CanonicalAddr,
@@ -177,19 +642,21 @@ pub fn query_staker(deps: Deps, address: String) -> StdResult<StakerResponse> {
 
     // filter out not in-progress polls
     token_manager.locked_balance.retain(|(poll_id, _)| {
-        let poll: Poll = poll_read(deps.storage)
-            .load(&poll_id.to_be_bytes())
-            .unwrap();
-
-        poll.status == PollStatus::InProgress
+        match poll_read(deps.storage).may_load(&poll_id.to_be_bytes()) {
+            Ok(Some(poll)) => poll.status == PollStatus::InProgress,
+            _ => false,
+        }
     });
+    token_manager
+        .sponsored_balance
+        .retain(|(poll_id, _)| is_pending(deps.storage, *poll_id));
 
     let total_balance = query_token_balance(
         &deps.querier,
         deps.api.addr_humanize(&config.anchor_token)?,
         deps.api.addr_humanize(&state.contract_addr)?,
     )?
-    .checked_sub(state.total_deposit)?;
+    .checked_sub(state.reserved_amount())?;
 
     Ok(StakerResponse {
         balance: if !state.total_share.is_zero() {
@@ -201,5 +668,146 @@ pub fn query_staker(deps: Deps, address: String) -> StdResult<StakerResponse> {
         },
         share: token_manager.share,
         locked_balance: token_manager.locked_balance,
+        sponsored_balance: token_manager.sponsored_balance,
+    })
+}
+
+pub fn query_withdraw_status(deps: Deps, address: String) -> StdResult<WithdrawStatusResponse> {
+    let staker = query_staker(deps, address)?;
+    let locks = staker
+        .locked_balance
+        .iter()
+        .map(|(poll_id, voter_info)| {
+            let poll: Poll = poll_read(deps.storage).load(&poll_id.to_be_bytes())?;
+            Ok(WithdrawLockResponseItem {
+                poll_id: *poll_id,
+                amount: voter_info.balance,
+                end_height: poll.end_height,
+                // the end time of polls on the height clock is only an estimate
+                end_time: match poll.clock {
+                    PollClock::Time => Some(poll.end_time),
+                    PollClock::Height => None,
+                },
+            })
+        })
+        .chain(staker.sponsored_balance.iter().map(|(poll_id, weight)| {
+            let poll: Poll = poll_read(deps.storage).load(&poll_id.to_be_bytes())?;
+            Ok(WithdrawLockResponseItem {
+                poll_id: *poll_id,
+                amount: *weight,
+                end_height: poll.end_height,
+                // the sponsorship period always ends by height
+                end_time: None,
+            })
+        }))
+        .collect::<StdResult<Vec<WithdrawLockResponseItem>>>()?;
+
+    let locked_amount = locks
+        .iter()
+        .map(|lock| lock.amount)
+        .max()
+        .unwrap_or_default();
+
+    Ok(WithdrawStatusResponse {
+        balance: staker.balance,
+        locked_amount,
+        withdrawable_amount: staker.balance.saturating_sub(locked_amount),
+        earliest_full_withdrawal_height: locks
+            .iter()
+            .filter(|lock| lock.end_time.is_none())
+            .map(|lock| lock.end_height)
+            .max(),
+        earliest_full_withdrawal_time: locks.iter().filter_map(|lock| lock.end_time).max(),
+        locks,
+    })
+}
+
+pub fn query_staking_metrics(deps: Deps) -> StdResult<StakingMetricsResponse> {
+    let config: Config = config_read(deps.storage).load()?;
+    let state: State = state_read(deps.storage).load()?;
+
+    let anc_balance = query_token_balance(
+        &deps.querier,
+        deps.api.addr_humanize(&config.anchor_token)?,
+        deps.api.addr_humanize(&state.contract_addr)?,
+    )?;
+    let total_staked = anc_balance.checked_sub(state.reserved_amount())?;
+
+    let share_exchange_rate = share_rate(total_staked, state.total_share);
+
+    Ok(StakingMetricsResponse {
+        total_staked,
+        total_share: state.total_share,
+        share_exchange_rate,
+        total_deposited_in_polls: state.total_deposit,
+        anc_balance,
+        remaining_capacity: config
+            .deposit_cap
+            .map(|deposit_cap| deposit_cap.saturating_sub(state.total_principal)),
     })
 }
+
+pub fn query_share_rate_history(
+    deps: Deps,
+    limit: Option<u32>,
+) -> StdResult<ShareRateHistoryResponse> {
+    let count = share_rate_count_read(deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    let limit = limit
+        .map(u64::from)
+        .unwrap_or(SHARE_RATE_HISTORY_SIZE)
+        .min(SHARE_RATE_HISTORY_SIZE)
+        .min(count);
+
+    let checkpoints = (count - limit..count)
+        .rev()
+        .map(|index| {
+            let checkpoint = share_rate_read(deps.storage)
+                .load(&(index % SHARE_RATE_HISTORY_SIZE).to_be_bytes())?;
+            Ok(ShareRateCheckpointResponse {
+                time: checkpoint.time,
+                total_balance: checkpoint.total_balance,
+                total_share: checkpoint.total_share,
+                share_rate: share_rate(checkpoint.total_balance, checkpoint.total_share),
+            })
+        })
+        .collect::<StdResult<Vec<ShareRateCheckpointResponse>>>()?;
+
+    Ok(ShareRateHistoryResponse { checkpoints })
+}
+
+pub fn query_staker_rewards(deps: Deps, address: String) -> StdResult<StakerRewardsResponse> {
+    let addr_raw = deps.api.addr_canonicalize(&address)?;
+    let token_manager = bank_read(deps.storage)
+        .may_load(addr_raw.as_slice())?
+        .unwrap_or_default();
+
+    let mut rewards = read_reward_tokens(deps.storage)?
+        .into_iter()
+        .map(|(token_raw, reward_token)| {
+            let mut staker_reward = staker_reward_read(deps.storage, &addr_raw)
+                .may_load(token_raw.as_slice())?
+                .unwrap_or_default();
+            compute_staker_reward(&reward_token, &mut staker_reward, token_manager.share)?;
+
+            Ok(StakerRewardsResponseItem {
+                token: deps.api.addr_humanize(&token_raw)?.to_string(),
+                pending_reward: staker_reward.pending_reward,
+            })
+        })
+        .collect::<StdResult<Vec<StakerRewardsResponseItem>>>()?;
+
+    let participation_reward = participation_reward_read(deps.storage)
+        .may_load(addr_raw.as_slice())?
+        .unwrap_or_default();
+    if !participation_reward.is_zero() {
+        let config: Config = config_read(deps.storage).load()?;
+        rewards.push(StakerRewardsResponseItem {
+            token: deps.api.addr_humanize(&config.anchor_token)?.to_string(),
+            pending_reward: participation_reward,
+        });
+    }
+
+    Ok(StakerRewardsResponse { rewards })
+}
@@ -1,4 +1,4 @@
-use cosmwasm_std::{Binary, CanonicalAddr, Decimal, StdResult, Storage, Uint128};
+use cosmwasm_std::{Binary, CanonicalAddr, Decimal, Order, StdResult, Storage, Uint128};
 use cosmwasm_storage::{
     bucket, bucket_read, singleton, singleton_read, Bucket, ReadonlyBucket, ReadonlySingleton,
     Singleton,
@@ -7,17 +7,41 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use anchor_token::common::OrderBy;
-use anchor_token::gov::{PollStatus, VoterInfo};
+use anchor_token::gov::{PollClock, PollSpendMsg, PollStatus, VoteOption, VoterInfo, WeightMode};
 use std::cmp::Ordering;
 
-static KEY_CONFIG: &[u8] = b"config";
-static KEY_STATE: &[u8] = b"state";
+pub static KEY_CONFIG: &[u8] = b"config";
+pub static KEY_STATE: &[u8] = b"state";
 static KEY_TMP_POLL_ID: &[u8] = b"tmp_poll_id";
+static KEY_SHARE_RATE_COUNT: &[u8] = b"share_rate_count";
+static KEY_CONFIG_CHANGE_COUNT: &[u8] = b"config_change_count";
 
 static PREFIX_POLL_INDEXER: &[u8] = b"poll_indexer";
 static PREFIX_POLL_VOTER: &[u8] = b"poll_voter";
+static PREFIX_POLL_SPONSOR: &[u8] = b"poll_sponsor";
+static PREFIX_CREATOR_POLL: &[u8] = b"creator_poll";
+static PREFIX_VOTE_LEAF: &[u8] = b"vote_leaf";
+static PREFIX_VOTE_NODE: &[u8] = b"vote_node";
 static PREFIX_POLL: &[u8] = b"poll";
+static PREFIX_ARCHIVED_POLL: &[u8] = b"archived_poll";
 static PREFIX_BANK: &[u8] = b"bank";
+static PREFIX_REWARD_TOKEN: &[u8] = b"reward_token";
+static PREFIX_STAKER_REWARD: &[u8] = b"staker_reward";
+static PREFIX_PARTICIPATION_REWARD: &[u8] = b"participation_reward";
+static PREFIX_SHARE_RATE: &[u8] = b"share_rate";
+static PREFIX_CONFIG_CHANGE: &[u8] = b"config_change";
+static PREFIX_EPOCH_STATS: &[u8] = b"epoch_stats";
+static PREFIX_EPOCH_VOTER: &[u8] = b"epoch_voter";
+
+pub const DEFAULT_MAX_EXECUTE_MSG_SIZE: u64 = 4096;
+pub const DEFAULT_MAX_EXECUTE_MSGS: u64 = 16;
+pub const DEFAULT_ARCHIVE_AFTER: u64 = 5_256_000; // about a year of 6s blocks
+pub const DEFAULT_BLOCK_TIME: u64 = 6; // seconds
+pub const DEFAULT_EPOCH_LENGTH: u64 = 604_800; // a week in seconds
+pub const DEFAULT_MAX_RESUBMIT_DEPTH: u32 = 1;
+pub const DEFAULT_EXPIRED_DEPOSIT_REFUND_PERCENT: u64 = 50;
+// 1k, 10k and 100k ANC
+pub const DEFAULT_VOTE_WEIGHT_BUCKETS: [u128; 3] = [1_000_000_000, 10_000_000_000, 100_000_000_000];
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
@@ -30,6 +54,41 @@ pub struct Config {
     pub expiration_period: u64,
     pub proposal_deposit: Uint128,
     pub snapshot_period: u64,
+    pub sponsorship_threshold: Uint128, // zero disables the sponsorship stage
+    pub sponsorship_period: u64,
+    pub max_execute_msg_size: u64, // in bytes, per execute message
+    pub max_execute_msgs: u64,
+    pub min_stake_amount: Uint128,
+    pub stake_depositors: Vec<CanonicalAddr>, // contracts allowed to stake on behalf of others
+    pub protocol_addresses: ProtocolAddresses,
+    pub max_spend_per_poll: Uint128,
+    pub archive_after: u64, // blocks after the end of a finished poll until it can be archived
+    pub poll_clock: PollClock, // applies to polls created afterwards
+    pub block_time: u64,    // average seconds per block
+    pub late_quorum_window: u64, // blocks before the end of voting where reaching quorum extends it
+    pub late_quorum_extension: u64, // blocks, zero disables the extension
+    pub participation_reward_ratio: Decimal, // of the participation pool given to each poll reaching quorum
+    pub execution_denylist: Vec<CanonicalAddr>, // contracts polls may not execute messages on
+    pub epoch_length: u64, // seconds per statistics epoch, applies from the next epoch
+    pub max_open_polls_per_creator: u32, // zero for no limit
+    pub min_creator_stake: Uint128, // required to create polls executing messages
+    pub vote_weight_buckets: Vec<Uint128>, // ascending boundaries, applies to polls created afterwards
+    pub resubmit_window: u64, // blocks after a quorum failure, zero to disable resubmission
+    pub resubmit_deposit: Uint128, // capped at proposal_deposit
+    pub max_resubmit_depth: u32, // resubmissions in a chain of polls
+    pub deposit_cap: Option<Uint128>, // on the staked principal, none for no cap
+    pub atomic_batch: bool,   // whether a failing vote of CastVotes reverts the batch
+    pub expired_deposit_refund_ratio: Decimal, // of the deposit of a poll not sponsored in time
+}
+
+/// ProtocolAddresses is the registry of the other protocol contracts,
+/// which every lookup of them reads from
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct ProtocolAddresses {
+    pub community_fund: Option<CanonicalAddr>,
+    pub collector: Option<CanonicalAddr>,
+    pub voting_escrow: Option<CanonicalAddr>,
+    pub gauge_controller: Option<CanonicalAddr>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -38,12 +97,76 @@ pub struct State {
     pub poll_count: u64,
     pub total_share: Uint128,
     pub total_deposit: Uint128,
+    pub polls_in_progress: u64,
+    pub polls_awaiting_execution: u64, // passed polls not yet executed or failed
+    /// Slashed deposits waiting to be given to voters
+    #[serde(default)]
+    pub participation_pool: Uint128,
+    /// Participation rewards given to polls but not claimed yet
+    #[serde(default)]
+    pub participation_rewards: Uint128,
+    /// Staked tokens without the rewards they earned, which the deposit cap applies to
+    #[serde(default)]
+    pub total_principal: Uint128,
+}
+
+impl State {
+    /// reserved_amount is the ANC held by the contract which is not staked
+    pub fn reserved_amount(&self) -> Uint128 {
+        self.total_deposit + self.participation_pool + self.participation_rewards
+    }
 }
 
 #[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TokenManager {
     pub share: Uint128,                        // total staked balance
     pub locked_balance: Vec<(u64, VoterInfo)>, // maps poll_id to weight voted
+    #[serde(default)]
+    pub sponsored_balance: Vec<(u64, Uint128)>, // maps pending poll_id to weight sponsored
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardToken {
+    pub global_reward_index: Decimal,
+}
+
+#[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakerReward {
+    pub reward_index: Decimal,
+    pub pending_reward: Uint128,
+}
+
+/// EpochStats aggregates the staking and voting activity of an epoch.
+/// An epoch is final once a later one exists.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EpochStats {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub total_staked: Uint128, // at the start of the epoch
+    pub polls_created: u64,
+    pub polls_ended: u64,
+    pub yes_votes: Uint128,
+    pub no_votes: Uint128,
+    pub unique_voters: u64,
+}
+
+/// ConfigChange records who changed which config fields and when
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigChange {
+    pub id: u64,
+    pub height: u64,
+    pub time: u64,
+    pub sender: CanonicalAddr,
+    pub poll_id: Option<u64>,
+    pub changed_fields: Vec<String>,
+}
+
+/// ShareRateCheckpoint records the staked balance and shares at a point in time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ShareRateCheckpoint {
+    pub time: u64,
+    pub total_balance: Uint128,
+    pub total_share: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -53,6 +176,7 @@ pub struct Poll {
     pub status: PollStatus,
     pub yes_votes: Uint128,
     pub no_votes: Uint128,
+    /// End of the sponsorship stage while the poll is pending, end of voting afterwards
     pub end_height: u64,
     pub title: String,
     pub description: String,
@@ -62,6 +186,95 @@ pub struct Poll {
     /// Total balance at the end poll
     pub total_balance_at_end_poll: Option<Uint128>,
     pub staked_amount: Option<Uint128>,
+    #[serde(default)]
+    pub sponsor_weight: Uint128,
+    /// Set when ended with more voters than can be unlocked in one transaction
+    #[serde(default)]
+    pub unlock_pending: bool,
+    #[serde(default)]
+    pub amended: bool,
+    #[serde(default = "default_weight_mode")]
+    pub weight_mode: WeightMode,
+    /// Tally after applying the weight mode
+    #[serde(default)]
+    pub weighted_yes_votes: Uint128,
+    #[serde(default)]
+    pub weighted_no_votes: Uint128,
+    /// Estimated from the block time while the clock is Height
+    #[serde(default)]
+    pub end_time: u64,
+    #[serde(default = "default_poll_clock")]
+    pub clock: PollClock,
+    /// Votes committed to the vote tree so far
+    #[serde(default)]
+    pub vote_leaf_count: u64,
+    #[serde(default)]
+    pub vote_root: Option<String>,
+    /// Voting can be extended only once
+    #[serde(default)]
+    pub extended: bool,
+    /// Participation reward shared by the voters pro-rata to their vote
+    #[serde(default)]
+    pub participation_reward: Uint128,
+    #[serde(default)]
+    pub participation_distributed: Uint128,
+    /// Bucket boundaries of the config at creation, empty for older polls
+    #[serde(default)]
+    pub weight_buckets: Vec<Uint128>,
+    /// Voters per bucket, one more than the boundaries
+    #[serde(default)]
+    pub weight_bucket_voters: Vec<u64>,
+    #[serde(default)]
+    pub last_vote_height: u64,
+    /// Height of the end poll when the quorum was not reached
+    #[serde(default)]
+    pub quorum_failed_at: Option<u64>,
+    #[serde(default)]
+    pub resubmitted_from: Option<u64>,
+    #[serde(default)]
+    pub resubmitted_as: Option<u64>,
+    /// Resubmissions since the first poll of the chain
+    #[serde(default)]
+    pub resubmit_depth: u32,
+    /// Community fund transfer sent as the last execute message
+    #[serde(default)]
+    pub spend: Option<PollSpendMsg>,
+}
+
+pub fn default_vote_weight_buckets() -> Vec<Uint128> {
+    DEFAULT_VOTE_WEIGHT_BUCKETS
+        .iter()
+        .map(|boundary| Uint128::from(*boundary))
+        .collect()
+}
+
+fn default_weight_mode() -> WeightMode {
+    WeightMode::Linear
+}
+
+fn default_poll_clock() -> PollClock {
+    PollClock::Height
+}
+
+/// VoteLeaf is the position and content of a vote in the vote tree of its poll
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VoteLeaf {
+    pub index: u64,
+    pub vote: VoteOption,
+    pub balance: Uint128,
+}
+
+/// ArchivedPoll is the summary kept for a finished poll after its records are cleared
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ArchivedPoll {
+    pub id: u64,
+    pub status: PollStatus,
+    pub yes_votes: Uint128,
+    pub no_votes: Uint128,
+    pub end_height: u64,
+    pub execute_hash: Option<String>,
+    #[serde(default)]
+    pub last_vote_height: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
@@ -122,6 +335,14 @@ pub fn poll_read(storage: &dyn Storage) -> ReadonlyBucket<Poll> {
     bucket_read(storage, PREFIX_POLL)
 }
 
+pub fn archived_poll_store(storage: &mut dyn Storage) -> Bucket<ArchivedPoll> {
+    bucket(storage, PREFIX_ARCHIVED_POLL)
+}
+
+pub fn archived_poll_read(storage: &dyn Storage) -> ReadonlyBucket<ArchivedPoll> {
+    bucket_read(storage, PREFIX_ARCHIVED_POLL)
+}
+
 pub fn poll_indexer_store<'a>(
     storage: &'a mut dyn Storage,
     status: &PollStatus,
@@ -132,6 +353,16 @@ pub fn poll_indexer_store<'a>(
     )
 }
 
+pub fn poll_indexer_read<'a>(
+    storage: &'a dyn Storage,
+    status: &PollStatus,
+) -> ReadonlyBucket<'a, bool> {
+    ReadonlyBucket::multilevel(
+        storage,
+        &[PREFIX_POLL_INDEXER, status.to_string().as_bytes()],
+    )
+}
+
 pub fn poll_voter_store(storage: &mut dyn Storage, poll_id: u64) -> Bucket<VoterInfo> {
     Bucket::multilevel(storage, &[PREFIX_POLL_VOTER, &poll_id.to_be_bytes()])
 }
@@ -140,6 +371,56 @@ pub fn poll_voter_read(storage: &dyn Storage, poll_id: u64) -> ReadonlyBucket<Vo
     ReadonlyBucket::multilevel(storage, &[PREFIX_POLL_VOTER, &poll_id.to_be_bytes()])
 }
 
+pub fn poll_sponsor_store(storage: &mut dyn Storage, poll_id: u64) -> Bucket<Uint128> {
+    Bucket::multilevel(storage, &[PREFIX_POLL_SPONSOR, &poll_id.to_be_bytes()])
+}
+
+pub fn poll_sponsor_read(storage: &dyn Storage, poll_id: u64) -> ReadonlyBucket<Uint128> {
+    ReadonlyBucket::multilevel(storage, &[PREFIX_POLL_SPONSOR, &poll_id.to_be_bytes()])
+}
+
+pub fn vote_leaf_store(storage: &mut dyn Storage, poll_id: u64) -> Bucket<VoteLeaf> {
+    Bucket::multilevel(storage, &[PREFIX_VOTE_LEAF, &poll_id.to_be_bytes()])
+}
+
+pub fn vote_leaf_read(storage: &dyn Storage, poll_id: u64) -> ReadonlyBucket<VoteLeaf> {
+    ReadonlyBucket::multilevel(storage, &[PREFIX_VOTE_LEAF, &poll_id.to_be_bytes()])
+}
+
+/// vote nodes are keyed by their level in the tree followed by their index in the level
+pub fn vote_node_store(storage: &mut dyn Storage, poll_id: u64) -> Bucket<Binary> {
+    Bucket::multilevel(storage, &[PREFIX_VOTE_NODE, &poll_id.to_be_bytes()])
+}
+
+pub fn vote_node_read(storage: &dyn Storage, poll_id: u64) -> ReadonlyBucket<Binary> {
+    ReadonlyBucket::multilevel(storage, &[PREFIX_VOTE_NODE, &poll_id.to_be_bytes()])
+}
+
+pub fn read_poll_sponsors<'a>(
+    storage: &'a dyn Storage,
+    poll_id: u64,
+    start_after: Option<CanonicalAddr>,
+    limit: Option<u32>,
+    order_by: Option<OrderBy>,
+) -> StdResult<Vec<(CanonicalAddr, Uint128)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let (start, end, order_by) = match order_by {
+        Some(OrderBy::Asc) => (calc_range_start_addr(start_after), None, OrderBy::Asc),
+        _ => (None, calc_range_end_addr(start_after), OrderBy::Desc),
+    };
+
+    let sponsors: ReadonlyBucket<'a, Uint128> =
+        ReadonlyBucket::multilevel(storage, &[PREFIX_POLL_SPONSOR, &poll_id.to_be_bytes()]);
+    sponsors
+        .range(start.as_deref(), end.as_deref(), order_by.into())
+        .take(limit)
+        .map(|item| {
+            let (k, v) = item?;
+            Ok((CanonicalAddr::from(k), v))
+        })
+        .collect()
+}
+
 pub fn read_poll_voters<'a>(
     storage: &'a dyn Storage,
     poll_id: u64,
@@ -165,6 +446,24 @@ pub fn read_poll_voters<'a>(
         .collect()
 }
 
+/// read_poll_ids returns the ids of polls with the given status in ascending order
+pub fn read_poll_ids(
+    storage: &dyn Storage,
+    status: &PollStatus,
+    limit: usize,
+) -> StdResult<Vec<u64>> {
+    poll_indexer_read(storage, status)
+        .range(None, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, _) = item?;
+            let mut poll_id = [0u8; 8];
+            poll_id.copy_from_slice(&k);
+            Ok(u64::from_be_bytes(poll_id))
+        })
+        .collect()
+}
+
 const MAX_LIMIT: u32 = 30;
 const DEFAULT_LIMIT: u32 = 10;
 pub fn read_polls<'a>(
@@ -242,3 +541,160 @@ fn calc_range_start_addr(start_after: Option<CanonicalAddr>) -> Option<Vec<u8>>
 fn calc_range_end_addr(start_after: Option<CanonicalAddr>) -> Option<Vec<u8>> {
     start_after.map(|addr| addr.as_slice().to_vec())
 }
+
+pub fn reward_token_store(storage: &mut dyn Storage) -> Bucket<RewardToken> {
+    bucket(storage, PREFIX_REWARD_TOKEN)
+}
+
+pub fn reward_token_read(storage: &dyn Storage) -> ReadonlyBucket<RewardToken> {
+    bucket_read(storage, PREFIX_REWARD_TOKEN)
+}
+
+pub fn read_reward_tokens(storage: &dyn Storage) -> StdResult<Vec<(CanonicalAddr, RewardToken)>> {
+    reward_token_read(storage)
+        .range(None, None, Order::Ascending)
+        .map(|item| {
+            let (k, v) = item?;
+            Ok((CanonicalAddr::from(k), v))
+        })
+        .collect()
+}
+
+pub fn staker_reward_store<'a>(
+    storage: &'a mut dyn Storage,
+    staker: &CanonicalAddr,
+) -> Bucket<'a, StakerReward> {
+    Bucket::multilevel(storage, &[PREFIX_STAKER_REWARD, staker.as_slice()])
+}
+
+pub fn staker_reward_read<'a>(
+    storage: &'a dyn Storage,
+    staker: &CanonicalAddr,
+) -> ReadonlyBucket<'a, StakerReward> {
+    ReadonlyBucket::multilevel(storage, &[PREFIX_STAKER_REWARD, staker.as_slice()])
+}
+
+pub fn participation_reward_store(storage: &mut dyn Storage) -> Bucket<Uint128> {
+    bucket(storage, PREFIX_PARTICIPATION_REWARD)
+}
+
+pub fn participation_reward_read(storage: &dyn Storage) -> ReadonlyBucket<Uint128> {
+    bucket_read(storage, PREFIX_PARTICIPATION_REWARD)
+}
+
+/// share_rate_count is the number of share rate checkpoints ever recorded
+pub fn share_rate_count_store(storage: &mut dyn Storage) -> Singleton<u64> {
+    singleton(storage, KEY_SHARE_RATE_COUNT)
+}
+
+pub fn share_rate_count_read(storage: &dyn Storage) -> ReadonlySingleton<u64> {
+    singleton_read(storage, KEY_SHARE_RATE_COUNT)
+}
+
+/// share rate checkpoints are kept in a ring buffer keyed by slot
+pub fn share_rate_store(storage: &mut dyn Storage) -> Bucket<ShareRateCheckpoint> {
+    bucket(storage, PREFIX_SHARE_RATE)
+}
+
+pub fn share_rate_read(storage: &dyn Storage) -> ReadonlyBucket<ShareRateCheckpoint> {
+    bucket_read(storage, PREFIX_SHARE_RATE)
+}
+
+pub fn epoch_stats_store(storage: &mut dyn Storage) -> Bucket<EpochStats> {
+    bucket(storage, PREFIX_EPOCH_STATS)
+}
+
+pub fn epoch_stats_read(storage: &dyn Storage) -> ReadonlyBucket<EpochStats> {
+    bucket_read(storage, PREFIX_EPOCH_STATS)
+}
+
+/// epoch_voter maps a voter to the start of the last epoch they voted in,
+/// so unique voters are counted with a single entry per voter
+pub fn epoch_voter_store(storage: &mut dyn Storage) -> Bucket<u64> {
+    bucket(storage, PREFIX_EPOCH_VOTER)
+}
+
+pub fn epoch_voter_read(storage: &dyn Storage) -> ReadonlyBucket<u64> {
+    bucket_read(storage, PREFIX_EPOCH_VOTER)
+}
+
+pub fn read_latest_epoch(storage: &dyn Storage) -> StdResult<Option<EpochStats>> {
+    epoch_stats_read(storage)
+        .range(None, None, Order::Descending)
+        .next()
+        .map(|item| item.map(|(_, epoch)| epoch))
+        .transpose()
+}
+
+pub fn read_epochs(
+    storage: &dyn Storage,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<EpochStats>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = calc_range_start(start_after);
+
+    epoch_stats_read(storage)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, epoch)| epoch))
+        .collect()
+}
+
+/// creator_poll holds the open polls of a creator, which are not rejected, executed,
+/// failed or expired yet
+pub fn creator_poll_store<'a>(
+    storage: &'a mut dyn Storage,
+    creator: &CanonicalAddr,
+) -> Bucket<'a, bool> {
+    Bucket::multilevel(storage, &[PREFIX_CREATOR_POLL, creator.as_slice()])
+}
+
+pub fn read_creator_polls(
+    storage: &dyn Storage,
+    creator: &CanonicalAddr,
+    limit: usize,
+) -> StdResult<Vec<u64>> {
+    ReadonlyBucket::<bool>::multilevel(storage, &[PREFIX_CREATOR_POLL, creator.as_slice()])
+        .range(None, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, _) = item?;
+            let mut poll_id = [0u8; 8];
+            poll_id.copy_from_slice(&k);
+            Ok(u64::from_be_bytes(poll_id))
+        })
+        .collect()
+}
+
+/// config_change_count is the number of config changes ever recorded
+pub fn config_change_count_store(storage: &mut dyn Storage) -> Singleton<u64> {
+    singleton(storage, KEY_CONFIG_CHANGE_COUNT)
+}
+
+pub fn config_change_count_read(storage: &dyn Storage) -> ReadonlySingleton<u64> {
+    singleton_read(storage, KEY_CONFIG_CHANGE_COUNT)
+}
+
+pub fn config_change_store(storage: &mut dyn Storage) -> Bucket<ConfigChange> {
+    bucket(storage, PREFIX_CONFIG_CHANGE)
+}
+
+pub fn config_change_read(storage: &dyn Storage) -> ReadonlyBucket<ConfigChange> {
+    bucket_read(storage, PREFIX_CONFIG_CHANGE)
+}
+
+pub fn read_config_changes(
+    storage: &dyn Storage,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<ConfigChange>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = calc_range_start(start_after);
+
+    config_change_read(storage)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, change)| change))
+        .collect()
+}
@@ -1,24 +1,41 @@
-use crate::contract::{execute, instantiate, query, reply};
+use crate::contract::{execute, instantiate, migrate, query, reply};
 use crate::error::ContractError;
-use crate::mock_querier::mock_dependencies;
+use crate::migration::{LegacyConfig, LegacyState};
+use crate::mock_querier::{mock_dependencies, WasmMockQuerier};
 use crate::state::{
-    bank_read, bank_store, config_read, poll_store, poll_voter_read, poll_voter_store, state_read,
-    Config, Poll, State, TokenManager,
+    bank_read, bank_store, config_read, poll_indexer_read, poll_indexer_store, poll_read,
+    poll_store, poll_voter_read, poll_voter_store, read_creator_polls, state_read, state_store,
+    vote_leaf_read, Config, Poll, ProtocolAddresses, State, TokenManager, KEY_CONFIG, KEY_STATE,
 };
 
 use anchor_token::common::OrderBy;
+use anchor_token::community::{
+    ConfigResponse as CommunityConfigResponse, ExecuteMsg as CommunityExecuteMsg,
+};
 use anchor_token::gov::{
-    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, PollExecuteMsg, PollResponse,
-    PollStatus, PollsResponse, QueryMsg, StakerResponse, VoteOption, VoterInfo, VotersResponse,
-    VotersResponseItem,
+    ArchivedPollResponse, ConfigHistoryResponse, ConfigHistoryResponseItem, ConfigResponse,
+    ConfigUpdate, Cw20HookMsg, EpochStatsResponse, EpochStatsResponseItem, ExecuteMsg,
+    ExecutionVerdict, InstantiateMsg, MigrateMsg, PollClock, PollExecuteMsg,
+    PollExecuteMsgsResponse, PollExecuteMsgsResponseItem, PollProgressResponse, PollResponse,
+    PollSpendMsg, PollStatus, PollTallyDigestResponse, PollTallyDigestResponseItem, PollVoteMsg,
+    PollVoteProofResponse, PollWeightDistributionResponse, PollsResponse, ProtocolAddressesMsg,
+    ProtocolAddressesResponse, QueryMsg, ShareRateCheckpointResponse, ShareRateHistoryResponse,
+    SimulatePollExecutionResponse, SimulatePollExecutionResponseItem, SponsorsResponse,
+    SponsorsResponseItem, StakerResponse, StakerRewardsResponse, StakerRewardsResponseItem,
+    StakingMetricsResponse, StateResponse, VoteOption, VoterInfo, VotersResponse,
+    VotersResponseItem, WeightBucketResponse, WeightMode, WithdrawLockResponseItem,
+    WithdrawStatusResponse,
 };
 use astroport::querier::query_token_balance;
-use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    attr, coins, from_binary, to_binary, Addr, Api, CanonicalAddr, ContractResult, CosmosMsg,
-    Decimal, Deps, DepsMut, Env, Reply, Response, StdError, SubMsg, Timestamp, Uint128, WasmMsg,
+    attr, coins, from_binary, to_binary, Addr, Api, Binary, CanonicalAddr, ContractResult,
+    CosmosMsg, Decimal, Deps, DepsMut, Env, Order, OwnedDeps, Reply, ReplyOn, Response, StdError,
+    Storage, SubMsg, Timestamp, Uint128, WasmMsg,
 };
+use cosmwasm_storage::Singleton;
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use sha2::{Digest, Sha256};
 
 const VOTING_TOKEN: &str = "voting_token";
 const TEST_CREATOR: &str = "creator";
@@ -95,7 +112,36 @@ fn proper_initialization() {
             timelock_period: DEFAULT_TIMELOCK_PERIOD,
             expiration_period: 0u64, // Deprecated
             proposal_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
-            snapshot_period: DEFAULT_FIX_PERIOD
+            snapshot_period: DEFAULT_FIX_PERIOD,
+            sponsorship_threshold: Uint128::zero(),
+            sponsorship_period: 0u64,
+            max_execute_msg_size: 4096u64,
+            max_execute_msgs: 16u64,
+            min_stake_amount: Uint128::zero(),
+            stake_depositors: vec![],
+            protocol_addresses: ProtocolAddresses::default(),
+            max_spend_per_poll: Uint128::zero(),
+            archive_after: 5_256_000u64,
+            poll_clock: PollClock::Height,
+            block_time: 6u64,
+            late_quorum_window: 0u64,
+            late_quorum_extension: 0u64,
+            participation_reward_ratio: Decimal::zero(),
+            execution_denylist: vec![],
+            epoch_length: 604_800u64,
+            max_open_polls_per_creator: 0u32,
+            min_creator_stake: Uint128::zero(),
+            vote_weight_buckets: vec![
+                Uint128::from(1_000_000_000u128),
+                Uint128::from(10_000_000_000u128),
+                Uint128::from(100_000_000_000u128),
+            ],
+            resubmit_window: 0u64,
+            resubmit_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+            max_resubmit_depth: 1u32,
+            deposit_cap: None,
+            atomic_batch: true,
+            expired_deposit_refund_ratio: Decimal::percent(50),
         }
     );
 
@@ -117,6 +163,11 @@ fn proper_initialization() {
             poll_count: 0,
             total_share: Uint128::zero(),
             total_deposit: Uint128::zero(),
+            polls_in_progress: 0,
+            polls_awaiting_execution: 0,
+            participation_pool: Uint128::zero(),
+            participation_rewards: Uint128::zero(),
+            total_principal: Uint128::zero(),
         }
     );
 }
@@ -153,8 +204,9 @@ fn fails_init_invalid_quorum() {
 
     match res {
         Ok(_) => panic!("Must return error"),
-        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => {
-            assert_eq!(msg, "quorum must be 0 to 1")
+        Err(ContractError::InvalidConfig(field, allowed)) => {
+            assert_eq!(field, "quorum");
+            assert_eq!(allowed, "greater than 0 and at most 1");
         }
         Err(e) => panic!("Unexpected error: {:?}", e),
     }
@@ -177,13 +229,58 @@ fn fails_init_invalid_threshold() {
 
     match res {
         Ok(_) => panic!("Must return error"),
-        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => {
-            assert_eq!(msg, "threshold must be 0 to 1")
+        Err(ContractError::InvalidConfig(field, allowed)) => {
+            assert_eq!(field, "threshold");
+            assert_eq!(allowed, "greater than 0 and at most 1");
         }
         Err(e) => panic!("Unexpected error: {:?}", e),
     }
 }
 
+#[test]
+fn fails_init_invalid_config() {
+    let invalid_msgs = [
+        (
+            InstantiateMsg {
+                voting_period: 0,
+                ..instantiate_msg()
+            },
+            "voting_period",
+        ),
+        (
+            InstantiateMsg {
+                timelock_period: 2_592_001,
+                ..instantiate_msg()
+            },
+            "timelock_period",
+        ),
+        (
+            InstantiateMsg {
+                snapshot_period: DEFAULT_VOTING_PERIOD + 1,
+                ..instantiate_msg()
+            },
+            "snapshot_period",
+        ),
+        (
+            InstantiateMsg {
+                proposal_deposit: Uint128::zero(),
+                ..instantiate_msg()
+            },
+            "proposal_deposit",
+        ),
+    ];
+
+    for (msg, invalid_field) in invalid_msgs {
+        let mut deps = mock_dependencies(&[]);
+        let info = mock_info(TEST_CREATOR, &[]);
+        match instantiate(deps.as_mut(), mock_env(), info, msg) {
+            Ok(_) => panic!("Must return error"),
+            Err(ContractError::InvalidConfig(field, _)) => assert_eq!(field, invalid_field),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+}
+
 #[test]
 fn fails_contract_already_registered() {
     let mut deps = mock_dependencies(&[]);
@@ -312,6 +409,103 @@ fn fails_create_poll_invalid_link() {
     }
 }
 
+#[test]
+fn fails_create_poll_invalid_execute_msgs() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+    let info = mock_info(VOTING_TOKEN, &[]);
+
+    let execute_msg = |order: u64, size: usize| PollExecuteMsg {
+        order,
+        contract: VOTING_TOKEN.to_string(),
+        msg: Binary::from(vec![order as u8; size]),
+    };
+
+    // payload at the size limit is accepted
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(vec![execute_msg(1, 4096)]),
+    );
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // payload over the size limit is rejected with its index
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(vec![execute_msg(1, 10), execute_msg(2, 4097)]),
+    );
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::ExecuteMsgTooLarge(1, 4096)) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    // too many messages
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some((1..=17).map(|order| execute_msg(order, 10)).collect()),
+    );
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::TooManyExecuteMsgs(16)) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    // same contract and payload twice, regardless of order
+    let mut duplicate = execute_msg(1, 10);
+    duplicate.order = 3;
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(vec![execute_msg(1, 10), execute_msg(2, 10), duplicate]),
+    );
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::DuplicateExecuteMsg(2)) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    // messages to known contracts are compared by their content, not their formatting
+    let burn = |msg: &str| PollExecuteMsg {
+        order: 1,
+        contract: VOTING_TOKEN.to_string(),
+        msg: Binary::from(msg.as_bytes()),
+    };
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(vec![
+            burn(r#"{"burn":{"amount":"123"}}"#),
+            burn(r#"{ "burn": { "amount": "123" } }"#),
+        ]),
+    );
+    match execute(deps.as_mut(), mock_env(), info, msg) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::DuplicateExecuteMsg(1)) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    // the spend counts toward the message limit
+    let msg = create_spend_poll_msg(
+        Some((1..=16).map(|order| execute_msg(order, 10)).collect()),
+        TEST_VOTER,
+        100,
+    );
+    match execute(deps.as_mut(), mock_env(), mock_info(VOTING_TOKEN, &[]), msg) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::TooManyExecuteMsgs(16)) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+}
+
 #[test]
 fn fails_create_poll_invalid_deposit() {
     let mut deps = mock_dependencies(&[]);
@@ -326,6 +520,8 @@ fn fails_create_poll_invalid_deposit() {
             description: "TESTTEST".to_string(),
             link: None,
             execute_msgs: None,
+            spend: None,
+            weight_mode: None,
         })
         .unwrap(),
     });
@@ -352,6 +548,8 @@ fn create_poll_msg(
             description,
             link,
             execute_msgs: execute_msg,
+            spend: None,
+            weight_mode: None,
         })
         .unwrap(),
     })
@@ -377,6 +575,147 @@ fn happy_days_create_poll() {
     );
 }
 
+#[test]
+fn create_poll_min_creator_stake() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let msg = update_config_msg(|update| {
+        update.min_creator_stake = Some(Uint128::from(100u128));
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+
+    let set_balance = |querier: &mut WasmMockQuerier, amount: u128| {
+        querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(amount))],
+        )]);
+    };
+
+    // text polls need no stake
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    set_balance(&mut deps.querier, DEFAULT_PROPOSAL_DEPOSIT + 99);
+    mock_stake(deps.as_mut(), TEST_CREATOR, 99);
+
+    let execute_msgs = vec![PollExecuteMsg {
+        order: 1u64,
+        contract: VOTING_TOKEN.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Burn {
+            amount: Uint128::new(123),
+        })
+        .unwrap(),
+    }];
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(execute_msgs),
+    );
+
+    set_balance(&mut deps.querier, 2 * DEFAULT_PROPOSAL_DEPOSIT + 99);
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone());
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::InsufficientCreatorStake(100, 1)
+    );
+
+    set_balance(&mut deps.querier, DEFAULT_PROPOSAL_DEPOSIT + 100);
+    mock_stake(deps.as_mut(), TEST_CREATOR, 1);
+
+    set_balance(&mut deps.querier, 2 * DEFAULT_PROPOSAL_DEPOSIT + 100);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(res.attributes[1], attr("creator", TEST_CREATOR));
+}
+
+#[test]
+fn amend_poll_before_votes() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let msg = create_poll_msg("test".to_string(), "tset".to_string(), None, None);
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(0, 10000),
+        mock_info(VOTING_TOKEN, &[]),
+        msg,
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::AmendPoll {
+        poll_id: 1,
+        description: "test".to_string(),
+        link: Some("http://google.com".to_string()),
+    };
+    match execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_VOTER, &[]),
+        msg.clone(),
+    ) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::Unauthorized {}) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_CREATOR, &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "amend_poll"),
+            attr("poll_id", "1"),
+            attr("old_description_hash", hex::encode(Sha256::digest(b"tset"))),
+            attr("new_description_hash", hex::encode(Sha256::digest(b"test"))),
+        ]
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let value: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(value.title, "test");
+    assert_eq!(value.description, "test");
+    assert_eq!(value.link, Some("http://google.com".to_string()));
+    assert!(value.amended);
+
+    // no more amendments once a vote is cast
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(10u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    mock_stake(deps.as_mut(), TEST_VOTER, 10);
+
+    let vote_msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::No,
+        amount: Uint128::from(10u128),
+    };
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(0, 10000),
+        mock_info(TEST_VOTER, &[]),
+        vote_msg,
+    )
+    .unwrap();
+
+    match execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::PollAlreadyVoted {}) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+}
+
 #[test]
 fn query_polls() {
     let mut deps = mock_dependencies(&[]);
@@ -458,6 +797,14 @@ fn query_polls() {
                 no_votes: Uint128::zero(),
                 staked_amount: None,
                 total_balance_at_end_poll: None,
+                amended: false,
+                weight_mode: WeightMode::Linear,
+                end_time: 130000u64,
+                clock: PollClock::Height,
+                vote_root: None,
+                extended: false,
+                resubmitted_from: None,
+                resubmitted_as: None,
             },
             PollResponse {
                 id: 2u64,
@@ -473,6 +820,14 @@ fn query_polls() {
                 no_votes: Uint128::zero(),
                 staked_amount: None,
                 total_balance_at_end_poll: None,
+                amended: false,
+                weight_mode: WeightMode::Linear,
+                end_time: 130000u64,
+                clock: PollClock::Height,
+                vote_root: None,
+                extended: false,
+                resubmitted_from: None,
+                resubmitted_as: None,
             },
         ]
     );
@@ -505,6 +860,14 @@ fn query_polls() {
             no_votes: Uint128::zero(),
             staked_amount: None,
             total_balance_at_end_poll: None,
+            amended: false,
+            weight_mode: WeightMode::Linear,
+            end_time: 130000u64,
+            clock: PollClock::Height,
+            vote_root: None,
+            extended: false,
+            resubmitted_from: None,
+            resubmitted_as: None,
         },]
     );
 
@@ -536,6 +899,14 @@ fn query_polls() {
             no_votes: Uint128::zero(),
             staked_amount: None,
             total_balance_at_end_poll: None,
+            amended: false,
+            weight_mode: WeightMode::Linear,
+            end_time: 130000u64,
+            clock: PollClock::Height,
+            vote_root: None,
+            extended: false,
+            resubmitted_from: None,
+            resubmitted_as: None,
         }]
     );
 
@@ -567,6 +938,14 @@ fn query_polls() {
             no_votes: Uint128::zero(),
             staked_amount: None,
             total_balance_at_end_poll: None,
+            amended: false,
+            weight_mode: WeightMode::Linear,
+            end_time: 130000u64,
+            clock: PollClock::Height,
+            vote_root: None,
+            extended: false,
+            resubmitted_from: None,
+            resubmitted_as: None,
         },]
     );
 
@@ -721,7 +1100,7 @@ fn happy_days_end_poll() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
         amount: Uint128::from(stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
@@ -803,6 +1182,13 @@ fn happy_days_end_poll() {
         }))]
     );
 
+    // passed poll is queued for execution
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let response: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(response.polls_in_progress, 0);
+    assert_eq!(response.polls_awaiting_execution, vec![1]);
+    assert!(!response.truncated);
+
     // End poll will withdraw deposit balance
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
@@ -869,6 +1255,10 @@ fn happy_days_end_poll() {
         vec![attr("action", "execute_poll"), attr("poll_id", "1"),]
     );
 
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let response: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(response.polls_awaiting_execution, Vec::<u64>::new());
+
     // Query executed polls
     let res = query(
         deps.as_ref(),
@@ -942,36 +1332,22 @@ fn happy_days_end_poll() {
         StakerResponse {
             balance: Uint128::from(stake_amount),
             share: Uint128::from(stake_amount),
-            locked_balance: vec![]
+            locked_balance: vec![],
+            sponsored_balance: vec![],
         }
     );
 
-    // But the data is still in the store
+    // and the votes are released from the store by end poll
     let voter_addr_raw = deps.api.addr_canonicalize(TEST_VOTER).unwrap();
     let voter = poll_voter_read(&deps.storage, 1u64)
-        .load(voter_addr_raw.as_slice())
+        .may_load(voter_addr_raw.as_slice())
         .unwrap();
-    assert_eq!(
-        voter,
-        VoterInfo {
-            vote: VoteOption::Yes,
-            balance: Uint128::from(stake_amount),
-        }
-    );
+    assert_eq!(voter, None);
 
     let token_manager = bank_read(&deps.storage)
         .load(voter_addr_raw.as_slice())
         .unwrap();
-    assert_eq!(
-        token_manager.locked_balance,
-        vec![(
-            1u64,
-            VoterInfo {
-                vote: VoteOption::Yes,
-                balance: Uint128::from(stake_amount),
-            }
-        )]
-    );
+    assert_eq!(token_manager.locked_balance, vec![]);
 }
 
 #[test]
@@ -1029,7 +1405,7 @@ fn fail_poll() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
         amount: Uint128::from(stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
@@ -1163,70 +1539,338 @@ fn fail_poll() {
     assert_eq!(polls_res.polls[0], poll_res);
 }
 
+/// dispatch_messages runs the messages gov sends to itself the way the chain would.
+/// A failing message reverts its own state changes, then either goes to the reply
+/// of its submessage or fails its parent. Returns the replies which were sent.
+fn dispatch_messages(
+    deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+    env: &Env,
+    messages: Vec<SubMsg>,
+) -> Result<Vec<Reply>, ContractError> {
+    let mut replies = vec![];
+    for sub_msg in messages {
+        let msg = match sub_msg.msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) if contract_addr == MOCK_CONTRACT_ADDR => from_binary(&msg).unwrap(),
+            _ => continue,
+        };
+
+        let snapshot: Vec<(Vec<u8>, Vec<u8>)> =
+            deps.storage.range(None, None, Order::Ascending).collect();
+        let info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let res = execute(deps.as_mut(), env.clone(), info, msg).and_then(|res| {
+            let mut nested = dispatch_messages(deps, env, res.messages)?;
+            replies.append(&mut nested);
+            Ok(())
+        });
+
+        if let Err(err) = res {
+            let keys: Vec<Vec<u8>> = deps
+                .storage
+                .range(None, None, Order::Ascending)
+                .map(|(key, _)| key)
+                .collect();
+            for key in keys {
+                deps.storage.remove(&key);
+            }
+            for (key, value) in snapshot {
+                deps.storage.set(&key, &value);
+            }
+
+            if sub_msg.reply_on != ReplyOn::Error {
+                return Err(err);
+            }
+            let reply_msg = Reply {
+                id: sub_msg.id,
+                result: ContractResult::Err(err.to_string()),
+            };
+            let res = reply(deps.as_mut(), env.clone(), reply_msg.clone())?;
+            replies.push(reply_msg);
+            replies.append(&mut dispatch_messages(deps, env, res.messages)?);
+        }
+    }
+
+    Ok(replies)
+}
+
 #[test]
-fn end_poll_zero_quorum() {
-    let mut deps = mock_dependencies(&coins(1000, VOTING_TOKEN));
+fn fails_execute_poll_messages_reentrancy() {
+    let mut deps = mock_dependencies(&[]);
     mock_instantiate(deps.as_mut());
     mock_register_voting_token(deps.as_mut());
-    let mut creator_env = mock_env_height(1000, 10000);
-    let mut creator_info = mock_info(VOTING_TOKEN, &[]);
 
-    let execute_msgs: Vec<PollExecuteMsg> = vec![PollExecuteMsg {
+    // the poll message calls back into gov to execute the same poll
+    let execute_msgs = vec![PollExecuteMsg {
         order: 1u64,
-        contract: VOTING_TOKEN.to_string(),
-        msg: to_binary(&Cw20ExecuteMsg::Burn {
-            amount: Uint128::new(123),
-        })
-        .unwrap(),
+        contract: MOCK_CONTRACT_ADDR.to_string(),
+        msg: to_binary(&ExecuteMsg::ExecutePoll { poll_id: 1 }).unwrap(),
     }];
-
+    let info = mock_info(VOTING_TOKEN, &[]);
     let msg = create_poll_msg(
         "test".to_string(),
         "test".to_string(),
         None,
         Some(execute_msgs),
     );
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info.clone(), msg).unwrap();
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
 
-    let execute_res = execute(
-        deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
-        msg,
-    )
-    .unwrap();
-    assert_create_poll_result(
-        1,
-        creator_env.block.height + DEFAULT_VOTING_PERIOD,
-        TEST_CREATOR,
-        execute_res,
-        deps.as_ref(),
-    );
-    let stake_amount = 100;
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
         &[(
             &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from(100u128 + DEFAULT_PROPOSAL_DEPOSIT),
+            &Uint128::from(10u128 + 2 * DEFAULT_PROPOSAL_DEPOSIT),
         )],
     )]);
+    mock_stake(deps.as_mut(), TEST_VOTER, 10);
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(10u128),
+    };
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(0, 10000),
+        mock_info(TEST_VOTER, &[]),
+        msg,
+    )
+    .unwrap();
 
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
-    });
+    let env = mock_env_height(DEFAULT_VOTING_PERIOD, 10000);
+    for poll_id in 1..=2 {
+        let msg = ExecuteMsg::EndPoll { poll_id };
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_CREATOR, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+    let state: State = state_read(deps.as_ref().storage).load().unwrap();
+    assert_eq!(state.polls_awaiting_execution, 1);
 
-    let info = mock_info(VOTING_TOKEN, &[]);
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let env = mock_env_height(DEFAULT_VOTING_PERIOD + DEFAULT_TIMELOCK_PERIOD, 10000);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::ExecutePoll { poll_id: 1 },
+    )
+    .unwrap();
 
-    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
-    creator_info.sender = Addr::unchecked(TEST_CREATOR);
-    creator_env.block.height += DEFAULT_VOTING_PERIOD;
+    // the second entry is rejected, which fails the poll messages
+    // and reverts them before the reply marks the poll as failed
+    let replies = dispatch_messages(&mut deps, &env, res.messages).unwrap();
+    assert_eq!(
+        replies,
+        vec![Reply {
+            id: 1,
+            result: ContractResult::Err(ContractError::PollNotPassed {}.to_string()),
+        }]
+    );
 
-    let execute_res = execute(deps.as_mut(), creator_env, creator_info, msg).unwrap();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let poll: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll.status, PollStatus::Failed);
 
-    assert_eq!(
-        execute_res.attributes,
+    let state: State = state_read(deps.as_ref().storage).load().unwrap();
+    assert_eq!(state.polls_awaiting_execution, 0);
+    assert_eq!(state.polls_in_progress, 0);
+    let passed = poll_indexer_read(&deps.storage, &PollStatus::Passed)
+        .range(None, None, Order::Ascending)
+        .count();
+    assert_eq!(passed, 0);
+
+    // neither poll can be executed afterwards
+    let contract_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+    for poll_id in 1..=2 {
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            contract_info.clone(),
+            ExecuteMsg::ExecutePollMsgs { poll_id },
+        );
+        assert_eq!(res.unwrap_err(), ContractError::PollNotPassed {});
+
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            contract_info.clone(),
+            ExecuteMsg::ExecutePoll { poll_id },
+        );
+        assert_eq!(res.unwrap_err(), ContractError::PollNotPassed {});
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        env,
+        contract_info,
+        ExecuteMsg::EndPoll { poll_id: 1 },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollNotInProgress {});
+}
+
+#[test]
+fn poll_execution_without_awaiting_count() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let execute_msgs = vec![PollExecuteMsg {
+        order: 1u64,
+        contract: VOTING_TOKEN.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Burn {
+            amount: Uint128::new(123),
+        })
+        .unwrap(),
+    }];
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(execute_msgs),
+    );
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(10u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    mock_stake(deps.as_mut(), TEST_VOTER, 10);
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(10u128),
+    };
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(0, 10000),
+        mock_info(TEST_VOTER, &[]),
+        msg,
+    )
+    .unwrap();
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 1 },
+    )
+    .unwrap();
+
+    // a poll passed before the counter was backfilled is not counted
+    let reset_count = |storage: &mut dyn Storage| {
+        let mut state: State = state_read(storage).load().unwrap();
+        state.polls_awaiting_execution = 0;
+        state_store(storage).save(&state).unwrap();
+    };
+    reset_count(&mut deps.storage);
+
+    let env = mock_env_height(DEFAULT_VOTING_PERIOD + DEFAULT_TIMELOCK_PERIOD, 10000);
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::ExecutePollMsgs { poll_id: 1 },
+    )
+    .unwrap();
+    let state: State = state_read(&deps.storage).load().unwrap();
+    assert_eq!(state.polls_awaiting_execution, 0);
+
+    // the failed execution reverts the poll to passed before the reply
+    let mut poll: Poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+    poll.status = PollStatus::Passed;
+    poll_store(&mut deps.storage)
+        .save(&1u64.to_be_bytes(), &poll)
+        .unwrap();
+    let _res = execute(
+        deps.as_mut(),
+        env,
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::ExecutePoll { poll_id: 1 },
+    )
+    .unwrap();
+    reset_count(&mut deps.storage);
+
+    let reply_msg = Reply {
+        id: 1,
+        result: ContractResult::Err("Error".to_string()),
+    };
+    let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+    let state: State = state_read(&deps.storage).load().unwrap();
+    assert_eq!(state.polls_awaiting_execution, 0);
+}
+
+#[test]
+fn end_poll_zero_quorum() {
+    let mut deps = mock_dependencies(&coins(1000, VOTING_TOKEN));
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+    let mut creator_env = mock_env_height(1000, 10000);
+    let mut creator_info = mock_info(VOTING_TOKEN, &[]);
+
+    let execute_msgs: Vec<PollExecuteMsg> = vec![PollExecuteMsg {
+        order: 1u64,
+        contract: VOTING_TOKEN.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Burn {
+            amount: Uint128::new(123),
+        })
+        .unwrap(),
+    }];
+
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(execute_msgs),
+    );
+
+    let execute_res = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        msg,
+    )
+    .unwrap();
+    assert_create_poll_result(
+        1,
+        creator_env.block.height + DEFAULT_VOTING_PERIOD,
+        TEST_CREATOR,
+        execute_res,
+        deps.as_ref(),
+    );
+    let stake_amount = 100;
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(100u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(stake_amount as u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
+    creator_info.sender = Addr::unchecked(TEST_CREATOR);
+    creator_env.block.height += DEFAULT_VOTING_PERIOD;
+
+    let execute_res = execute(deps.as_mut(), creator_env, creator_info, msg).unwrap();
+
+    assert_eq!(
+        execute_res.attributes,
         vec![
             attr("action", "end_poll"),
             attr("poll_id", "1"),
@@ -1319,7 +1963,7 @@ fn end_poll_quorum_rejected() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
         amount: Uint128::from(stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
@@ -1452,7 +2096,7 @@ fn end_poll_nay_rejected() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
         amount: Uint128::from(voter1_stake as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
@@ -1477,7 +2121,7 @@ fn end_poll_nay_rejected() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER_2.to_string(),
         amount: Uint128::from(voter2_stake as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
@@ -1546,7 +2190,7 @@ fn fails_cast_vote_not_enough_staked() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
         amount: Uint128::from(10u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
@@ -1607,7 +2251,7 @@ fn happy_days_cast_vote() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
         amount: Uint128::from(11u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
@@ -1663,7 +2307,8 @@ fn happy_days_cast_vote() {
                     vote: VoteOption::Yes,
                     balance: Uint128::from(amount),
                 }
-            )]
+            )],
+            sponsored_balance: vec![],
         }
     );
 
@@ -1704,6 +2349,143 @@ fn happy_days_cast_vote() {
     assert_eq!(response.voters.len(), 0);
 }
 
+#[test]
+fn query_withdraw_status() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    for (height, time) in [(0u64, 10000u64), (100, 10600), (50, 10300)] {
+        let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+        let _res = execute(
+            deps.as_mut(),
+            mock_env_height(height, time),
+            info.clone(),
+            msg,
+        )
+        .unwrap();
+    }
+
+    let set_balance = |querier: &mut WasmMockQuerier, balance: u128| {
+        querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(balance))],
+        )]);
+    };
+    set_balance(&mut deps.querier, 100 + 3 * DEFAULT_PROPOSAL_DEPOSIT);
+    mock_stake(deps.as_mut(), TEST_VOTER, 100);
+
+    let query_status = |deps: Deps| -> WithdrawStatusResponse {
+        let msg = QueryMsg::WithdrawStatus {
+            address: TEST_VOTER.to_string(),
+        };
+        from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
+    };
+    assert_eq!(
+        query_status(deps.as_ref()),
+        WithdrawStatusResponse {
+            balance: Uint128::from(100u128),
+            locked_amount: Uint128::zero(),
+            withdrawable_amount: Uint128::from(100u128),
+            locks: vec![],
+            earliest_full_withdrawal_height: None,
+            earliest_full_withdrawal_time: None,
+        }
+    );
+
+    for (poll_id, amount) in [(1u64, 30u128), (2, 60), (3, 20)] {
+        let msg = ExecuteMsg::CastVote {
+            poll_id,
+            vote: VoteOption::Yes,
+            amount: Uint128::from(amount),
+        };
+        let _res = execute(
+            deps.as_mut(),
+            mock_env_height(100, 10600),
+            mock_info(TEST_VOTER, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+
+    // an ended poll no longer locks its votes
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 1 },
+    )
+    .unwrap();
+    set_balance(&mut deps.querier, 100 + 2 * DEFAULT_PROPOSAL_DEPOSIT);
+
+    // votes on several polls lock the same tokens
+    assert_eq!(
+        query_status(deps.as_ref()),
+        WithdrawStatusResponse {
+            balance: Uint128::from(100u128),
+            locked_amount: Uint128::from(60u128),
+            withdrawable_amount: Uint128::from(40u128),
+            locks: vec![
+                WithdrawLockResponseItem {
+                    poll_id: 2,
+                    amount: Uint128::from(60u128),
+                    end_height: 100 + DEFAULT_VOTING_PERIOD,
+                    end_time: None,
+                },
+                WithdrawLockResponseItem {
+                    poll_id: 3,
+                    amount: Uint128::from(20u128),
+                    end_height: 50 + DEFAULT_VOTING_PERIOD,
+                    end_time: None,
+                },
+            ],
+            earliest_full_withdrawal_height: Some(100 + DEFAULT_VOTING_PERIOD),
+            earliest_full_withdrawal_time: None,
+        }
+    );
+
+    // only polls on the time clock have a known end time
+    let msg = update_config_msg(|update| {
+        update.poll_clock = Some(PollClock::Time);
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(100, 10600), info, msg).unwrap();
+    set_balance(&mut deps.querier, 100 + 3 * DEFAULT_PROPOSAL_DEPOSIT);
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 4,
+        vote: VoteOption::No,
+        amount: Uint128::from(10u128),
+    };
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(100, 10600),
+        mock_info(TEST_VOTER, &[]),
+        msg,
+    )
+    .unwrap();
+
+    let status = query_status(deps.as_ref());
+    assert_eq!(
+        status.locks[2],
+        WithdrawLockResponseItem {
+            poll_id: 4,
+            amount: Uint128::from(10u128),
+            end_height: 100 + DEFAULT_VOTING_PERIOD,
+            end_time: Some(10600 + DEFAULT_VOTING_PERIOD * 6),
+        }
+    );
+    assert_eq!(
+        status.earliest_full_withdrawal_height,
+        Some(100 + DEFAULT_VOTING_PERIOD)
+    );
+    assert_eq!(
+        status.earliest_full_withdrawal_time,
+        Some(10600 + DEFAULT_VOTING_PERIOD * 6)
+    );
+}
+
 #[test]
 fn happy_days_withdraw_voting_tokens() {
     let mut deps = mock_dependencies(&[]);
@@ -1718,7 +2500,7 @@ fn happy_days_withdraw_voting_tokens() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
         amount: Uint128::from(11u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
@@ -1733,6 +2515,11 @@ fn happy_days_withdraw_voting_tokens() {
             poll_count: 0,
             total_share: Uint128::from(11u128),
             total_deposit: Uint128::zero(),
+            polls_in_progress: 0,
+            polls_awaiting_execution: 0,
+            participation_pool: Uint128::zero(),
+            participation_rewards: Uint128::zero(),
+            total_principal: Uint128::from(11u128),
         }
     );
 
@@ -1771,6 +2558,11 @@ fn happy_days_withdraw_voting_tokens() {
             poll_count: 0,
             total_share: Uint128::from(6u128),
             total_deposit: Uint128::zero(),
+            polls_in_progress: 0,
+            polls_awaiting_execution: 0,
+            participation_pool: Uint128::zero(),
+            participation_rewards: Uint128::zero(),
+            total_principal: Uint128::from(6u128),
         }
     );
 }
@@ -1789,7 +2581,7 @@ fn happy_days_withdraw_voting_tokens_all() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
         amount: Uint128::from(11u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
@@ -1804,6 +2596,11 @@ fn happy_days_withdraw_voting_tokens_all() {
             poll_count: 0,
             total_share: Uint128::from(11u128),
             total_deposit: Uint128::zero(),
+            polls_in_progress: 0,
+            polls_awaiting_execution: 0,
+            participation_pool: Uint128::zero(),
+            participation_rewards: Uint128::zero(),
+            total_principal: Uint128::from(11u128),
         }
     );
 
@@ -1840,6 +2637,11 @@ fn happy_days_withdraw_voting_tokens_all() {
             poll_count: 0,
             total_share: Uint128::zero(),
             total_deposit: Uint128::zero(),
+            polls_in_progress: 0,
+            polls_awaiting_execution: 0,
+            participation_pool: Uint128::zero(),
+            participation_rewards: Uint128::zero(),
+            total_principal: Uint128::zero(),
         }
     );
 }
@@ -1858,7 +2660,7 @@ fn withdraw_voting_tokens_remove_not_in_progress_poll_voter_info() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
         amount: Uint128::from(11u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
@@ -1883,6 +2685,27 @@ fn withdraw_voting_tokens_remove_not_in_progress_poll_voter_info() {
                 execute_data: None,
                 total_balance_at_end_poll: None,
                 staked_amount: None,
+                sponsor_weight: Uint128::zero(),
+                unlock_pending: false,
+                amended: false,
+                weight_mode: WeightMode::Linear,
+                weighted_yes_votes: Uint128::zero(),
+                weighted_no_votes: Uint128::zero(),
+                end_time: 0,
+                clock: PollClock::Height,
+                vote_leaf_count: 0,
+                vote_root: None,
+                extended: false,
+                participation_reward: Uint128::zero(),
+                participation_distributed: Uint128::zero(),
+                weight_buckets: vec![],
+                weight_bucket_voters: vec![],
+                last_vote_height: 0,
+                quorum_failed_at: None,
+                resubmitted_from: None,
+                resubmitted_as: None,
+                resubmit_depth: 0,
+                spend: None,
             },
         )
         .unwrap();
@@ -1904,6 +2727,27 @@ fn withdraw_voting_tokens_remove_not_in_progress_poll_voter_info() {
                 execute_data: None,
                 total_balance_at_end_poll: None,
                 staked_amount: None,
+                sponsor_weight: Uint128::zero(),
+                unlock_pending: false,
+                amended: false,
+                weight_mode: WeightMode::Linear,
+                weighted_yes_votes: Uint128::zero(),
+                weighted_no_votes: Uint128::zero(),
+                end_time: 0,
+                clock: PollClock::Height,
+                vote_leaf_count: 0,
+                vote_root: None,
+                extended: false,
+                participation_reward: Uint128::zero(),
+                participation_distributed: Uint128::zero(),
+                weight_buckets: vec![],
+                weight_bucket_voters: vec![],
+                last_vote_height: 0,
+                quorum_failed_at: None,
+                resubmitted_from: None,
+                resubmitted_as: None,
+                resubmit_depth: 0,
+                spend: None,
             },
         )
         .unwrap();
@@ -1948,6 +2792,7 @@ fn withdraw_voting_tokens_remove_not_in_progress_poll_voter_info() {
                         },
                     ),
                 ],
+                sponsored_balance: vec![],
             },
         )
         .unwrap();
@@ -2022,7 +2867,7 @@ fn fails_withdraw_too_many_tokens() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
         amount: Uint128::from(10u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
@@ -2074,7 +2919,7 @@ fn fails_cast_vote_twice() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
         amount: Uint128::from(11u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
@@ -2149,7 +2994,7 @@ fn happy_days_stake_voting_tokens() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
         amount: Uint128::from(11u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
@@ -2169,7 +3014,7 @@ fn fails_insufficient_funds() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
         amount: Uint128::from(0u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
@@ -2183,105 +3028,103 @@ fn fails_insufficient_funds() {
 }
 
 #[test]
-fn fails_staking_wrong_token() {
+fn fails_stake_below_min_stake_amount() {
     let mut deps = mock_dependencies(&[]);
-
-    // initialize the store
     mock_instantiate(deps.as_mut());
     mock_register_voting_token(deps.as_mut());
 
+    let info = mock_info(TEST_CREATOR, &[]);
+    let msg = update_config_msg(|update| {
+        update.min_stake_amount = Some(Uint128::from(10u128));
+    });
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
-        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(11u128))],
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(9u128))],
     )]);
 
-    // wrong token
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(11u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        amount: Uint128::from(9u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
-
-    let info = mock_info(&(VOTING_TOKEN.to_string() + "2"), &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, msg);
-
-    match res {
+    let info = mock_info(VOTING_TOKEN, &[]);
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg) {
         Ok(_) => panic!("Must return error"),
-        Err(ContractError::Unauthorized {}) => (),
+        Err(ContractError::InsufficientStakeAmount(10)) => (),
         Err(e) => panic!("Unexpected error: {:?}", e),
     }
-}
-
-#[test]
-fn share_calculation() {
-    let mut deps = mock_dependencies(&[]);
 
-    // initialize the store
-    mock_instantiate(deps.as_mut());
-    mock_register_voting_token(deps.as_mut());
-
-    // create 100 share
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
-        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(100u128))],
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(10u128))],
     )]);
 
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(100u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        amount: Uint128::from(10u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
     });
+    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_stake_tokens_result(10, 0, 10, 0, execute_res, deps.as_ref());
+}
 
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let _res = execute(deps.as_mut(), mock_env(), info, msg);
+#[test]
+fn stake_voting_tokens_on_behalf_of_staker() {
+    const DEPOSITOR: &str = "depositor";
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
 
-    // add more balance(100) to make share:balance = 1:2
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from(200u128 + 100u128),
-        )],
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(10u128))],
     )]);
 
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(100u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        sender: DEPOSITOR.to_string(),
+        amount: Uint128::from(10u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {
+            staker: Some(TEST_VOTER.to_string()),
+        })
+        .unwrap(),
     });
-
     let info = mock_info(VOTING_TOKEN, &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_eq!(
-        res.attributes,
-        vec![
-            attr("action", "staking"),
-            attr("sender", TEST_VOTER),
-            attr("share", "50"),
-            attr("amount", "100"),
-        ]
-    );
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::Unauthorized {}) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
 
-    let msg = ExecuteMsg::WithdrawVotingTokens {
-        amount: Some(Uint128::from(100u128)),
-    };
-    let info = mock_info(TEST_VOTER, &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let msg_update = update_config_msg(|update| {
+        update.stake_depositors = Some(vec![DEPOSITOR.to_string()]);
+    });
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_CREATOR, &[]),
+        msg_update,
+    )
+    .unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.stake_depositors, vec![DEPOSITOR.to_string()]);
+
+    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
     assert_eq!(
-        res.attributes,
+        execute_res.attributes,
         vec![
-            attr("action", "withdraw"),
-            attr("recipient", TEST_VOTER),
-            attr("amount", "100"),
+            attr("action", "staking"),
+            attr("sender", DEPOSITOR),
+            attr("share", "10"),
+            attr("amount", "10"),
+            attr("staker", TEST_VOTER),
         ]
     );
 
-    // 100 tokens withdrawn
-    deps.querier.with_token_balances(&[(
-        &VOTING_TOKEN.to_string(),
-        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(200u128))],
-    )]);
-
     let res = query(
         deps.as_ref(),
         mock_env(),
@@ -2290,1374 +3133,5829 @@ fn share_calculation() {
         },
     )
     .unwrap();
-    let stake_info: StakerResponse = from_binary(&res).unwrap();
-    assert_eq!(stake_info.share, Uint128::new(100));
-    assert_eq!(stake_info.balance, Uint128::new(200));
-    assert_eq!(stake_info.locked_balance, vec![]);
+    let response: StakerResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        response,
+        StakerResponse {
+            balance: Uint128::from(10u128),
+            share: Uint128::from(10u128),
+            locked_balance: vec![],
+            sponsored_balance: vec![],
+        }
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Staker {
+            address: DEPOSITOR.to_string(),
+        },
+    )
+    .unwrap();
+    let response: StakerResponse = from_binary(&res).unwrap();
+    assert_eq!(response.share, Uint128::zero());
 }
 
-// helper to confirm the expected create_poll response
-fn assert_create_poll_result(
-    poll_id: u64,
-    end_height: u64,
-    creator: &str,
-    execute_res: Response,
-    deps: Deps,
-) {
+#[test]
+fn distribute_and_claim_staking_rewards() {
+    const REWARD_TOKEN: &str = "reward_token";
+    const REWARD_TOKEN_2: &str = "reward_token2";
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let register = |token: &str| ExecuteMsg::RegisterRewardToken {
+        token: token.to_string(),
+    };
+    let distribute = |token: &str, amount: u128| {
+        (
+            mock_info(token, &[]),
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: TEST_CREATOR.to_string(),
+                amount: Uint128::from(amount),
+                msg: to_binary(&Cw20HookMsg::DistributeRewards {}).unwrap(),
+            }),
+        )
+    };
+    let stake = |staker: &str, amount: u128| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: staker.to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+        })
+    };
+
+    match execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_VOTER, &[]),
+        register(REWARD_TOKEN),
+    ) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::Unauthorized {}) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    match execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_CREATOR, &[]),
+        register(VOTING_TOKEN),
+    ) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::InvalidRewardToken {}) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    for token in [REWARD_TOKEN, REWARD_TOKEN_2] {
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(TEST_CREATOR, &[]),
+            register(token),
+        )
+        .unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "register_reward_token"),
+                attr("token", token),
+            ]
+        );
+    }
+
+    match execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_CREATOR, &[]),
+        register(REWARD_TOKEN),
+    ) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::InvalidRewardToken {}) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    // nobody to distribute to yet
+    let (info, msg) = distribute(REWARD_TOKEN, 100);
+    match execute(deps.as_mut(), mock_env(), info, msg) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::NothingStaked {}) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(100u128))],
+    )]);
+    let info = mock_info(VOTING_TOKEN, &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        stake(TEST_VOTER, 100),
+    )
+    .unwrap();
+
+    let (reward_info, msg) = distribute(REWARD_TOKEN, 100);
+    let res = execute(deps.as_mut(), mock_env(), reward_info, msg).unwrap();
     assert_eq!(
-        execute_res.attributes,
+        res.attributes,
         vec![
-            attr("action", "create_poll"),
-            attr("creator", creator),
-            attr("poll_id", poll_id.to_string()),
-            attr("end_height", end_height.to_string()),
+            attr("action", "distribute_rewards"),
+            attr("token", REWARD_TOKEN),
+            attr("amount", "100"),
         ]
     );
 
-    //confirm poll count
-    let state: State = state_read(deps.storage).load().unwrap();
-    assert_eq!(
-        state,
-        State {
-            contract_addr: deps.api.addr_canonicalize(MOCK_CONTRACT_ADDR).unwrap(),
-            poll_count: 1,
-            total_share: Uint128::zero(),
-            total_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
-        }
-    );
-}
+    // a staker joining later does not share earlier rewards
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(200u128))],
+    )]);
+    execute(deps.as_mut(), mock_env(), info, stake(TEST_VOTER_2, 100)).unwrap();
 
-fn assert_stake_tokens_result(
-    total_share: u128,
-    total_deposit: u128,
-    new_share: u128,
-    poll_count: u64,
-    execute_res: Response,
-    deps: Deps,
-) {
+    let (reward_info, msg) = distribute(REWARD_TOKEN_2, 50);
+    execute(deps.as_mut(), mock_env(), reward_info, msg).unwrap();
+    let (reward_info, msg) = distribute(REWARD_TOKEN, 100);
+    execute(deps.as_mut(), mock_env(), reward_info, msg).unwrap();
+
+    // rewards accrued before a withdrawal are kept
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_VOTER_2, &[]),
+        ExecuteMsg::WithdrawVotingTokens { amount: None },
+    )
+    .unwrap();
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(100u128))],
+    )]);
+    let (reward_info, msg) = distribute(REWARD_TOKEN, 100);
+    execute(deps.as_mut(), mock_env(), reward_info, msg).unwrap();
+
+    let pending_rewards = |deps: Deps, staker: &str| -> Vec<(String, Uint128)> {
+        let res = query(
+            deps,
+            mock_env(),
+            QueryMsg::StakerRewards {
+                address: staker.to_string(),
+            },
+        )
+        .unwrap();
+        let response: StakerRewardsResponse = from_binary(&res).unwrap();
+        let mut rewards: Vec<(String, Uint128)> = response
+            .rewards
+            .into_iter()
+            .map(|item| (item.token, item.pending_reward))
+            .collect();
+        rewards.sort();
+        rewards
+    };
     assert_eq!(
-        execute_res.attributes.get(2).expect("no log"),
-        &attr("share", new_share.to_string())
+        pending_rewards(deps.as_ref(), TEST_VOTER),
+        vec![
+            (REWARD_TOKEN.to_string(), Uint128::from(250u128)),
+            (REWARD_TOKEN_2.to_string(), Uint128::from(25u128)),
+        ]
     );
-
-    let state: State = state_read(deps.storage).load().unwrap();
     assert_eq!(
-        state,
-        State {
-            contract_addr: deps.api.addr_canonicalize(MOCK_CONTRACT_ADDR).unwrap(),
-            poll_count,
-            total_share: Uint128::from(total_share),
-            total_deposit: Uint128::from(total_deposit),
-        }
+        pending_rewards(deps.as_ref(), TEST_VOTER_2),
+        vec![
+            (REWARD_TOKEN.to_string(), Uint128::from(50u128)),
+            (REWARD_TOKEN_2.to_string(), Uint128::from(25u128)),
+        ]
     );
-}
 
-fn assert_cast_vote_success(
-    voter: &str,
-    amount: u128,
-    poll_id: u64,
-    vote_option: VoteOption,
-    execute_res: Response,
-) {
+    // only registered tokens can distribute rewards
+    let (_, msg) = distribute(REWARD_TOKEN, 100);
+    match execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("other_token", &[]),
+        msg,
+    ) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::Unauthorized {}) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    match execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_VOTER_2, &[]),
+        ExecuteMsg::ClaimRewards {
+            token: Some("other_token".to_string()),
+        },
+    ) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::RewardTokenNotRegistered {}) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    let transfer = |token: &str, recipient: &str, amount: u128| {
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount: Uint128::from(amount),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))
+    };
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_VOTER_2, &[]),
+        ExecuteMsg::ClaimRewards {
+            token: Some(REWARD_TOKEN.to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![transfer(REWARD_TOKEN, TEST_VOTER_2, 50)]);
     assert_eq!(
-        execute_res.attributes,
+        pending_rewards(deps.as_ref(), TEST_VOTER_2),
         vec![
-            attr("action", "cast_vote"),
-            attr("poll_id", poll_id.to_string()),
-            attr("amount", amount.to_string()),
-            attr("voter", voter),
-            attr("vote_option", vote_option.to_string()),
+            (REWARD_TOKEN.to_string(), Uint128::zero()),
+            (REWARD_TOKEN_2.to_string(), Uint128::from(25u128)),
         ]
     );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_VOTER, &[]),
+        ExecuteMsg::ClaimRewards { token: None },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert!(res
+        .messages
+        .contains(&transfer(REWARD_TOKEN, TEST_VOTER, 250)));
+    assert!(res
+        .messages
+        .contains(&transfer(REWARD_TOKEN_2, TEST_VOTER, 25)));
+
+    match execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_VOTER, &[]),
+        ExecuteMsg::ClaimRewards { token: None },
+    ) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::NothingToClaim {}) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
 }
 
 #[test]
-fn update_config() {
+fn fails_dust_stake_and_withdraw_after_share_price_growth() {
     let mut deps = mock_dependencies(&[]);
     mock_instantiate(deps.as_mut());
     mock_register_voting_token(deps.as_mut());
 
-    // update owner
-    let info = mock_info(TEST_CREATOR, &[]);
-    let msg = ExecuteMsg::UpdateConfig {
-        owner: Some("addr0001".to_string()),
-        quorum: None,
-        threshold: None,
-        voting_period: None,
-        timelock_period: None,
-        proposal_deposit: None,
-        snapshot_period: None,
-    };
-
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_eq!(0, res.messages.len());
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(100u128))],
+    )]);
 
-    // it worked, let's query the state
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
-    let config: ConfigResponse = from_binary(&res).unwrap();
-    assert_eq!("addr0001", config.owner.as_str());
-    assert_eq!(Decimal::percent(DEFAULT_QUORUM), config.quorum);
-    assert_eq!(Decimal::percent(DEFAULT_THRESHOLD), config.threshold);
-    assert_eq!(DEFAULT_VOTING_PERIOD, config.voting_period);
-    assert_eq!(DEFAULT_TIMELOCK_PERIOD, config.timelock_period);
-    assert_eq!(DEFAULT_PROPOSAL_DEPOSIT, config.proposal_deposit.u128());
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
 
-    // update left items
-    let info = mock_info("addr0001", &[]);
-    let msg = ExecuteMsg::UpdateConfig {
-        owner: None,
-        quorum: Some(Decimal::percent(20)),
-        threshold: Some(Decimal::percent(75)),
-        voting_period: Some(20000u64),
-        timelock_period: Some(20000u64),
-        proposal_deposit: Some(Uint128::from(123u128)),
-        snapshot_period: Some(11),
-    };
+    // simulate rewards so that one share is worth 1000 tokens
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(100_000u128 + 1u128),
+        )],
+    )]);
 
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_eq!(0, res.messages.len());
+    // 1 uANC would mint zero share
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER_2.to_string(),
+        amount: Uint128::from(1u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+    match execute(deps.as_mut(), mock_env(), info, msg) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::InsufficientFunds {}) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
 
-    // it worked, let's query the state
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
-    let config: ConfigResponse = from_binary(&res).unwrap();
-    assert_eq!("addr0001", config.owner.as_str());
-    assert_eq!(Decimal::percent(20), config.quorum);
-    assert_eq!(Decimal::percent(75), config.threshold);
-    assert_eq!(20000u64, config.voting_period);
-    assert_eq!(20000u64, config.timelock_period);
-    assert_eq!(123u128, config.proposal_deposit.u128());
-    assert_eq!(11u64, config.snapshot_period);
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(100_000u128))],
+    )]);
 
-    // Unauthorzied err
-    let info = mock_info(TEST_CREATOR, &[]);
-    let msg = ExecuteMsg::UpdateConfig {
-        owner: None,
-        quorum: None,
-        threshold: None,
-        voting_period: None,
-        timelock_period: None,
-        proposal_deposit: None,
-        snapshot_period: None,
+    // withdrawing 1 uANC would burn zero share
+    let msg = ExecuteMsg::WithdrawVotingTokens {
+        amount: Some(Uint128::from(1u128)),
+    };
+    match execute(deps.as_mut(), mock_env(), mock_info(TEST_VOTER, &[]), msg) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::InvalidWithdrawAmount {}) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    let msg = ExecuteMsg::WithdrawVotingTokens {
+        amount: Some(Uint128::from(1000u128)),
     };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(TEST_VOTER, &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "withdraw"),
+            attr("recipient", TEST_VOTER),
+            attr("amount", "1000"),
+        ]
+    );
+}
+
+#[test]
+fn fails_staking_wrong_token() {
+    let mut deps = mock_dependencies(&[]);
+
+    // initialize the store
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(11u128))],
+    )]);
 
+    // wrong token
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(11u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+
+    let info = mock_info(&(VOTING_TOKEN.to_string() + "2"), &[]);
     let res = execute(deps.as_mut(), mock_env(), info, msg);
+
     match res {
+        Ok(_) => panic!("Must return error"),
         Err(ContractError::Unauthorized {}) => (),
-        _ => panic!("Must return unauthorized error"),
+        Err(e) => panic!("Unexpected error: {:?}", e),
     }
 }
 
 #[test]
-fn add_several_execute_msgs() {
+fn share_calculation() {
     let mut deps = mock_dependencies(&[]);
+
+    // initialize the store
     mock_instantiate(deps.as_mut());
     mock_register_voting_token(deps.as_mut());
+
+    // create 100 share
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(100u128))],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+
     let info = mock_info(VOTING_TOKEN, &[]);
-    let env = mock_env_height(0, 10000);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg);
 
-    let exec_msg_bz = to_binary(&Cw20ExecuteMsg::Burn {
-        amount: Uint128::new(123),
-    })
+    // add more balance(100) to make share:balance = 1:2
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(200u128 + 100u128),
+        )],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "staking"),
+            attr("sender", TEST_VOTER),
+            attr("share", "50"),
+            attr("amount", "100"),
+        ]
+    );
+
+    let msg = ExecuteMsg::WithdrawVotingTokens {
+        amount: Some(Uint128::from(100u128)),
+    };
+    let info = mock_info(TEST_VOTER, &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "withdraw"),
+            attr("recipient", TEST_VOTER),
+            attr("amount", "100"),
+        ]
+    );
+
+    // 100 tokens withdrawn
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(200u128))],
+    )]);
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Staker {
+            address: TEST_VOTER.to_string(),
+        },
+    )
     .unwrap();
+    let stake_info: StakerResponse = from_binary(&res).unwrap();
+    assert_eq!(stake_info.share, Uint128::new(100));
+    assert_eq!(stake_info.balance, Uint128::new(200));
+    assert_eq!(stake_info.locked_balance, vec![]);
+}
 
-    let exec_msg_bz2 = to_binary(&Cw20ExecuteMsg::Burn {
-        amount: Uint128::new(12),
-    })
+#[test]
+fn participation_rewards_from_slashed_deposits() {
+    const DEPOSIT: u128 = DEFAULT_PROPOSAL_DEPOSIT;
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let msg = update_config_msg(|update| {
+        update.participation_reward_ratio = Some(Decimal::percent(50));
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    for title in ["first", "second"] {
+        let msg = create_poll_msg(title.to_string(), title.to_string(), None, None);
+        let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info.clone(), msg).unwrap();
+    }
+
+    for (staker, amount, total_staked) in [
+        (TEST_VOTER, 10u128, 10u128),
+        (TEST_VOTER_2, 20, 30),
+        (TEST_VOTER_3, 30, 60),
+    ] {
+        deps.querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &Uint128::from(total_staked + 2 * DEPOSIT),
+            )],
+        )]);
+        mock_stake(deps.as_mut(), staker, amount);
+    }
+
+    for (voter, poll_id, vote, amount) in [
+        (TEST_VOTER, 1u64, VoteOption::Yes, 10u128),
+        (TEST_VOTER, 2, VoteOption::Yes, 10),
+        (TEST_VOTER_2, 2, VoteOption::No, 20),
+        (TEST_VOTER_3, 2, VoteOption::Yes, 30),
+    ] {
+        let msg = ExecuteMsg::CastVote {
+            poll_id,
+            vote,
+            amount: Uint128::from(amount),
+        };
+        let _res = execute(
+            deps.as_mut(),
+            mock_env_height(1, 10000),
+            mock_info(voter, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+
+    let query_state = |deps: Deps| -> StateResponse {
+        let res = query(deps, mock_env(), QueryMsg::State {}).unwrap();
+        from_binary(&res).unwrap()
+    };
+
+    // the first poll misses quorum, its deposit feeds the pool
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 1 },
+    )
+    .unwrap();
+    let state = query_state(deps.as_ref());
+    assert_eq!(state.participation_pool, Uint128::from(DEPOSIT));
+    assert_eq!(state.participation_rewards, Uint128::zero());
+
+    // the pool is not staked
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Staker {
+            address: TEST_VOTER_3.to_string(),
+        },
+    )
+    .unwrap();
+    let staker: StakerResponse = from_binary(&res).unwrap();
+    assert_eq!(staker.balance, Uint128::from(30u128));
+
+    // half of the pool goes to the voters of the second poll, pro-rata to their votes
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 2 },
+    )
+    .unwrap();
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(60 + DEPOSIT),
+        )],
+    )]);
+
+    let expected_rewards = [
+        (TEST_VOTER, 833_333_333u128),
+        (TEST_VOTER_2, 1_666_666_666),
+        (TEST_VOTER_3, 2_500_000_000),
+    ];
+    for (voter, reward) in expected_rewards {
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::StakerRewards {
+                address: voter.to_string(),
+            },
+        )
+        .unwrap();
+        let response: StakerRewardsResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            response.rewards,
+            vec![StakerRewardsResponseItem {
+                token: VOTING_TOKEN.to_string(),
+                pending_reward: Uint128::from(reward),
+            }]
+        );
+    }
+
+    // the rounding leftover goes back to the pool
+    let state = query_state(deps.as_ref());
+    assert_eq!(state.participation_pool, Uint128::from(DEPOSIT / 2 + 1));
+    assert_eq!(state.participation_rewards, Uint128::from(DEPOSIT / 2 - 1));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_VOTER, &[]),
+        ExecuteMsg::ClaimRewards {
+            token: Some(VOTING_TOKEN.to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: TEST_VOTER.to_string(),
+                amount: Uint128::from(833_333_333u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_VOTER, &[]),
+        ExecuteMsg::ClaimRewards { token: None },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::NothingToClaim {});
+
+    // nothing was created or lost
+    let state = query_state(deps.as_ref());
+    assert_eq!(
+        state.participation_pool + state.participation_rewards + Uint128::from(833_333_333u128),
+        Uint128::from(DEPOSIT)
+    );
+}
+
+#[test]
+fn disable_participation_rewards() {
+    const DEPOSIT: u128 = DEFAULT_PROPOSAL_DEPOSIT;
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let set_ratio = |deps: DepsMut, ratio: Decimal| {
+        let msg = update_config_msg(|update| {
+            update.participation_reward_ratio = Some(ratio);
+        });
+        execute(deps, mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+    };
+    set_ratio(deps.as_mut(), Decimal::percent(50));
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(10u128))],
+    )]);
+    mock_stake(deps.as_mut(), TEST_VOTER, 10);
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    for title in ["first", "second"] {
+        let msg = create_poll_msg(title.to_string(), title.to_string(), None, None);
+        let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info.clone(), msg).unwrap();
+    }
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(10 + 2 * DEPOSIT),
+        )],
+    )]);
+
+    let end_poll = |deps: DepsMut, poll_id: u64| {
+        execute(
+            deps,
+            mock_env_height(DEFAULT_VOTING_PERIOD, 10000),
+            mock_info(TEST_CREATOR, &[]),
+            ExecuteMsg::EndPoll { poll_id },
+        )
+        .unwrap();
+    };
+    let participation_pool =
+        |deps: Deps| state_read(deps.storage).load().unwrap().participation_pool;
+    let staked_balance = |deps: Deps| -> Uint128 {
+        let msg = QueryMsg::Staker {
+            address: TEST_VOTER.to_string(),
+        };
+        let staker: StakerResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        staker.balance
+    };
+
+    // the first poll misses quorum and its deposit is slashed into the pool
+    end_poll(deps.as_mut(), 1);
+    assert_eq!(participation_pool(deps.as_ref()), Uint128::from(DEPOSIT));
+    assert_eq!(staked_balance(deps.as_ref()), Uint128::from(10u128));
+
+    // a zero ratio returns the pool to the stakers
+    set_ratio(deps.as_mut(), Decimal::zero());
+    assert_eq!(participation_pool(deps.as_ref()), Uint128::zero());
+    assert_eq!(staked_balance(deps.as_ref()), Uint128::from(10 + DEPOSIT));
+
+    // and later slashes do not feed it
+    end_poll(deps.as_mut(), 2);
+    assert_eq!(participation_pool(deps.as_ref()), Uint128::zero());
+    assert_eq!(
+        staked_balance(deps.as_ref()),
+        Uint128::from(10 + 2 * DEPOSIT)
+    );
+
+    mock_enable_sponsorship(deps.as_mut(), 15, 100);
+    let msg = create_poll_msg("third".to_string(), "third".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(101, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 3 },
+    )
+    .unwrap();
+    assert_eq!(res.attributes[0], attr("action", "expire_poll"));
+    assert_eq!(participation_pool(deps.as_ref()), Uint128::zero());
+}
+
+#[test]
+fn query_epoch_stats() {
+    const WEEK: u64 = 604_800u64;
+    const DEPOSIT: u128 = DEFAULT_PROPOSAL_DEPOSIT;
+    const NOW: u64 = 1_600_000_000u64;
+    const EPOCH_START: u64 = NOW - NOW % WEEK;
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let set_balance = |querier: &mut WasmMockQuerier, balance: u128| {
+        querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(balance))],
+        )]);
+    };
+    let stake = |deps: DepsMut, staker: &str, amount: u128, time: u64| {
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: staker.to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+        });
+        execute(
+            deps,
+            mock_env_height(1, time),
+            mock_info(VOTING_TOKEN, &[]),
+            msg,
+        )
+        .unwrap();
+    };
+    let cast_vote = |deps: DepsMut, voter: &str, poll_id: u64, vote: VoteOption, time: u64| {
+        let amount = Uint128::from(if voter == TEST_VOTER { 10u128 } else { 20u128 });
+        let msg = ExecuteMsg::CastVote {
+            poll_id,
+            vote,
+            amount,
+        };
+        execute(deps, mock_env_height(1, time), mock_info(voter, &[]), msg).unwrap();
+    };
+
+    // first epoch: two polls are created and voted on
+    set_balance(&mut deps.querier, DEPOSIT);
+    let msg = create_poll_msg("first".to_string(), "first".to_string(), None, None);
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(0, NOW),
+        mock_info(VOTING_TOKEN, &[]),
+        msg,
+    )
+    .unwrap();
+    set_balance(&mut deps.querier, 10 + DEPOSIT);
+    stake(deps.as_mut(), TEST_VOTER, 10, NOW + 10);
+    set_balance(&mut deps.querier, 30 + DEPOSIT);
+    stake(deps.as_mut(), TEST_VOTER_2, 20, NOW + 20);
+    cast_vote(deps.as_mut(), TEST_VOTER, 1, VoteOption::Yes, NOW + 30);
+
+    set_balance(&mut deps.querier, 30 + 2 * DEPOSIT);
+    let msg = create_poll_msg("second".to_string(), "second".to_string(), None, None);
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(0, NOW + 40),
+        mock_info(VOTING_TOKEN, &[]),
+        msg,
+    )
+    .unwrap();
+    cast_vote(deps.as_mut(), TEST_VOTER_2, 2, VoteOption::No, NOW + 50);
+    cast_vote(deps.as_mut(), TEST_VOTER, 2, VoteOption::Yes, NOW + 60);
+
+    // second epoch: the first poll gets another vote, then both polls end
+    cast_vote(
+        deps.as_mut(),
+        TEST_VOTER_2,
+        1,
+        VoteOption::No,
+        EPOCH_START + WEEK + 10,
+    );
+    for poll_id in [1u64, 2] {
+        let _res = execute(
+            deps.as_mut(),
+            mock_env_height(DEFAULT_VOTING_PERIOD, EPOCH_START + WEEK + 20),
+            mock_info(TEST_CREATOR, &[]),
+            ExecuteMsg::EndPoll { poll_id },
+        )
+        .unwrap();
+    }
+
+    // nothing happens in the third epoch, the fourth starts on its own boundary
+    set_balance(&mut deps.querier, 40);
+    stake(deps.as_mut(), TEST_VOTER_3, 10, EPOCH_START + 3 * WEEK + 5);
+
+    let first = EpochStatsResponseItem {
+        start_time: EPOCH_START,
+        end_time: EPOCH_START + WEEK,
+        total_staked: Uint128::zero(),
+        polls_created: 2,
+        polls_ended: 0,
+        yes_votes: Uint128::from(20u128),
+        no_votes: Uint128::from(20u128),
+        unique_voters: 2,
+    };
+    let second = EpochStatsResponseItem {
+        start_time: EPOCH_START + WEEK,
+        end_time: EPOCH_START + 2 * WEEK,
+        total_staked: Uint128::from(30u128),
+        polls_created: 0,
+        polls_ended: 2,
+        yes_votes: Uint128::zero(),
+        no_votes: Uint128::from(20u128),
+        unique_voters: 1,
+    };
+    let fourth = EpochStatsResponseItem {
+        start_time: EPOCH_START + 3 * WEEK,
+        end_time: EPOCH_START + 4 * WEEK,
+        total_staked: Uint128::from(30u128),
+        polls_created: 0,
+        polls_ended: 0,
+        yes_votes: Uint128::zero(),
+        no_votes: Uint128::zero(),
+        unique_voters: 0,
+    };
+
+    let query_epochs = |deps: Deps, start_after: Option<u64>, limit: Option<u32>| {
+        let res = query(
+            deps,
+            mock_env(),
+            QueryMsg::EpochStats { start_after, limit },
+        )
+        .unwrap();
+        let response: EpochStatsResponse = from_binary(&res).unwrap();
+        response.epochs
+    };
+    assert_eq!(
+        query_epochs(deps.as_ref(), None, None),
+        vec![first.clone(), second.clone(), fourth.clone()]
+    );
+    assert_eq!(
+        query_epochs(deps.as_ref(), Some(EPOCH_START), None),
+        vec![second, fourth]
+    );
+    assert_eq!(query_epochs(deps.as_ref(), None, Some(1)), vec![first]);
+}
+
+#[test]
+fn query_share_rate_history() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let query_history = |deps: Deps, limit: Option<u32>| -> Vec<ShareRateCheckpointResponse> {
+        let res = query(deps, mock_env(), QueryMsg::ShareRateHistory { limit }).unwrap();
+        let response: ShareRateHistoryResponse = from_binary(&res).unwrap();
+        response.checkpoints
+    };
+
+    // nothing staked yet
+    assert_eq!(query_history(deps.as_ref(), None), vec![]);
+
+    let day = 86400u64;
+    let start = 1_600_000_000u64;
+    let mut balance = Uint128::zero();
+    let mut total_share = Uint128::zero();
+    let mut expected = vec![];
+
+    // stake 100 on day 0, then 10 a day while 2 of rewards arrive daily
+    for i in 0..100u64 {
+        let amount = Uint128::from(if i == 0 { 100u128 } else { 10u128 });
+        if i > 0 {
+            balance += Uint128::from(2u128);
+        }
+        let share = if total_share.is_zero() {
+            amount
+        } else {
+            amount.multiply_ratio(total_share, balance)
+        };
+        balance += amount;
+        total_share += share;
+
+        deps.querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(&MOCK_CONTRACT_ADDR.to_string(), &balance)],
+        )]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: TEST_VOTER.to_string(),
+            amount,
+            msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+        });
+        let info = mock_info(VOTING_TOKEN, &[]);
+        execute(
+            deps.as_mut(),
+            mock_env_height(0, start + i * day),
+            info,
+            msg,
+        )
+        .unwrap();
+
+        expected.push(ShareRateCheckpointResponse {
+            time: start + i * day,
+            total_balance: balance,
+            total_share,
+            share_rate: Decimal::from_ratio(balance, total_share),
+        });
+
+        if i == 0 {
+            assert_eq!(
+                query_history(deps.as_ref(), None),
+                vec![ShareRateCheckpointResponse {
+                    time: start,
+                    total_balance: Uint128::from(100u128),
+                    total_share: Uint128::from(100u128),
+                    share_rate: Decimal::one(),
+                }]
+            );
+        }
+    }
+
+    // a withdrawal less than a day after the last checkpoint is not recorded
+    let msg = ExecuteMsg::WithdrawVotingTokens {
+        amount: Some(Uint128::from(10u128)),
+    };
+    let info = mock_info(TEST_VOTER, &[]);
+    execute(
+        deps.as_mut(),
+        mock_env_height(0, start + 99 * day + day - 1),
+        info,
+        msg,
+    )
+    .unwrap();
+
+    // only the latest 90 checkpoints are kept, newest first
+    let history = query_history(deps.as_ref(), None);
+    assert_eq!(history.len(), 90);
+    assert_eq!(
+        history,
+        expected.iter().rev().take(90).cloned().collect::<Vec<_>>()
+    );
+    assert!(history[0].share_rate > history[89].share_rate);
+
+    assert_eq!(
+        query_history(deps.as_ref(), Some(3)),
+        expected.iter().rev().take(3).cloned().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn query_staking_metrics() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::zero())],
+    )]);
+
+    // nothing staked yet
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::StakingMetrics {}).unwrap();
+    let metrics: StakingMetricsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        metrics,
+        StakingMetricsResponse {
+            total_staked: Uint128::zero(),
+            total_share: Uint128::zero(),
+            share_exchange_rate: Decimal::one(),
+            total_deposited_in_polls: Uint128::zero(),
+            anc_balance: Uint128::zero(),
+            remaining_capacity: None,
+        }
+    );
+
+    // stake 100 and create a poll with deposit
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(100u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::StakingMetrics {}).unwrap();
+    let metrics: StakingMetricsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        metrics,
+        StakingMetricsResponse {
+            total_staked: Uint128::from(100u128),
+            total_share: Uint128::from(100u128),
+            share_exchange_rate: Decimal::one(),
+            total_deposited_in_polls: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+            anc_balance: Uint128::from(100u128 + DEFAULT_PROPOSAL_DEPOSIT),
+            remaining_capacity: None,
+        }
+    );
+
+    // simulate 50 reward inflow; poll deposit must not count as stake
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(150u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::StakingMetrics {}).unwrap();
+    let metrics: StakingMetricsResponse = from_binary(&res).unwrap();
+    assert_eq!(metrics.total_staked, Uint128::from(150u128));
+    assert_eq!(metrics.total_share, Uint128::from(100u128));
+    assert_eq!(metrics.share_exchange_rate, Decimal::percent(150));
+}
+
+// helper to confirm the expected create_poll response
+fn assert_create_poll_result(
+    poll_id: u64,
+    end_height: u64,
+    creator: &str,
+    execute_res: Response,
+    deps: Deps,
+) {
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "create_poll"),
+            attr("creator", creator),
+            attr("poll_id", poll_id.to_string()),
+            attr("end_height", end_height.to_string()),
+        ]
+    );
+
+    //confirm poll count
+    let state: State = state_read(deps.storage).load().unwrap();
+    assert_eq!(
+        state,
+        State {
+            contract_addr: deps.api.addr_canonicalize(MOCK_CONTRACT_ADDR).unwrap(),
+            poll_count: 1,
+            total_share: Uint128::zero(),
+            total_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+            polls_in_progress: 1,
+            polls_awaiting_execution: 0,
+            participation_pool: Uint128::zero(),
+            participation_rewards: Uint128::zero(),
+            total_principal: Uint128::zero(),
+        }
+    );
+}
+
+// stakes are made at a share rate of 1, so the principal equals the total share
+fn assert_stake_tokens_result(
+    total_share: u128,
+    total_deposit: u128,
+    new_share: u128,
+    poll_count: u64,
+    execute_res: Response,
+    deps: Deps,
+) {
+    assert_eq!(
+        execute_res.attributes.get(2).expect("no log"),
+        &attr("share", new_share.to_string())
+    );
+
+    let state: State = state_read(deps.storage).load().unwrap();
+    assert_eq!(
+        state,
+        State {
+            contract_addr: deps.api.addr_canonicalize(MOCK_CONTRACT_ADDR).unwrap(),
+            poll_count,
+            total_share: Uint128::from(total_share),
+            total_deposit: Uint128::from(total_deposit),
+            polls_in_progress: poll_count,
+            polls_awaiting_execution: 0,
+            participation_pool: Uint128::zero(),
+            participation_rewards: Uint128::zero(),
+            total_principal: Uint128::from(total_share),
+        }
+    );
+}
+
+fn assert_cast_vote_success(
+    voter: &str,
+    amount: u128,
+    poll_id: u64,
+    vote_option: VoteOption,
+    execute_res: Response,
+) {
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "cast_vote"),
+            attr("poll_id", poll_id.to_string()),
+            attr("amount", amount.to_string()),
+            attr("voter", voter),
+            attr("vote_option", vote_option.to_string()),
+        ]
+    );
+}
+
+#[test]
+fn stake_deposit_cap() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let msg = update_config_msg(|update| {
+        update.deposit_cap = Some(Some(Uint128::from(1000u128)));
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+
+    let set_balance = |querier: &mut WasmMockQuerier, staked: u128| {
+        querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &Uint128::from(staked + DEFAULT_PROPOSAL_DEPOSIT),
+            )],
+        )]);
+    };
+    let stake = |deps: DepsMut, amount: u128| {
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: TEST_VOTER.to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+        });
+        execute(deps, mock_env(), mock_info(VOTING_TOKEN, &[]), msg)
+    };
+    let remaining_capacity = |deps: Deps| {
+        let res = query(deps, mock_env(), QueryMsg::StakingMetrics {}).unwrap();
+        from_binary::<StakingMetricsResponse>(&res)
+            .unwrap()
+            .remaining_capacity
+    };
+
+    // the poll deposit does not count toward the cap
+    set_balance(&mut deps.querier, 0);
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(VOTING_TOKEN, &[]), msg).unwrap();
+    assert_eq!(
+        remaining_capacity(deps.as_ref()),
+        Some(Uint128::from(1000u128))
+    );
+
+    set_balance(&mut deps.querier, 600);
+    stake(deps.as_mut(), 600).unwrap();
+    assert_eq!(
+        remaining_capacity(deps.as_ref()),
+        Some(Uint128::from(400u128))
+    );
+
+    // a reward distribution grows the staked balance past the cap,
+    // only the principal counts toward it
+    set_balance(&mut deps.querier, 1101);
+    assert_eq!(
+        stake(deps.as_mut(), 401).unwrap_err(),
+        ContractError::DepositCapExceeded(400)
+    );
+
+    set_balance(&mut deps.querier, 1500);
+    stake(deps.as_mut(), 400).unwrap();
+    assert_eq!(remaining_capacity(deps.as_ref()), Some(Uint128::zero()));
+
+    // withdrawing half of the shares releases half of the principal
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_VOTER, &[]),
+        ExecuteMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(750u128)),
+        },
+    )
+    .unwrap();
+    set_balance(&mut deps.querier, 750);
+    assert_eq!(
+        remaining_capacity(deps.as_ref()),
+        Some(Uint128::from(500u128))
+    );
+
+    // lowering the cap below the staked principal only blocks new deposits
+    let msg = update_config_msg(|update| {
+        update.deposit_cap = Some(Some(Uint128::from(400u128)));
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+    assert_eq!(remaining_capacity(deps.as_ref()), Some(Uint128::zero()));
+
+    set_balance(&mut deps.querier, 751);
+    assert_eq!(
+        stake(deps.as_mut(), 1).unwrap_err(),
+        ContractError::DepositCapExceeded(0)
+    );
+
+    // null removes the cap, a missing field keeps it
+    let msg: ExecuteMsg = from_binary(&Binary::from(br#"{"update_config":{}}"#)).unwrap();
+    assert_eq!(msg, update_config_msg(|_| {}));
+    let msg: ExecuteMsg =
+        from_binary(&Binary::from(br#"{"update_config":{"deposit_cap":null}}"#)).unwrap();
+    assert_eq!(
+        msg,
+        update_config_msg(|update| {
+            update.deposit_cap = Some(None);
+        })
+    );
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+    set_balance(&mut deps.querier, 752);
+    stake(deps.as_mut(), 2).unwrap();
+    assert_eq!(remaining_capacity(deps.as_ref()), None);
+
+    let state: State = state_read(deps.as_ref().storage).load().unwrap();
+    assert_eq!(state.total_principal, Uint128::from(502u128));
+}
+
+#[test]
+fn update_config() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    // update owner
+    let info = mock_info(TEST_CREATOR, &[]);
+    let msg = ExecuteMsg::UpdateConfig(ConfigUpdate {
+        owner: Some("addr0001".to_string()),
+        ..ConfigUpdate::default()
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    // it worked, let's query the state
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!("addr0001", config.owner.as_str());
+    assert_eq!(Decimal::percent(DEFAULT_QUORUM), config.quorum);
+    assert_eq!(Decimal::percent(DEFAULT_THRESHOLD), config.threshold);
+    assert_eq!(DEFAULT_VOTING_PERIOD, config.voting_period);
+    assert_eq!(DEFAULT_TIMELOCK_PERIOD, config.timelock_period);
+    assert_eq!(DEFAULT_PROPOSAL_DEPOSIT, config.proposal_deposit.u128());
+
+    // update left items
+    let info = mock_info("addr0001", &[]);
+    let msg = ExecuteMsg::UpdateConfig(ConfigUpdate {
+        quorum: Some(Decimal::percent(20)),
+        threshold: Some(Decimal::percent(75)),
+        voting_period: Some(20000u64),
+        timelock_period: Some(20000u64),
+        proposal_deposit: Some(Uint128::from(123u128)),
+        snapshot_period: Some(11),
+        ..ConfigUpdate::default()
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    // it worked, let's query the state
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!("addr0001", config.owner.as_str());
+    assert_eq!(Decimal::percent(20), config.quorum);
+    assert_eq!(Decimal::percent(75), config.threshold);
+    assert_eq!(20000u64, config.voting_period);
+    assert_eq!(20000u64, config.timelock_period);
+    assert_eq!(123u128, config.proposal_deposit.u128());
+    assert_eq!(11u64, config.snapshot_period);
+
+    // the message keeps its JSON form, fields left out are kept
+    let msg: ExecuteMsg = from_binary(&Binary::from(
+        br#"{"update_config":{"quorum":"0.3"}}"#.to_vec(),
+    ))
+    .unwrap();
+    assert_eq!(
+        msg,
+        ExecuteMsg::UpdateConfig(ConfigUpdate {
+            quorum: Some(Decimal::percent(30)),
+            ..ConfigUpdate::default()
+        })
+    );
+
+    // Unauthorzied err
+    let info = mock_info(TEST_CREATOR, &[]);
+    let msg = ExecuteMsg::UpdateConfig(ConfigUpdate::default());
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg);
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("Must return unauthorized error"),
+    }
+}
+
+#[test]
+fn update_protocol_addresses() {
+    const COMMUNITY_FUND: &str = "community";
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let update = |deps: DepsMut, sender: &str, addresses: ProtocolAddressesMsg| {
+        let msg = ExecuteMsg::UpdateProtocolAddresses(addresses);
+        execute(deps, mock_env(), mock_info(sender, &[]), msg)
+    };
+
+    // only a passed poll can update the registry
+    for sender in [TEST_VOTER, TEST_CREATOR] {
+        let res = update(
+            deps.as_mut(),
+            sender,
+            ProtocolAddressesMsg {
+                collector: Some(Some("collector0000".to_string())),
+                ..ProtocolAddressesMsg::default()
+            },
+        );
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    }
+
+    let _res = update(
+        deps.as_mut(),
+        MOCK_CONTRACT_ADDR,
+        ProtocolAddressesMsg {
+            community_fund: Some(Some(COMMUNITY_FUND.to_string())),
+            collector: Some(Some("collector0000".to_string())),
+            ..ProtocolAddressesMsg::default()
+        },
+    )
+    .unwrap();
+
+    // addresses left out are kept, null removes one
+    let msg: ExecuteMsg = from_binary(&Binary::from(
+        br#"{"update_protocol_addresses":{"collector":null,"voting_escrow":"escrow0000"}}"#,
+    ))
+    .unwrap();
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        msg,
+    )
+    .unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::ProtocolAddresses {}).unwrap();
+    let addresses: ProtocolAddressesResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        addresses,
+        ProtocolAddressesResponse {
+            anchor_token: VOTING_TOKEN.to_string(),
+            community_fund: Some(COMMUNITY_FUND.to_string()),
+            collector: None,
+            voting_escrow: Some("escrow0000".to_string()),
+            gauge_controller: None,
+        }
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ConfigHistory {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let history: ConfigHistoryResponse = from_binary(&res).unwrap();
+    assert_eq!(history.changes.len(), 2);
+    assert_eq!(
+        history.changes[1].changed_fields,
+        vec!["protocol_addresses"]
+    );
+}
+
+#[test]
+fn add_several_execute_msgs() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let env = mock_env_height(0, 10000);
+
+    let exec_msg_bz = to_binary(&Cw20ExecuteMsg::Burn {
+        amount: Uint128::new(123),
+    })
+    .unwrap();
+
+    let exec_msg_bz2 = to_binary(&Cw20ExecuteMsg::Burn {
+        amount: Uint128::new(12),
+    })
+    .unwrap();
+
+    let exec_msg_bz3 = to_binary(&Cw20ExecuteMsg::Burn {
+        amount: Uint128::new(1),
+    })
+    .unwrap();
+
+    // push two execute msgs to the list
+    let execute_msgs: Vec<PollExecuteMsg> = vec![
+        PollExecuteMsg {
+            order: 1u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: exec_msg_bz,
+        },
+        PollExecuteMsg {
+            order: 3u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: exec_msg_bz3,
+        },
+        PollExecuteMsg {
+            order: 2u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: exec_msg_bz2,
+        },
+    ];
+
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(execute_msgs.clone()),
+    );
+
+    let execute_res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    assert_create_poll_result(
+        1,
+        env.block.height + DEFAULT_VOTING_PERIOD,
+        TEST_CREATOR,
+        execute_res,
+        deps.as_ref(),
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let value: PollResponse = from_binary(&res).unwrap();
+
+    let response_execute_data = value.execute_data.unwrap();
+    assert_eq!(response_execute_data.len(), 3);
+    assert_eq!(response_execute_data, execute_msgs);
+}
+
+#[test]
+fn query_poll_execute_msgs() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let gov_msg = to_binary(&ExecuteMsg::EndPoll { poll_id: 1 }).unwrap();
+    let token_msg = to_binary(&Cw20ExecuteMsg::Burn {
+        amount: Uint128::new(123),
+    })
+    .unwrap();
+    let opaque_msg = Binary::from(b"{\"do_something\":{}}".to_vec());
+
+    let execute_msgs: Vec<PollExecuteMsg> = vec![
+        PollExecuteMsg {
+            order: 3u64,
+            contract: "third_party".to_string(),
+            msg: opaque_msg.clone(),
+        },
+        PollExecuteMsg {
+            order: 1u64,
+            contract: MOCK_CONTRACT_ADDR.to_string(),
+            msg: gov_msg.clone(),
+        },
+        PollExecuteMsg {
+            order: 2u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: token_msg.clone(),
+        },
+        PollExecuteMsg {
+            order: 4u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: opaque_msg.clone(),
+        },
+    ];
+
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(execute_msgs),
+    );
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(VOTING_TOKEN, &[]), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PollExecuteMsgs { poll_id: 1 },
+    )
+    .unwrap();
+    let response: PollExecuteMsgsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        response.execute_msgs,
+        vec![
+            PollExecuteMsgsResponseItem {
+                order: 1u64,
+                contract: MOCK_CONTRACT_ADDR.to_string(),
+                msg: gov_msg,
+                decoded_msg: Some("{\"end_poll\":{\"poll_id\":1}}".to_string()),
+            },
+            PollExecuteMsgsResponseItem {
+                order: 2u64,
+                contract: VOTING_TOKEN.to_string(),
+                msg: token_msg,
+                decoded_msg: Some("{\"burn\":{\"amount\":\"123\"}}".to_string()),
+            },
+            PollExecuteMsgsResponseItem {
+                order: 3u64,
+                contract: "third_party".to_string(),
+                msg: opaque_msg.clone(),
+                decoded_msg: None,
+            },
+            PollExecuteMsgsResponseItem {
+                order: 4u64,
+                contract: VOTING_TOKEN.to_string(),
+                msg: opaque_msg,
+                decoded_msg: None,
+            },
+        ]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PollExecuteMsgs { poll_id: 2 },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollNotFound {});
+}
+
+#[test]
+fn simulate_poll_execution() {
+    const COMMUNITY_FUND: &str = "community";
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let msg = ExecuteMsg::UpdateProtocolAddresses(ProtocolAddressesMsg {
+        community_fund: Some(Some(COMMUNITY_FUND.to_string())),
+        ..ProtocolAddressesMsg::default()
+    });
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        msg,
+    )
+    .unwrap();
+    deps.querier.with_community_config(
+        COMMUNITY_FUND,
+        CommunityConfigResponse {
+            gov_contract: MOCK_CONTRACT_ADDR.to_string(),
+            anchor_token: VOTING_TOKEN.to_string(),
+            spend_limit: Uint128::new(1000),
+        },
+    );
+
+    let spend_msg = |amount: u128| {
+        to_binary(&CommunityExecuteMsg::Spend {
+            recipient: TEST_VOTER.to_string(),
+            amount: Uint128::new(amount),
+        })
+        .unwrap()
+    };
+    let execute_msgs: Vec<PollExecuteMsg> = vec![
+        PollExecuteMsg {
+            order: 1u64,
+            contract: MOCK_CONTRACT_ADDR.to_string(),
+            msg: to_binary(&ExecuteMsg::EndPoll { poll_id: 1 }).unwrap(),
+        },
+        PollExecuteMsg {
+            order: 2u64,
+            contract: MOCK_CONTRACT_ADDR.to_string(),
+            msg: to_binary(&ExecuteMsg::RegisterRewardToken {
+                token: "reward".to_string(),
+            })
+            .unwrap(),
+        },
+        PollExecuteMsg {
+            order: 3u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: TEST_VOTER.to_string(),
+                amount: Uint128::new(100),
+            })
+            .unwrap(),
+        },
+        PollExecuteMsg {
+            order: 4u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: Binary::from(b"{\"do_something\":{}}".to_vec()),
+        },
+        PollExecuteMsg {
+            order: 5u64,
+            contract: COMMUNITY_FUND.to_string(),
+            msg: spend_msg(1000),
+        },
+        PollExecuteMsg {
+            order: 6u64,
+            contract: COMMUNITY_FUND.to_string(),
+            msg: spend_msg(1001),
+        },
+        PollExecuteMsg {
+            order: 7u64,
+            contract: "third_party".to_string(),
+            msg: spend_msg(1000),
+        },
+    ];
+
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(execute_msgs),
+    );
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(VOTING_TOKEN, &[]), msg).unwrap();
+
+    let simulate = |deps: Deps| -> Vec<SimulatePollExecutionResponseItem> {
+        let res = query(
+            deps,
+            mock_env(),
+            QueryMsg::SimulatePollExecution { poll_id: 1 },
+        )
+        .unwrap();
+        let response: SimulatePollExecutionResponse = from_binary(&res).unwrap();
+        response.execute_msgs
+    };
+    let item = |order: u64, contract: &str, verdict: ExecutionVerdict, reason: Option<&str>| {
+        SimulatePollExecutionResponseItem {
+            order,
+            contract: contract.to_string(),
+            verdict,
+            reason: reason.map(|reason| reason.to_string()),
+        }
+    };
+    let not_owner = Some("the gov contract is not the owner of the target");
+
+    let mut response = simulate(deps.as_ref());
+    // a malformed message is reported with the parse error
+    let malformed = response.remove(3);
+    assert_eq!(malformed.verdict, ExecutionVerdict::Invalid);
+    assert!(malformed
+        .reason
+        .unwrap()
+        .starts_with("does not match the target ExecuteMsg"));
+    assert_eq!(
+        response,
+        vec![
+            item(1, MOCK_CONTRACT_ADDR, ExecutionVerdict::Valid, None),
+            // the owner of the gov contract is the creator, not the gov contract itself
+            item(2, MOCK_CONTRACT_ADDR, ExecutionVerdict::Invalid, not_owner),
+            item(3, VOTING_TOKEN, ExecutionVerdict::Valid, None),
+            item(5, COMMUNITY_FUND, ExecutionVerdict::Valid, None),
+            item(
+                6,
+                COMMUNITY_FUND,
+                ExecutionVerdict::Invalid,
+                Some("amount exceeds the spend limit"),
+            ),
+            item(
+                7,
+                "third_party",
+                ExecutionVerdict::Unknown,
+                Some("not a known protocol contract"),
+            ),
+        ]
+    );
+
+    // the community fund is governed by another contract
+    deps.querier.with_community_config(
+        COMMUNITY_FUND,
+        CommunityConfigResponse {
+            gov_contract: "other_gov".to_string(),
+            anchor_token: VOTING_TOKEN.to_string(),
+            spend_limit: Uint128::new(1000),
+        },
+    );
+    let response = simulate(deps.as_ref());
+    assert_eq!(
+        response[4],
+        item(5, COMMUNITY_FUND, ExecutionVerdict::Invalid, not_owner)
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::SimulatePollExecution { poll_id: 2 },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollNotFound {});
+}
+
+#[test]
+fn execution_denylist() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let token_poll_msg = |title: &str| {
+        create_poll_msg(
+            title.to_string(),
+            title.to_string(),
+            None,
+            Some(vec![PollExecuteMsg {
+                order: 1u64,
+                contract: VOTING_TOKEN.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::IncreaseAllowance {
+                    spender: TEST_VOTER.to_string(),
+                    amount: Uint128::MAX,
+                    expires: None,
+                })
+                .unwrap(),
+            }]),
+        )
+    };
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        token_poll_msg("first"),
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::UpdateExecutionDenylist {
+        denylist: vec![VOTING_TOKEN.to_string()],
+    };
+
+    // only a poll can update the denylist, not even the owner
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_CREATOR, &[]),
+        msg.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::UpdateExecutionDenylist {
+            denylist: vec![MOCK_CONTRACT_ADDR.to_string()],
+        },
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::InvalidConfig(
+            "execution_denylist".to_string(),
+            "without the gov contract".to_string()
+        )
+    );
+
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        msg,
+    )
+    .unwrap();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.execution_denylist, vec![VOTING_TOKEN.to_string()]);
+
+    // new polls can not target a denied contract
+    let res = execute(deps.as_mut(), mock_env(), info, token_poll_msg("second"));
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::DeniedExecuteTarget(VOTING_TOKEN.to_string())
+    );
+
+    // a poll created before the contract was denied fails when executed
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::ExecutePollMsgs { poll_id: 1 },
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::DeniedExecuteTarget(VOTING_TOKEN.to_string())
+    );
+}
+
+fn create_spend_poll_msg(
+    execute_msgs: Option<Vec<PollExecuteMsg>>,
+    recipient: &str,
+    amount: u128,
+) -> ExecuteMsg {
+    ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_CREATOR.to_string(),
+        amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+        msg: to_binary(&Cw20HookMsg::CreatePoll {
+            title: "spend".to_string(),
+            description: "spend".to_string(),
+            link: None,
+            execute_msgs,
+            spend: Some(PollSpendMsg {
+                recipient: recipient.to_string(),
+                amount: Uint128::from(amount),
+            }),
+            weight_mode: None,
+        })
+        .unwrap(),
+    })
+}
+
+#[test]
+fn create_spend_poll() {
+    const COMMUNITY_FUND: &str = "community";
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+    let info = mock_info(VOTING_TOKEN, &[]);
+
+    let msg = create_spend_poll_msg(None, TEST_VOTER, 100);
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::CommunityFundNotSet {}) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    let msg = ExecuteMsg::UpdateProtocolAddresses(ProtocolAddressesMsg {
+        community_fund: Some(Some(COMMUNITY_FUND.to_string())),
+        ..ProtocolAddressesMsg::default()
+    });
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        msg,
+    )
+    .unwrap();
+
+    let msg = update_config_msg(|update| {
+        update.max_spend_per_poll = Some(Uint128::from(1000u128));
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+
+    let msg = create_spend_poll_msg(None, TEST_VOTER, 0);
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::InvalidSpendAmount {}) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    let msg = create_spend_poll_msg(None, TEST_VOTER, 1001);
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::SpendLimitExceeded(1000)) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    let msg = create_spend_poll_msg(None, "a", 1000);
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg) {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::Std(_)) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    let token_msg = to_binary(&Cw20ExecuteMsg::Burn {
+        amount: Uint128::new(123),
+    })
+    .unwrap();
+    let execute_msgs = vec![PollExecuteMsg {
+        order: 2u64,
+        contract: VOTING_TOKEN.to_string(),
+        msg: token_msg.clone(),
+    }];
+    let msg = create_spend_poll_msg(Some(execute_msgs), TEST_VOTER, 1000);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PollExecuteMsgs { poll_id: 1 },
+    )
+    .unwrap();
+    let response: PollExecuteMsgsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        response.execute_msgs,
+        vec![
+            PollExecuteMsgsResponseItem {
+                order: 2u64,
+                contract: VOTING_TOKEN.to_string(),
+                msg: token_msg,
+                decoded_msg: Some("{\"burn\":{\"amount\":\"123\"}}".to_string()),
+            },
+            PollExecuteMsgsResponseItem {
+                order: 3u64,
+                contract: COMMUNITY_FUND.to_string(),
+                msg: to_binary(&CommunityExecuteMsg::Spend {
+                    recipient: TEST_VOTER.to_string(),
+                    amount: Uint128::from(1000u128),
+                })
+                .unwrap(),
+                decoded_msg: Some(
+                    "{\"spend\":{\"recipient\":\"voter1\",\"amount\":\"1000\"}}".to_string()
+                ),
+            },
+        ]
+    );
+}
+
+#[test]
+fn execute_poll_with_order() {
+    const POLL_START_HEIGHT: u64 = 1000;
+    const POLL_ID: u64 = 1;
+    let stake_amount = 1000;
+
+    let mut deps = mock_dependencies(&coins(1000, VOTING_TOKEN));
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+    let mut creator_env = mock_env_height(POLL_START_HEIGHT, 10000);
+    let mut creator_info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
+
+    let exec_msg_bz = to_binary(&Cw20ExecuteMsg::Burn {
+        amount: Uint128::new(10),
+    })
+    .unwrap();
+
+    let exec_msg_bz2 = to_binary(&Cw20ExecuteMsg::Burn {
+        amount: Uint128::new(20),
+    })
+    .unwrap();
+
+    let exec_msg_bz3 = to_binary(&Cw20ExecuteMsg::Burn {
+        amount: Uint128::new(30),
+    })
+    .unwrap();
+    let exec_msg_bz4 = to_binary(&Cw20ExecuteMsg::Burn {
+        amount: Uint128::new(40),
+    })
+    .unwrap();
+    let exec_msg_bz5 = to_binary(&Cw20ExecuteMsg::Burn {
+        amount: Uint128::new(50),
+    })
+    .unwrap();
+
+    //add three messages with different order
+    let execute_msgs: Vec<PollExecuteMsg> = vec![
+        PollExecuteMsg {
+            order: 3u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: exec_msg_bz3.clone(),
+        },
+        PollExecuteMsg {
+            order: 4u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: exec_msg_bz4.clone(),
+        },
+        PollExecuteMsg {
+            order: 2u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: exec_msg_bz2.clone(),
+        },
+        PollExecuteMsg {
+            order: 5u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: exec_msg_bz5.clone(),
+        },
+        PollExecuteMsg {
+            order: 1u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: exec_msg_bz.clone(),
+        },
+    ];
+
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(execute_msgs),
+    );
+
+    let execute_res = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        msg,
+    )
+    .unwrap();
+
+    assert_create_poll_result(
+        1,
+        creator_env.block.height + DEFAULT_VOTING_PERIOD,
+        TEST_CREATOR,
+        execute_res,
+        deps.as_ref(),
+    );
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+        )],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(stake_amount as u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_stake_tokens_result(
+        stake_amount,
+        DEFAULT_PROPOSAL_DEPOSIT,
+        stake_amount,
+        1,
+        execute_res,
+        deps.as_ref(),
+    );
+
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(stake_amount),
+    };
+    let env = mock_env_height(POLL_START_HEIGHT, 10000);
+    let info = mock_info(TEST_VOTER, &[]);
+    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "cast_vote"),
+            attr("poll_id", POLL_ID.to_string().as_str()),
+            attr("amount", "1000"),
+            attr("voter", TEST_VOTER),
+            attr("vote_option", "yes"),
+        ]
+    );
+
+    creator_info.sender = Addr::unchecked(TEST_CREATOR);
+    creator_env.block.height += DEFAULT_VOTING_PERIOD;
+
+    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
+    let execute_res = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        msg,
+    )
+    .unwrap();
+
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "end_poll"),
+            attr("poll_id", "1"),
+            attr("rejected_reason", ""),
+            attr("passed", "true"),
+        ]
+    );
+    assert_eq!(
+        execute_res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: TEST_CREATOR.to_string(),
+                amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+
+    // End poll will withdraw deposit balance
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(stake_amount as u128),
+        )],
+    )]);
+
+    creator_env.block.height += DEFAULT_TIMELOCK_PERIOD;
+    let msg = ExecuteMsg::ExecutePoll { poll_id: 1 };
+    let execute_res = execute(deps.as_mut(), creator_env.clone(), creator_info, msg).unwrap();
+    assert_eq!(
+        execute_res.messages,
+        vec![SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: creator_env.contract.address.to_string(),
+                msg: to_binary(&ExecuteMsg::ExecutePollMsgs { poll_id: 1 }).unwrap(),
+                funds: vec![],
+            }),
+            1
+        )]
+    );
+
+    let msg = ExecuteMsg::ExecutePollMsgs { poll_id: 1 };
+    let contract_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+    let execute_res = execute(deps.as_mut(), creator_env, contract_info, msg).unwrap();
+    assert_eq!(
+        execute_res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz,
+                funds: vec![],
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz2,
+                funds: vec![],
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz3,
+                funds: vec![],
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz4,
+                funds: vec![],
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz5,
+                funds: vec![],
+            })),
+        ]
+    );
+    assert_eq!(
+        execute_res.attributes,
+        vec![attr("action", "execute_poll"), attr("poll_id", "1"),]
+    );
+}
+
+#[test]
+fn poll_with_empty_execute_data_marked_as_executed() {
+    const POLL_START_HEIGHT: u64 = 1000;
+    const POLL_ID: u64 = 1;
+    let stake_amount = 1000;
+
+    let mut deps = mock_dependencies(&coins(1000, VOTING_TOKEN));
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+    let mut creator_env = mock_env_height(POLL_START_HEIGHT, 10000);
+    let mut creator_info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
+
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, Some(vec![]));
+
+    let execute_res = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        msg,
+    )
+    .unwrap();
+
+    assert_create_poll_result(
+        1,
+        creator_env.block.height + DEFAULT_VOTING_PERIOD,
+        TEST_CREATOR,
+        execute_res,
+        deps.as_ref(),
+    );
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+        )],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(stake_amount as u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_stake_tokens_result(
+        stake_amount,
+        DEFAULT_PROPOSAL_DEPOSIT,
+        stake_amount,
+        1,
+        execute_res,
+        deps.as_ref(),
+    );
+
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(stake_amount),
+    };
+    let env = mock_env_height(POLL_START_HEIGHT, 10000);
+    let info = mock_info(TEST_VOTER, &[]);
+    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "cast_vote"),
+            attr("poll_id", POLL_ID.to_string().as_str()),
+            attr("amount", "1000"),
+            attr("voter", TEST_VOTER),
+            attr("vote_option", "yes"),
+        ]
+    );
+
+    creator_info.sender = Addr::unchecked(TEST_CREATOR);
+    creator_env.block.height += DEFAULT_VOTING_PERIOD;
+
+    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
+    let execute_res = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        msg,
+    )
+    .unwrap();
+
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "end_poll"),
+            attr("poll_id", "1"),
+            attr("rejected_reason", ""),
+            attr("passed", "true"),
+        ]
+    );
+    assert_eq!(
+        execute_res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: TEST_CREATOR.to_string(),
+                amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+
+    // End poll will withdraw deposit balance
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(stake_amount as u128),
+        )],
+    )]);
+
+    creator_env.block.height += DEFAULT_TIMELOCK_PERIOD;
+    let msg = ExecuteMsg::ExecutePoll { poll_id: 1 };
+    let execute_res = execute(deps.as_mut(), creator_env.clone(), creator_info, msg).unwrap();
+    assert_eq!(
+        execute_res.messages,
+        vec![SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: creator_env.contract.address.to_string(),
+                msg: to_binary(&ExecuteMsg::ExecutePollMsgs { poll_id: 1 }).unwrap(),
+                funds: vec![],
+            }),
+            1
+        )]
+    );
+
+    // Executes since empty polls are allowed
+    let msg = ExecuteMsg::ExecutePollMsgs { poll_id: 1 };
+    let contract_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+    let execute_res = execute(deps.as_mut(), creator_env, contract_info, msg).unwrap();
+    assert_eq!(execute_res.messages, vec![]);
+    assert_eq!(
+        execute_res.attributes,
+        vec![attr("action", "execute_poll"), attr("poll_id", "1")]
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let poll_res: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll_res.status, PollStatus::Executed);
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Polls {
+            filter: Some(PollStatus::Executed),
+            start_after: None,
+            limit: None,
+            order_by: Some(OrderBy::Desc),
+        },
+    )
+    .unwrap();
+    let polls_res: PollsResponse = from_binary(&res).unwrap();
+    assert_eq!(polls_res.polls[0], poll_res);
+}
+
+#[test]
+fn poll_with_none_execute_data_marked_as_executed() {
+    const POLL_START_HEIGHT: u64 = 1000;
+    const POLL_ID: u64 = 1;
+    let stake_amount = 1000;
+
+    let mut deps = mock_dependencies(&coins(1000, VOTING_TOKEN));
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+    let mut creator_env = mock_env_height(POLL_START_HEIGHT, 10000);
+    let mut creator_info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
+
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+
+    let execute_res = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        msg,
+    )
+    .unwrap();
+
+    assert_create_poll_result(
+        1,
+        creator_env.block.height + DEFAULT_VOTING_PERIOD,
+        TEST_CREATOR,
+        execute_res,
+        deps.as_ref(),
+    );
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+        )],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(stake_amount as u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_stake_tokens_result(
+        stake_amount,
+        DEFAULT_PROPOSAL_DEPOSIT,
+        stake_amount,
+        1,
+        execute_res,
+        deps.as_ref(),
+    );
+
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(stake_amount),
+    };
+    let env = mock_env_height(POLL_START_HEIGHT, 10000);
+    let info = mock_info(TEST_VOTER, &[]);
+    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "cast_vote"),
+            attr("poll_id", POLL_ID.to_string().as_str()),
+            attr("amount", "1000"),
+            attr("voter", TEST_VOTER),
+            attr("vote_option", "yes"),
+        ]
+    );
+
+    creator_info.sender = Addr::unchecked(TEST_CREATOR);
+    creator_env.block.height += DEFAULT_VOTING_PERIOD;
+
+    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
+    let execute_res = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        msg,
+    )
+    .unwrap();
+
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "end_poll"),
+            attr("poll_id", "1"),
+            attr("rejected_reason", ""),
+            attr("passed", "true"),
+        ]
+    );
+    assert_eq!(
+        execute_res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: TEST_CREATOR.to_string(),
+                amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+
+    // End poll will withdraw deposit balance
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(stake_amount as u128),
+        )],
+    )]);
+
+    creator_env.block.height += DEFAULT_TIMELOCK_PERIOD;
+    let msg = ExecuteMsg::ExecutePoll { poll_id: 1 };
+    let execute_res = execute(deps.as_mut(), creator_env.clone(), creator_info, msg).unwrap();
+    assert_eq!(
+        execute_res.messages,
+        vec![SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: creator_env.contract.address.to_string(),
+                msg: to_binary(&ExecuteMsg::ExecutePollMsgs { poll_id: 1 }).unwrap(),
+                funds: vec![],
+            }),
+            1
+        )]
+    );
+
+    // Executes since empty polls are allowed
+    let msg = ExecuteMsg::ExecutePollMsgs { poll_id: 1 };
+    let contract_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+    let execute_res = execute(deps.as_mut(), creator_env, contract_info, msg).unwrap();
+    assert_eq!(execute_res.messages, vec![]);
+    assert_eq!(
+        execute_res.attributes,
+        vec![attr("action", "execute_poll"), attr("poll_id", "1")]
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let poll_res: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll_res.status, PollStatus::Executed);
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Polls {
+            filter: Some(PollStatus::Executed),
+            start_after: None,
+            limit: None,
+            order_by: Some(OrderBy::Desc),
+        },
+    )
+    .unwrap();
+    let polls_res: PollsResponse = from_binary(&res).unwrap();
+    assert_eq!(polls_res.polls[0], poll_res);
+}
+
+#[test]
+fn snapshot_poll() {
+    let stake_amount = 1000;
+
+    let mut deps = mock_dependencies(&coins(100, VOTING_TOKEN));
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let mut creator_env = mock_env();
+    let creator_info = mock_info(VOTING_TOKEN, &[]);
+    let execute_res = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "create_poll"),
+            attr("creator", TEST_CREATOR),
+            attr("poll_id", "1"),
+            attr("end_height", "32345"),
+        ]
+    );
+
+    //must not be executed
+    let snapshot_err = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        ExecuteMsg::SnapshotPoll { poll_id: 1 },
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::SnapshotHeight {}, snapshot_err);
+
+    // change time
+    creator_env.block.height = 32345 - 10;
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+        )],
+    )]);
+
+    let fix_res = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        ExecuteMsg::SnapshotPoll { poll_id: 1 },
+    )
+    .unwrap();
+
+    assert_eq!(
+        fix_res.attributes,
+        vec![
+            attr("action", "snapshot_poll"),
+            attr("poll_id", "1"),
+            attr("staked_amount", stake_amount.to_string().as_str()),
+        ]
+    );
+
+    //must not be executed
+    let snapshot_error = execute(
+        deps.as_mut(),
+        creator_env,
+        creator_info,
+        ExecuteMsg::SnapshotPoll { poll_id: 1 },
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::SnapshotAlreadyOccurred {}, snapshot_error);
+}
+
+#[test]
+fn happy_days_cast_vote_with_snapshot() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let env = mock_env_height(0, 10000);
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+
+    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_create_poll_result(
+        1,
+        DEFAULT_VOTING_PERIOD,
+        TEST_CREATOR,
+        execute_res,
+        deps.as_ref(),
+    );
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(11u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(11u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_stake_tokens_result(
+        11,
+        DEFAULT_PROPOSAL_DEPOSIT,
+        11,
+        1,
+        execute_res,
+        deps.as_ref(),
+    );
+
+    //cast_vote without snapshot
+    let env = mock_env_height(0, 10000);
+    let info = mock_info(TEST_VOTER, &coins(11, VOTING_TOKEN));
+    let amount = 10u128;
+
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(amount),
+    };
+
+    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_cast_vote_success(TEST_VOTER, amount, 1, VoteOption::Yes, execute_res);
+
+    // balance be double
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(22u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let value: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(value.staked_amount, None);
+    let end_height = value.end_height;
+
+    //cast another vote
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER_2.to_string(),
+        amount: Uint128::from(11u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let _execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // another voter cast a vote
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(10u128),
+    };
+    let env = mock_env_height(end_height - 9, 10000);
+    let info = mock_info(TEST_VOTER_2, &[]);
+    let execute_res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    assert_cast_vote_success(TEST_VOTER_2, amount, 1, VoteOption::Yes, execute_res);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let value: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(value.staked_amount, Some(Uint128::new(22)));
+
+    // snanpshot poll will not go through
+    let snap_error = execute(
+        deps.as_mut(),
+        env,
+        info,
+        ExecuteMsg::SnapshotPoll { poll_id: 1 },
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::SnapshotAlreadyOccurred {}, snap_error);
+
+    // balance be double
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(33u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+
+    // another voter cast a vote but the snapshot is already occurred
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER_3.to_string(),
+        amount: Uint128::from(11u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let _execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(10u128),
+    };
+    let env = mock_env_height(end_height - 8, 10000);
+    let info = mock_info(TEST_VOTER_3, &[]);
+    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_cast_vote_success(TEST_VOTER_3, amount, 1, VoteOption::Yes, execute_res);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let value: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(value.staked_amount, Some(Uint128::new(22)));
+}
+
+#[test]
+fn fails_end_poll_quorum_inflation_without_snapshot_poll() {
+    const POLL_START_HEIGHT: u64 = 1000;
+    const POLL_ID: u64 = 1;
+    let stake_amount = 1000;
+
+    let mut deps = mock_dependencies(&coins(1000, VOTING_TOKEN));
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let mut creator_env = mock_env_height(POLL_START_HEIGHT, 10000);
+    let mut creator_info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
+
+    let exec_msg_bz = to_binary(&Cw20ExecuteMsg::Burn {
+        amount: Uint128::new(123),
+    })
+    .unwrap();
+
+    //add two messages
+    let execute_msgs: Vec<PollExecuteMsg> = vec![
+        PollExecuteMsg {
+            order: 1u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: exec_msg_bz,
+        },
+        PollExecuteMsg {
+            order: 2u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::new(12),
+            })
+            .unwrap(),
+        },
+    ];
+
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(execute_msgs),
+    );
+
+    let execute_res = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        msg,
+    )
+    .unwrap();
+
+    assert_create_poll_result(
+        1,
+        creator_env.block.height + DEFAULT_VOTING_PERIOD,
+        TEST_CREATOR,
+        execute_res,
+        deps.as_ref(),
+    );
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+        )],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(stake_amount as u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_stake_tokens_result(
+        stake_amount,
+        DEFAULT_PROPOSAL_DEPOSIT,
+        stake_amount,
+        1,
+        execute_res,
+        deps.as_ref(),
+    );
+
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(stake_amount),
+    };
+    let env = mock_env_height(POLL_START_HEIGHT, 10000);
+    let info = mock_info(TEST_VOTER, &[]);
+    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "cast_vote"),
+            attr("poll_id", POLL_ID.to_string().as_str()),
+            attr("amount", "1000"),
+            attr("voter", TEST_VOTER),
+            attr("vote_option", "yes"),
+        ]
+    );
+
+    creator_env.block.height += DEFAULT_VOTING_PERIOD - 10;
+
+    // did not SnapshotPoll
+
+    // staked amount get increased 10 times
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(((10 * stake_amount) + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+        )],
+    )]);
+
+    //cast another vote
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER_2.to_string(),
+        amount: Uint128::from(8 * stake_amount as u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let _execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // another voter cast a vote
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(stake_amount),
+    };
+    let env = mock_env_height(creator_env.block.height, 10000);
+    let info = mock_info(TEST_VOTER_2, &[]);
+    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "cast_vote"),
+            attr("poll_id", POLL_ID.to_string().as_str()),
+            attr("amount", "1000"),
+            attr("voter", TEST_VOTER_2),
+            attr("vote_option", "yes"),
+        ]
+    );
+
+    creator_info.sender = Addr::unchecked(TEST_CREATOR);
+    creator_env.block.height += 10;
+
+    // quorum must reach
+    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
+    let execute_res = execute(deps.as_mut(), creator_env, creator_info, msg).unwrap();
+
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "end_poll"),
+            attr("poll_id", "1"),
+            attr("rejected_reason", "Quorum not reached"),
+            attr("passed", "false"),
+        ]
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let value: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        10 * stake_amount,
+        value.total_balance_at_end_poll.unwrap().u128()
+    );
+}
+
+#[test]
+fn happy_days_end_poll_with_controlled_quorum() {
+    const POLL_START_HEIGHT: u64 = 1000;
+    const POLL_ID: u64 = 1;
+    let stake_amount = 1000;
+
+    let mut deps = mock_dependencies(&coins(1000, VOTING_TOKEN));
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let mut creator_env = mock_env_height(POLL_START_HEIGHT, 10000);
+    let mut creator_info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
+
+    let exec_msg_bz = to_binary(&Cw20ExecuteMsg::Burn {
+        amount: Uint128::new(123),
+    })
+    .unwrap();
+
+    //add two messages
+    let execute_msgs: Vec<PollExecuteMsg> = vec![
+        PollExecuteMsg {
+            order: 1u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: exec_msg_bz,
+        },
+        PollExecuteMsg {
+            order: 2u64,
+            contract: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::new(12),
+            })
+            .unwrap(),
+        },
+    ];
+
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(execute_msgs),
+    );
+
+    let execute_res = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        msg,
+    )
+    .unwrap();
+
+    assert_create_poll_result(
+        1,
+        creator_env.block.height + DEFAULT_VOTING_PERIOD,
+        TEST_CREATOR,
+        execute_res,
+        deps.as_ref(),
+    );
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+        )],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(stake_amount as u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_stake_tokens_result(
+        stake_amount,
+        DEFAULT_PROPOSAL_DEPOSIT,
+        stake_amount,
+        1,
+        execute_res,
+        deps.as_ref(),
+    );
+
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(stake_amount),
+    };
+    let env = mock_env_height(POLL_START_HEIGHT, 10000);
+    let info = mock_info(TEST_VOTER, &[]);
+    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "cast_vote"),
+            attr("poll_id", POLL_ID.to_string().as_str()),
+            attr("amount", "1000"),
+            attr("voter", TEST_VOTER),
+            attr("vote_option", "yes"),
+        ]
+    );
+
+    creator_env.block.height += DEFAULT_VOTING_PERIOD - 10;
+
+    // send SnapshotPoll
+    let fix_res = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        ExecuteMsg::SnapshotPoll { poll_id: 1 },
+    )
+    .unwrap();
+
+    assert_eq!(
+        fix_res.attributes,
+        vec![
+            attr("action", "snapshot_poll"),
+            attr("poll_id", "1"),
+            attr("staked_amount", stake_amount.to_string().as_str()),
+        ]
+    );
+
+    // staked amount get increased 10 times
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(((10 * stake_amount) + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+        )],
+    )]);
+
+    //cast another vote
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER_2.to_string(),
+        amount: Uint128::from(8 * stake_amount as u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let _execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(8 * stake_amount),
+    };
+    let env = mock_env_height(creator_env.block.height, 10000);
+    let info = mock_info(TEST_VOTER_2, &[]);
+    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "cast_vote"),
+            attr("poll_id", POLL_ID.to_string().as_str()),
+            attr("amount", "8000"),
+            attr("voter", TEST_VOTER_2),
+            attr("vote_option", "yes"),
+        ]
+    );
+
+    creator_info.sender = Addr::unchecked(TEST_CREATOR);
+    creator_env.block.height += 10;
+
+    // quorum must reach
+    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
+    let execute_res = execute(deps.as_mut(), creator_env, creator_info, msg).unwrap();
+
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "end_poll"),
+            attr("poll_id", "1"),
+            attr("rejected_reason", ""),
+            attr("passed", "true"),
+        ]
+    );
+    assert_eq!(
+        execute_res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: TEST_CREATOR.to_string(),
+                amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let value: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        stake_amount,
+        value.total_balance_at_end_poll.unwrap().u128()
+    );
+
+    assert_eq!(value.yes_votes.u128(), 9 * stake_amount);
+
+    // actual staked amount is 10 times bigger than staked amount
+    let actual_staked_weight = query_token_balance(
+        &deps.as_ref().querier,
+        Addr::unchecked(VOTING_TOKEN),
+        Addr::unchecked(MOCK_CONTRACT_ADDR),
+    )
+    .unwrap()
+    .checked_sub(Uint128::from(DEFAULT_PROPOSAL_DEPOSIT))
+    .unwrap();
+
+    assert_eq!(actual_staked_weight.u128(), (10 * stake_amount))
+}
+
+fn mock_enable_sponsorship(deps: DepsMut, threshold: u128, period: u64) {
+    let info = mock_info(TEST_CREATOR, &[]);
+    let msg = update_config_msg(|update| {
+        update.sponsorship_threshold = Some(Uint128::from(threshold));
+        update.sponsorship_period = Some(period);
+    });
+    let _res = execute(deps, mock_env(), info, msg).unwrap();
+}
+
+type ConfigChange = fn(&mut ConfigUpdate);
+
+fn update_config_msg(set: impl FnOnce(&mut ConfigUpdate)) -> ExecuteMsg {
+    let mut update = ConfigUpdate::default();
+    set(&mut update);
+    ExecuteMsg::UpdateConfig(update)
+}
+
+fn update_config_error(deps: DepsMut, set: ConfigChange) -> ContractError {
+    let msg = update_config_msg(set);
+    execute(deps, mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap_err()
+}
+
+#[test]
+fn query_config_history() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let set_quorum: ConfigChange = |update| {
+        update.quorum = Some(Decimal::percent(20));
+    };
+    let env = mock_env_height(100, 10000);
+    let info = mock_info(TEST_CREATOR, &[]);
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        update_config_msg(set_quorum),
+    )
+    .unwrap();
+
+    // updates changing nothing are not recorded
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        update_config_msg(set_quorum),
+    )
+    .unwrap();
+
+    // hand the config over to polls
+    let msg = update_config_msg(|update| {
+        update.owner = Some(MOCK_CONTRACT_ADDR.to_string());
+    });
+    let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+    let poll_msg = update_config_msg(|update| {
+        update.threshold = Some(Decimal::percent(60));
+        update.timelock_period = Some(10);
+    });
+    let execute_msgs = vec![PollExecuteMsg {
+        order: 1u64,
+        contract: MOCK_CONTRACT_ADDR.to_string(),
+        msg: to_binary(&poll_msg).unwrap(),
+    }];
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(execute_msgs),
+    );
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(0, 10000),
+        mock_info(VOTING_TOKEN, &[]),
+        msg,
+    )
+    .unwrap();
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(10u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    mock_stake(deps.as_mut(), TEST_VOTER, 10);
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(10u128),
+    };
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(0, 10000),
+        mock_info(TEST_VOTER, &[]),
+        msg,
+    )
+    .unwrap();
+
+    let env = mock_env_height(DEFAULT_VOTING_PERIOD, 20000);
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 1 },
+    )
+    .unwrap();
+
+    let env = mock_env_height(DEFAULT_VOTING_PERIOD + DEFAULT_TIMELOCK_PERIOD, 20000);
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::ExecutePoll { poll_id: 1 },
+    )
+    .unwrap();
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        poll_msg,
+    )
+    .unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ConfigHistory {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let history: ConfigHistoryResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        history.changes,
+        vec![
+            ConfigHistoryResponseItem {
+                id: 0,
+                height: 100,
+                time: 10000,
+                sender: TEST_CREATOR.to_string(),
+                poll_id: None,
+                changed_fields: vec!["quorum".to_string()],
+            },
+            ConfigHistoryResponseItem {
+                id: 1,
+                height: 100,
+                time: 10000,
+                sender: TEST_CREATOR.to_string(),
+                poll_id: None,
+                changed_fields: vec!["owner".to_string()],
+            },
+            ConfigHistoryResponseItem {
+                id: 2,
+                height: env.block.height,
+                time: 20000,
+                sender: MOCK_CONTRACT_ADDR.to_string(),
+                poll_id: Some(1),
+                changed_fields: vec!["threshold".to_string(), "timelock_period".to_string()],
+            },
+        ]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ConfigHistory {
+            start_after: Some(1),
+            limit: None,
+        },
+    )
+    .unwrap();
+    let history: ConfigHistoryResponse = from_binary(&res).unwrap();
+    assert_eq!(history.changes.len(), 1);
+    assert_eq!(history.changes[0].poll_id, Some(1));
+}
+
+#[test]
+fn fails_update_config_invalid_values() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let ratio = "greater than 0 and at most 1".to_string();
+    let period = "1 to 2592000 blocks".to_string();
+    let cases: [(ConfigChange, &str, &str); 18] = [
+        // a percentage given where a ratio is expected
+        (
+            |update| {
+                update.quorum = Some(Decimal::from_ratio(51u128, 1u128));
+            },
+            "quorum",
+            &ratio,
+        ),
+        (
+            |update| {
+                update.quorum = Some(Decimal::zero());
+            },
+            "quorum",
+            &ratio,
+        ),
+        (
+            |update| {
+                update.threshold = Some(Decimal::zero());
+            },
+            "threshold",
+            &ratio,
+        ),
+        (
+            |update| {
+                update.voting_period = Some(0);
+            },
+            "voting_period",
+            &period,
+        ),
+        (
+            |update| {
+                update.voting_period = Some(2_592_001);
+            },
+            "voting_period",
+            &period,
+        ),
+        (
+            |update| {
+                update.timelock_period = Some(0);
+            },
+            "timelock_period",
+            &period,
+        ),
+        (
+            |update| {
+                update.snapshot_period = Some(DEFAULT_VOTING_PERIOD + 1);
+            },
+            "snapshot_period",
+            "at most voting_period",
+        ),
+        (
+            |update| {
+                update.proposal_deposit = Some(Uint128::zero());
+            },
+            "proposal_deposit",
+            "greater than 0",
+        ),
+        (
+            |update| {
+                update.sponsorship_threshold = Some(Uint128::from(10u128));
+                update.sponsorship_period = Some(0);
+            },
+            "sponsorship_period",
+            &period,
+        ),
+        (
+            |update| {
+                update.block_time = Some(0);
+            },
+            "block_time",
+            "1 to 60 seconds",
+        ),
+        (
+            |update| {
+                update.late_quorum_window = Some(DEFAULT_VOTING_PERIOD + 1);
+            },
+            "late_quorum_window",
+            "at most voting_period",
+        ),
+        (
+            |update| {
+                update.participation_reward_ratio = Some(Decimal::percent(101));
+            },
+            "participation_reward_ratio",
+            "at most 1",
+        ),
+        (
+            |update| {
+                update.max_open_polls_per_creator = Some(101);
+            },
+            "max_open_polls_per_creator",
+            "at most 100",
+        ),
+        (
+            |update| {
+                update.vote_weight_buckets =
+                    Some(vec![Uint128::from(10u128), Uint128::from(10u128)]);
+            },
+            "vote_weight_buckets",
+            "ascending and above 0, with at most 10 boundaries",
+        ),
+        (
+            |update| {
+                update.resubmit_deposit = Some(Uint128::zero());
+            },
+            "resubmit_deposit",
+            "greater than 0",
+        ),
+        (
+            |update| {
+                update.max_resubmit_depth = Some(6);
+            },
+            "max_resubmit_depth",
+            "at most 5",
+        ),
+        // no cap is set with null
+        (
+            |update| {
+                update.deposit_cap = Some(Some(Uint128::zero()));
+            },
+            "deposit_cap",
+            "greater than 0",
+        ),
+        (
+            |update| {
+                update.expired_deposit_refund_ratio = Some(Decimal::percent(101));
+            },
+            "expired_deposit_refund_ratio",
+            "at most 1",
+        ),
+    ];
+
+    for (set, field, allowed) in cases {
+        assert_eq!(
+            update_config_error(deps.as_mut(), set),
+            ContractError::InvalidConfig(field.to_string(), allowed.to_string())
+        );
+    }
+
+    // failed updates leave the config untouched
+    let config: Config = config_read(&deps.storage).load().unwrap();
+    assert_eq!(config.quorum, Decimal::percent(DEFAULT_QUORUM));
+    assert_eq!(config.voting_period, DEFAULT_VOTING_PERIOD);
+    assert_eq!(
+        config.proposal_deposit,
+        Uint128::from(DEFAULT_PROPOSAL_DEPOSIT)
+    );
+}
+
+fn mock_set_poll_clock(deps: DepsMut, clock: PollClock) {
+    let info = mock_info(TEST_CREATOR, &[]);
+    let msg = update_config_msg(|update| {
+        update.poll_clock = Some(clock);
+    });
+    let _res = execute(deps, mock_env(), info, msg).unwrap();
+}
+
+fn mock_stake(deps: DepsMut, staker: &str, amount: u128) {
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: staker.to_string(),
+        amount: Uint128::from(amount),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens { staker: None }).unwrap(),
+    });
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let _res = execute(deps, mock_env(), info, msg).unwrap();
+}
+
+#[test]
+fn happy_days_sponsor_poll() {
+    const SPONSORSHIP_PERIOD: u64 = 100u64;
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+    mock_enable_sponsorship(deps.as_mut(), 15, SPONSORSHIP_PERIOD);
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(20u128))],
+    )]);
+    mock_stake(deps.as_mut(), TEST_VOTER, 10);
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(20u128))],
+    )]);
+    mock_stake(deps.as_mut(), TEST_VOTER_2, 10);
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(20u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+
+    let env = mock_env_height(0, 10000);
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(
+        execute_res.attributes[3],
+        attr("end_height", SPONSORSHIP_PERIOD.to_string())
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let value: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(PollStatus::Pending, value.status);
+
+    // voting is not possible before the poll is sponsored
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(10u128),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(1, 10000),
+        mock_info(TEST_VOTER, &[]),
+        msg,
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollNotInProgress {});
+
+    // first sponsor is not enough to reach the threshold
+    let msg = ExecuteMsg::SponsorPoll { poll_id: 1 };
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(10, 10000),
+        mock_info(TEST_VOTER, &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "sponsor_poll"),
+            attr("poll_id", "1"),
+            attr("sponsor", TEST_VOTER),
+            attr("weight", "10"),
+            attr("activated", "false"),
+        ]
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(10, 10000),
+        mock_info(TEST_VOTER, &[]),
+        msg.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::AlreadySponsored {});
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(10, 10000),
+        mock_info(TEST_VOTER_3, &[]),
+        msg.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::NothingStaked {});
+
+    let sponsored_balance = |deps: Deps| {
+        let msg = QueryMsg::Staker {
+            address: TEST_VOTER.to_string(),
+        };
+        from_binary::<StakerResponse>(&query(deps, mock_env(), msg).unwrap())
+            .unwrap()
+            .sponsored_balance
+    };
+    assert_eq!(
+        sponsored_balance(deps.as_ref()),
+        vec![(1u64, Uint128::from(10u128))]
+    );
+
+    // second sponsor activates the poll and starts the voting clock
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(50, 10000),
+        mock_info(TEST_VOTER_2, &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(res.attributes[4], attr("activated", "true"));
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let value: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(PollStatus::InProgress, value.status);
+    assert_eq!(50 + DEFAULT_VOTING_PERIOD, value.end_height);
+
+    // activation releases the sponsorships
+    assert_eq!(sponsored_balance(deps.as_ref()), vec![]);
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Sponsors {
+            poll_id: 1,
+            start_after: None,
+            limit: None,
+            order_by: Some(OrderBy::Asc),
+        },
+    )
+    .unwrap();
+    let value: SponsorsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        value.sponsors,
+        vec![
+            SponsorsResponseItem {
+                sponsor: TEST_VOTER_2.to_string(),
+                weight: Uint128::from(10u128),
+            },
+            SponsorsResponseItem {
+                sponsor: TEST_VOTER.to_string(),
+                weight: Uint128::from(10u128),
+            },
+        ]
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(51, 10000),
+        mock_info(TEST_VOTER_3, &[]),
+        msg,
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollNotPending {});
+
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(10u128),
+    };
+    let execute_res = execute(
+        deps.as_mut(),
+        mock_env_height(51, 10000),
+        mock_info(TEST_VOTER, &[]),
+        msg,
+    )
+    .unwrap();
+    assert_cast_vote_success(TEST_VOTER, 10, 1, VoteOption::Yes, execute_res);
+}
+
+#[test]
+fn expire_unsponsored_poll() {
+    const SPONSORSHIP_PERIOD: u64 = 100u64;
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+    mock_enable_sponsorship(deps.as_mut(), 15, SPONSORSHIP_PERIOD);
+
+    let msg = update_config_msg(|update| {
+        update.expired_deposit_refund_ratio = Some(Decimal::percent(20));
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(10u128))],
+    )]);
+    mock_stake(deps.as_mut(), TEST_VOTER, 10);
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(10u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SponsorPoll { poll_id: 1 };
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(10, 10000),
+        mock_info(TEST_VOTER, &[]),
+        msg,
+    )
     .unwrap();
 
-    let exec_msg_bz3 = to_binary(&Cw20ExecuteMsg::Burn {
-        amount: Uint128::new(1),
-    })
-    .unwrap();
+    // the sponsorship stays staked until the poll is activated or expired
+    let withdraw_status = |deps: Deps| -> WithdrawStatusResponse {
+        let msg = QueryMsg::WithdrawStatus {
+            address: TEST_VOTER.to_string(),
+        };
+        from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
+    };
+    let status = withdraw_status(deps.as_ref());
+    assert_eq!(status.locked_amount, Uint128::from(10u128));
+    assert_eq!(
+        status.earliest_full_withdrawal_height,
+        Some(SPONSORSHIP_PERIOD)
+    );
 
-    // push two execute msgs to the list
-    let execute_msgs: Vec<PollExecuteMsg> = vec![
-        PollExecuteMsg {
-            order: 1u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz,
-        },
-        PollExecuteMsg {
-            order: 3u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz3,
-        },
-        PollExecuteMsg {
-            order: 2u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz2,
-        },
-    ];
+    let withdraw = ExecuteMsg::WithdrawVotingTokens { amount: None };
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(SPONSORSHIP_PERIOD + 1, 10000),
+        mock_info(TEST_VOTER, &[]),
+        withdraw.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::InvalidWithdrawAmount {});
 
-    let msg = create_poll_msg(
-        "test".to_string(),
-        "test".to_string(),
-        None,
-        Some(execute_msgs.clone()),
+    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(SPONSORSHIP_PERIOD, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        msg.clone(),
     );
+    assert_eq!(res.unwrap_err(), ContractError::SponsorshipPeriod {});
 
-    let execute_res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
-    assert_create_poll_result(
-        1,
-        env.block.height + DEFAULT_VOTING_PERIOD,
-        TEST_CREATOR,
-        execute_res,
-        deps.as_ref(),
+    // late sponsorship is rejected
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(SPONSORSHIP_PERIOD + 1, 10000),
+        mock_info(TEST_VOTER, &[]),
+        ExecuteMsg::SponsorPoll { poll_id: 1 },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::SponsorshipPeriodExpired {});
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(SPONSORSHIP_PERIOD + 1, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: TEST_CREATOR.to_string(),
+                amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT / 5),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "expire_poll"),
+            attr("poll_id", "1"),
+            attr("refund_amount", (DEFAULT_PROPOSAL_DEPOSIT / 5).to_string()),
+        ]
     );
 
     let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
     let value: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(PollStatus::Expired, value.status);
 
-    let response_execute_data = value.execute_data.unwrap();
-    assert_eq!(response_execute_data.len(), 3);
-    assert_eq!(response_execute_data, execute_msgs);
+    let state: State = state_read(deps.as_ref().storage).load().unwrap();
+    assert_eq!(state.total_deposit, Uint128::zero());
+
+    let creator = deps.api.addr_canonicalize(TEST_CREATOR).unwrap();
+    assert!(read_creator_polls(deps.as_ref().storage, &creator, 10)
+        .unwrap()
+        .is_empty());
+
+    assert_eq!(
+        withdraw_status(deps.as_ref()).locked_amount,
+        Uint128::zero()
+    );
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(SPONSORSHIP_PERIOD + 1, 10000),
+        mock_info(TEST_VOTER, &[]),
+        withdraw,
+    )
+    .unwrap();
 }
 
 #[test]
-fn execute_poll_with_order() {
-    const POLL_START_HEIGHT: u64 = 1000;
-    const POLL_ID: u64 = 1;
-    let stake_amount = 1000;
-
-    let mut deps = mock_dependencies(&coins(1000, VOTING_TOKEN));
+fn max_open_polls_per_creator() {
+    let mut deps = mock_dependencies(&[]);
     mock_instantiate(deps.as_mut());
     mock_register_voting_token(deps.as_mut());
-    let mut creator_env = mock_env_height(POLL_START_HEIGHT, 10000);
-    let mut creator_info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
 
-    let exec_msg_bz = to_binary(&Cw20ExecuteMsg::Burn {
-        amount: Uint128::new(10),
-    })
-    .unwrap();
+    let msg = update_config_msg(|update| {
+        update.max_open_polls_per_creator = Some(2);
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
 
-    let exec_msg_bz2 = to_binary(&Cw20ExecuteMsg::Burn {
-        amount: Uint128::new(20),
-    })
-    .unwrap();
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(1000u128))],
+    )]);
+    mock_stake(deps.as_mut(), TEST_VOTER, 1000);
 
-    let exec_msg_bz3 = to_binary(&Cw20ExecuteMsg::Burn {
-        amount: Uint128::new(30),
-    })
-    .unwrap();
-    let exec_msg_bz4 = to_binary(&Cw20ExecuteMsg::Burn {
-        amount: Uint128::new(40),
-    })
-    .unwrap();
-    let exec_msg_bz5 = to_binary(&Cw20ExecuteMsg::Burn {
-        amount: Uint128::new(50),
+    let exec_msg_bz = to_binary(&Cw20ExecuteMsg::Burn {
+        amount: Uint128::new(123),
     })
     .unwrap();
+    let execute_msgs = vec![PollExecuteMsg {
+        order: 1u64,
+        contract: VOTING_TOKEN.to_string(),
+        msg: exec_msg_bz,
+    }];
 
-    //add three messages with different order
-    let execute_msgs: Vec<PollExecuteMsg> = vec![
-        PollExecuteMsg {
-            order: 3u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz3.clone(),
-        },
-        PollExecuteMsg {
-            order: 4u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz4.clone(),
-        },
-        PollExecuteMsg {
-            order: 2u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz2.clone(),
-        },
-        PollExecuteMsg {
-            order: 5u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz5.clone(),
-        },
-        PollExecuteMsg {
-            order: 1u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz.clone(),
-        },
-    ];
-
+    let env = mock_env_height(0, 10000);
+    let info = mock_info(VOTING_TOKEN, &[]);
     let msg = create_poll_msg(
         "test".to_string(),
         "test".to_string(),
         None,
         Some(execute_msgs),
     );
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
 
-    let execute_res = execute(
-        deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
-        msg,
-    )
-    .unwrap();
-
-    assert_create_poll_result(
-        1,
-        creator_env.block.height + DEFAULT_VOTING_PERIOD,
-        TEST_CREATOR,
-        execute_res,
-        deps.as_ref(),
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone());
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::TooManyOpenPolls("1, 2".to_string())
     );
 
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
         &[(
             &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+            &Uint128::from(1000u128 + 2 * DEFAULT_PROPOSAL_DEPOSIT),
         )],
     )]);
 
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
-    });
-
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        stake_amount,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        stake_amount,
-        1,
-        execute_res,
-        deps.as_ref(),
-    );
-
     let msg = ExecuteMsg::CastVote {
         poll_id: 1,
         vote: VoteOption::Yes,
-        amount: Uint128::from(stake_amount),
+        amount: Uint128::from(1000u128),
     };
-    let env = mock_env_height(POLL_START_HEIGHT, 10000);
-    let info = mock_info(TEST_VOTER, &[]);
-    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+    let _res = execute(deps.as_mut(), env, mock_info(TEST_VOTER, &[]), msg).unwrap();
+
+    // a rejected poll frees a slot, a passed poll keeps it until execution
+    let env = mock_env_height(DEFAULT_VOTING_PERIOD, 10000);
+    for poll_id in 1..=2 {
+        let msg = ExecuteMsg::EndPoll { poll_id };
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_CREATOR, &[]),
+            msg,
+        )
+        .unwrap();
+    }
 
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    let res = execute(deps.as_mut(), env, info.clone(), msg.clone());
     assert_eq!(
-        execute_res.attributes,
-        vec![
-            attr("action", "cast_vote"),
-            attr("poll_id", POLL_ID.to_string().as_str()),
-            attr("amount", "1000"),
-            attr("voter", TEST_VOTER),
-            attr("vote_option", "yes"),
-        ]
+        res.unwrap_err(),
+        ContractError::TooManyOpenPolls("1, 3".to_string())
     );
 
-    creator_info.sender = Addr::unchecked(TEST_CREATOR);
-    creator_env.block.height += DEFAULT_VOTING_PERIOD;
+    let env = mock_env_height(DEFAULT_VOTING_PERIOD + DEFAULT_TIMELOCK_PERIOD, 10000);
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::ExecutePoll { poll_id: 1 },
+    )
+    .unwrap();
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::ExecutePollMsgs { poll_id: 1 },
+    )
+    .unwrap();
+
+    let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+    // a failed execution does not free the slot twice
+    let reply_msg = Reply {
+        id: 1,
+        result: ContractResult::Err("Error".to_string()),
+    };
+    let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+    let creator = deps.api.addr_canonicalize(TEST_CREATOR).unwrap();
+    assert_eq!(
+        read_creator_polls(deps.as_ref().storage, &creator, 10).unwrap(),
+        vec![3, 4]
+    );
+}
+
+#[test]
+fn end_poll_unlocks_votes_in_batches() {
+    const NUM_VOTERS: u64 = 1000;
+    const POLL_ID: u64 = 1;
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+
+    // simulate voters by writing their stakes and votes directly
+    let voter_info = VoterInfo {
+        vote: VoteOption::Yes,
+        balance: Uint128::from(10u128),
+    };
+    for i in 0..NUM_VOTERS {
+        let voter = deps
+            .api
+            .addr_canonicalize(&format!("voter{:04}", i))
+            .unwrap();
+        bank_store(&mut deps.storage)
+            .save(
+                voter.as_slice(),
+                &TokenManager {
+                    share: Uint128::from(10u128),
+                    locked_balance: vec![(POLL_ID, voter_info.clone())],
+                    sponsored_balance: vec![],
+                },
+            )
+            .unwrap();
+        poll_voter_store(&mut deps.storage, POLL_ID)
+            .save(voter.as_slice(), &voter_info)
+            .unwrap();
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: POLL_ID },
+    )
+    .unwrap();
+    assert_eq!(res.attributes.last(), Some(&attr("unlock_pending", "true")));
+
+    let remaining_voters = |deps: Deps| {
+        poll_voter_read(deps.storage, POLL_ID)
+            .range(None, None, Order::Ascending)
+            .count()
+    };
+    assert_eq!(remaining_voters(deps.as_ref()), 900);
+
+    // anyone can drive the remaining batches
+    for batch in 1..10 {
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(TEST_VOTER_2, &[]),
+            ExecuteMsg::ContinueUnlock { poll_id: POLL_ID },
+        )
+        .unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "continue_unlock"),
+                attr("poll_id", POLL_ID.to_string()),
+                attr("unlock_pending", (batch < 9).to_string()),
+            ]
+        );
+        assert_eq!(remaining_voters(deps.as_ref()), 900 - batch * 100);
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_VOTER_2, &[]),
+        ExecuteMsg::ContinueUnlock { poll_id: POLL_ID },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::NothingToUnlock {});
+
+    for i in 0..NUM_VOTERS {
+        let voter = deps
+            .api
+            .addr_canonicalize(&format!("voter{:04}", i))
+            .unwrap();
+        let token_manager = bank_read(&deps.storage).load(voter.as_slice()).unwrap();
+        assert_eq!(token_manager.locked_balance, vec![]);
+    }
+}
+
+#[test]
+fn withdraw_after_vote_while_unlock_pending() {
+    const NUM_VOTERS: u64 = 150;
+    const POLL_ID: u64 = 1;
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+
+    // the last voter in key order stakes and votes for real, so it is left
+    // for the second unlock batch
+    let mut voters: Vec<String> = (0..NUM_VOTERS).map(|i| format!("voter{:04}", i)).collect();
+    voters.sort_by_key(|voter| deps.api.addr_canonicalize(voter).unwrap().to_vec());
+    let staker = voters.pop().unwrap();
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(10u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    mock_stake(deps.as_mut(), &staker, 10);
 
-    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
-    let execute_res = execute(
+    let msg = ExecuteMsg::CastVote {
+        poll_id: POLL_ID,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(10u128),
+    };
+    let _res = execute(
         deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
+        mock_env_height(0, 10000),
+        mock_info(&staker, &[]),
         msg,
     )
     .unwrap();
 
-    assert_eq!(
-        execute_res.attributes,
-        vec![
-            attr("action", "end_poll"),
-            attr("poll_id", "1"),
-            attr("rejected_reason", ""),
-            attr("passed", "true"),
-        ]
+    // locked stake can not be withdrawn while the poll is in progress
+    let msg = ExecuteMsg::WithdrawVotingTokens { amount: None };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&staker, &[]),
+        msg.clone(),
     );
+    assert_eq!(res.unwrap_err(), ContractError::InvalidWithdrawAmount {});
+
+    let voter_info = VoterInfo {
+        vote: VoteOption::No,
+        balance: Uint128::zero(),
+    };
+    for voter in voters {
+        let voter = deps.api.addr_canonicalize(&voter).unwrap();
+        poll_voter_store(&mut deps.storage, POLL_ID)
+            .save(voter.as_slice(), &voter_info)
+            .unwrap();
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: POLL_ID },
+    )
+    .unwrap();
+    // the voters written without a staker record are reported
     assert_eq!(
-        execute_res.messages,
-        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: VOTING_TOKEN.to_string(),
-            msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                recipient: TEST_CREATOR.to_string(),
-                amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
-            })
-            .unwrap(),
-            funds: vec![],
-        }))]
+        res.attributes[res.attributes.len() - 2..],
+        [
+            attr("unlock_pending", "true"),
+            attr("skipped_voters", "100")
+        ]
     );
 
-    // End poll will withdraw deposit balance
+    // the stake is free once voting ended, even before the voter is unlocked
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from(stake_amount as u128),
-        )],
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(10u128))],
     )]);
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&staker, &[]),
+        msg.clone(),
+    )
+    .unwrap();
 
-    creator_env.block.height += DEFAULT_TIMELOCK_PERIOD;
-    let msg = ExecuteMsg::ExecutePoll { poll_id: 1 };
-    let execute_res = execute(deps.as_mut(), creator_env.clone(), creator_info, msg).unwrap();
-    assert_eq!(
-        execute_res.messages,
-        vec![SubMsg::reply_on_error(
-            CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: creator_env.contract.address.to_string(),
-                msg: to_binary(&ExecuteMsg::ExecutePollMsgs { poll_id: 1 }).unwrap(),
-                funds: vec![],
-            }),
-            1
-        )]
-    );
+    // a record emptied by the withdrawal is not a division by zero
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::zero())],
+    )]);
+    let res = execute(deps.as_mut(), mock_env(), mock_info(&staker, &[]), msg);
+    assert_eq!(res.unwrap_err(), ContractError::NothingStaked {});
 
-    let msg = ExecuteMsg::ExecutePollMsgs { poll_id: 1 };
-    let contract_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
-    let execute_res = execute(deps.as_mut(), creator_env, contract_info, msg).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_VOTER_2, &[]),
+        ExecuteMsg::ContinueUnlock { poll_id: POLL_ID },
+    )
+    .unwrap();
     assert_eq!(
-        execute_res.messages,
+        res.attributes,
         vec![
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: VOTING_TOKEN.to_string(),
-                msg: exec_msg_bz,
-                funds: vec![],
-            })),
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: VOTING_TOKEN.to_string(),
-                msg: exec_msg_bz2,
-                funds: vec![],
-            })),
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: VOTING_TOKEN.to_string(),
-                msg: exec_msg_bz3,
-                funds: vec![],
-            })),
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: VOTING_TOKEN.to_string(),
-                msg: exec_msg_bz4,
-                funds: vec![],
-            })),
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: VOTING_TOKEN.to_string(),
-                msg: exec_msg_bz5,
-                funds: vec![],
-            })),
+            attr("action", "continue_unlock"),
+            attr("poll_id", POLL_ID.to_string()),
+            attr("unlock_pending", "false"),
+            attr("skipped_voters", (NUM_VOTERS - 101).to_string()),
         ]
     );
-    assert_eq!(
-        execute_res.attributes,
-        vec![attr("action", "execute_poll"), attr("poll_id", "1"),]
-    );
+
+    // the withdrawn voter still made it into the vote tree
+    let staker_raw = deps.api.addr_canonicalize(&staker).unwrap();
+    let leaf = vote_leaf_read(&deps.storage, POLL_ID)
+        .load(staker_raw.as_slice())
+        .unwrap();
+    assert_eq!(leaf.balance, Uint128::from(10u128));
+
+    let poll: Poll = poll_store(&mut deps.storage)
+        .load(&POLL_ID.to_be_bytes())
+        .unwrap();
+    assert_eq!(poll.vote_leaf_count, NUM_VOTERS);
+    assert!(!poll.unlock_pending);
+}
+
+fn sha256_pair(a: &[u8], b: &[u8]) -> Vec<u8> {
+    if a < b {
+        Sha256::digest(&[a, b].concat()).to_vec()
+    } else {
+        Sha256::digest(&[b, a].concat()).to_vec()
+    }
 }
 
 #[test]
-fn poll_with_empty_execute_data_marked_as_executed() {
-    const POLL_START_HEIGHT: u64 = 1000;
+fn query_poll_vote_proof() {
+    const NUM_VOTERS: u64 = 250;
     const POLL_ID: u64 = 1;
-    let stake_amount = 1000;
 
-    let mut deps = mock_dependencies(&coins(1000, VOTING_TOKEN));
+    let mut deps = mock_dependencies(&[]);
     mock_instantiate(deps.as_mut());
     mock_register_voting_token(deps.as_mut());
-    let mut creator_env = mock_env_height(POLL_START_HEIGHT, 10000);
-    let mut creator_info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
 
-    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, Some(vec![]));
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+
+    // simulate voters by writing their stakes and votes directly
+    let mut voters: Vec<(CanonicalAddr, String, VoterInfo)> = vec![];
+    for i in 0..NUM_VOTERS {
+        let voter = format!("voter{:04}", i);
+        let voter_raw = deps.api.addr_canonicalize(&voter).unwrap();
+        let voter_info = VoterInfo {
+            vote: if i % 3 == 0 {
+                VoteOption::No
+            } else {
+                VoteOption::Yes
+            },
+            balance: Uint128::from(10u128 + i as u128),
+        };
+        bank_store(&mut deps.storage)
+            .save(
+                voter_raw.as_slice(),
+                &TokenManager {
+                    share: voter_info.balance,
+                    locked_balance: vec![(POLL_ID, voter_info.clone())],
+                    sponsored_balance: vec![],
+                },
+            )
+            .unwrap();
+        poll_voter_store(&mut deps.storage, POLL_ID)
+            .save(voter_raw.as_slice(), &voter_info)
+            .unwrap();
+        voters.push((voter_raw, voter, voter_info));
+    }
 
-    let execute_res = execute(
+    // leaves are ordered by canonical address
+    voters.sort_by(|a, b| a.0.as_slice().cmp(b.0.as_slice()));
+    let leaves: Vec<Vec<u8>> = voters
+        .iter()
+        .map(|(_, voter, voter_info)| {
+            Sha256::digest(
+                format!("{}:{}:{}", voter, voter_info.vote, voter_info.balance).as_bytes(),
+            )
+            .to_vec()
+        })
+        .collect();
+    let mut level = leaves.clone();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => sha256_pair(a, b),
+                [a] => a.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    let expected_root = hex::encode(&level[0]);
+
+    let proof_query = |deps: Deps, voter: &str| {
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::PollVoteProof {
+                poll_id: POLL_ID,
+                voter: voter.to_string(),
+            },
+        )
+    };
+
+    let _res = execute(
         deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
-        msg,
+        mock_env_height(DEFAULT_VOTING_PERIOD, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: POLL_ID },
     )
     .unwrap();
 
-    assert_create_poll_result(
-        1,
-        creator_env.block.height + DEFAULT_VOTING_PERIOD,
-        TEST_CREATOR,
-        execute_res,
+    // the root is only known once every vote is unlocked
+    let res = proof_query(deps.as_ref(), &voters[0].1);
+    assert_eq!(res.unwrap_err(), ContractError::VoteRootNotAvailable {});
+
+    for _ in 0..2 {
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(TEST_VOTER_2, &[]),
+            ExecuteMsg::ContinueUnlock { poll_id: POLL_ID },
+        )
+        .unwrap();
+    }
+
+    let res = query(
         deps.as_ref(),
-    );
+        mock_env(),
+        QueryMsg::Poll { poll_id: POLL_ID },
+    )
+    .unwrap();
+    let poll: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll.vote_root, Some(expected_root.clone()));
+
+    for index in [0, NUM_VOTERS as usize / 2, NUM_VOTERS as usize - 1] {
+        let (_, voter, voter_info) = &voters[index];
+        let res = proof_query(deps.as_ref(), voter).unwrap();
+        let response: PollVoteProofResponse = from_binary(&res).unwrap();
+        assert_eq!(response.voter, voter.to_string());
+        assert_eq!(response.vote, voter_info.vote);
+        assert_eq!(response.balance, voter_info.balance);
+        assert_eq!(response.leaf, hex::encode(&leaves[index]));
+        assert_eq!(response.root, expected_root);
+
+        let root = response
+            .proof
+            .iter()
+            .fold(leaves[index].clone(), |hash, sibling| {
+                sha256_pair(&hash, &hex::decode(sibling).unwrap())
+            });
+        assert_eq!(hex::encode(root), expected_root);
+    }
 
-    deps.querier.with_token_balances(&[(
-        &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
-        )],
-    )]);
+    let res = proof_query(deps.as_ref(), TEST_CREATOR);
+    assert_eq!(res.unwrap_err(), ContractError::NotVoted {});
+}
 
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+#[test]
+fn archive_poll_in_chunks() {
+    const NUM_VOTERS: u64 = 150;
+    const POLL_ID: u64 = 1;
+    const ARCHIVE_AFTER: u64 = 100;
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let msg = update_config_msg(|update| {
+        update.archive_after = Some(ARCHIVE_AFTER);
     });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
 
     let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        stake_amount,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        stake_amount,
-        1,
-        execute_res,
-        deps.as_ref(),
-    );
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
 
-    let msg = ExecuteMsg::CastVote {
-        poll_id: 1,
-        vote: VoteOption::Yes,
-        amount: Uint128::from(stake_amount),
+    let archive = |limit: u32| ExecuteMsg::ArchivePoll {
+        poll_id: POLL_ID,
+        limit: Some(limit),
     };
-    let env = mock_env_height(POLL_START_HEIGHT, 10000);
-    let info = mock_info(TEST_VOTER, &[]);
-    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
 
-    assert_eq!(
-        execute_res.attributes,
-        vec![
-            attr("action", "cast_vote"),
-            attr("poll_id", POLL_ID.to_string().as_str()),
-            attr("amount", "1000"),
-            attr("voter", TEST_VOTER),
-            attr("vote_option", "yes"),
-        ]
+    // open polls cannot be archived
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD + ARCHIVE_AFTER, 10000),
+        mock_info(TEST_VOTER_2, &[]),
+        archive(20),
     );
+    assert_eq!(res.unwrap_err(), ContractError::PollNotArchivable {});
 
-    creator_info.sender = Addr::unchecked(TEST_CREATOR);
-    creator_env.block.height += DEFAULT_VOTING_PERIOD;
+    // simulate voters by writing their stakes and votes directly
+    let voter_info = VoterInfo {
+        vote: VoteOption::Yes,
+        balance: Uint128::from(10u128),
+    };
+    for i in 0..NUM_VOTERS {
+        let voter = deps
+            .api
+            .addr_canonicalize(&format!("voter{:04}", i))
+            .unwrap();
+        bank_store(&mut deps.storage)
+            .save(
+                voter.as_slice(),
+                &TokenManager {
+                    share: Uint128::from(10u128),
+                    locked_balance: vec![(POLL_ID, voter_info.clone())],
+                    sponsored_balance: vec![],
+                },
+            )
+            .unwrap();
+        poll_voter_store(&mut deps.storage, POLL_ID)
+            .save(voter.as_slice(), &voter_info)
+            .unwrap();
+    }
 
-    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
-    let execute_res = execute(
+    let _res = execute(
         deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
-        msg,
+        mock_env_height(DEFAULT_VOTING_PERIOD, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: POLL_ID },
     )
     .unwrap();
 
-    assert_eq!(
-        execute_res.attributes,
-        vec![
-            attr("action", "end_poll"),
-            attr("poll_id", "1"),
-            attr("rejected_reason", ""),
-            attr("passed", "true"),
-        ]
-    );
-    assert_eq!(
-        execute_res.messages,
-        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: VOTING_TOKEN.to_string(),
-            msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                recipient: TEST_CREATOR.to_string(),
-                amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
-            })
-            .unwrap(),
-            funds: vec![],
-        }))]
-    );
+    let remaining_voters = |deps: Deps| {
+        poll_voter_read(deps.storage, POLL_ID)
+            .range(None, None, Order::Ascending)
+            .count()
+    };
+    let vote_leaves = |deps: Deps| {
+        vote_leaf_read(deps.storage, POLL_ID)
+            .range(None, None, Order::Ascending)
+            .count()
+    };
+    assert_eq!(remaining_voters(deps.as_ref()), 50);
+    assert_eq!(vote_leaves(deps.as_ref()), 100);
 
-    // End poll will withdraw deposit balance
-    deps.querier.with_token_balances(&[(
-        &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from(stake_amount as u128),
-        )],
-    )]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD + ARCHIVE_AFTER - 1, 10000),
+        mock_info(TEST_VOTER_2, &[]),
+        archive(20),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollNotArchivable {});
+
+    for remaining in [30, 10, 0] {
+        let res = execute(
+            deps.as_mut(),
+            mock_env_height(DEFAULT_VOTING_PERIOD + ARCHIVE_AFTER, 10000),
+            mock_info(TEST_VOTER_2, &[]),
+            archive(20),
+        )
+        .unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "archive_poll"),
+                attr("poll_id", POLL_ID.to_string()),
+                attr("archive_pending", "true"),
+            ]
+        );
+        assert_eq!(remaining_voters(deps.as_ref()), remaining);
+    }
 
-    creator_env.block.height += DEFAULT_TIMELOCK_PERIOD;
-    let msg = ExecuteMsg::ExecutePoll { poll_id: 1 };
-    let execute_res = execute(deps.as_mut(), creator_env.clone(), creator_info, msg).unwrap();
-    assert_eq!(
-        execute_res.messages,
-        vec![SubMsg::reply_on_error(
-            CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: creator_env.contract.address.to_string(),
-                msg: to_binary(&ExecuteMsg::ExecutePollMsgs { poll_id: 1 }).unwrap(),
-                funds: vec![],
-            }),
-            1
-        )]
+    // voters unlocked by ArchivePoll are not committed to the vote tree,
+    // the last unlocking call already removed 20 of the 100 leaves
+    assert_eq!(vote_leaves(deps.as_ref()), 80);
+
+    // 80 vote leaves and 177 vote tree nodes are left, 20 of each go per call
+    for call in 1..=9 {
+        let res = execute(
+            deps.as_mut(),
+            mock_env_height(DEFAULT_VOTING_PERIOD + ARCHIVE_AFTER, 10000),
+            mock_info(TEST_VOTER_2, &[]),
+            archive(20),
+        )
+        .unwrap();
+        assert_eq!(
+            res.attributes.last(),
+            Some(&attr("archive_pending", (call < 9).to_string()))
+        );
+    }
+
+    for i in 0..NUM_VOTERS {
+        let voter = deps
+            .api
+            .addr_canonicalize(&format!("voter{:04}", i))
+            .unwrap();
+        let token_manager = bank_read(&deps.storage).load(voter.as_slice()).unwrap();
+        assert_eq!(token_manager.locked_balance, vec![]);
+    }
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Poll { poll_id: POLL_ID },
     );
+    assert_eq!(res.unwrap_err(), ContractError::PollArchived {});
 
-    // Executes since empty polls are allowed
-    let msg = ExecuteMsg::ExecutePollMsgs { poll_id: 1 };
-    let contract_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
-    let execute_res = execute(deps.as_mut(), creator_env, contract_info, msg).unwrap();
-    assert_eq!(execute_res.messages, vec![]);
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ArchivedPoll { poll_id: POLL_ID },
+    )
+    .unwrap();
+    let archived_poll: ArchivedPollResponse = from_binary(&res).unwrap();
     assert_eq!(
-        execute_res.attributes,
-        vec![attr("action", "execute_poll"), attr("poll_id", "1")]
+        archived_poll,
+        ArchivedPollResponse {
+            id: POLL_ID,
+            status: PollStatus::Rejected,
+            end_height: DEFAULT_VOTING_PERIOD,
+            yes_votes: Uint128::zero(),
+            no_votes: Uint128::zero(),
+            execute_hash: None,
+        }
     );
 
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
-    let poll_res: PollResponse = from_binary(&res).unwrap();
-    assert_eq!(poll_res.status, PollStatus::Executed);
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Voters {
+            poll_id: POLL_ID,
+            start_after: None,
+            limit: None,
+            order_by: None,
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollArchived {});
 
     let res = query(
         deps.as_ref(),
         mock_env(),
         QueryMsg::Polls {
-            filter: Some(PollStatus::Executed),
+            filter: Some(PollStatus::Rejected),
             start_after: None,
             limit: None,
-            order_by: Some(OrderBy::Desc),
+            order_by: None,
         },
     )
     .unwrap();
-    let polls_res: PollsResponse = from_binary(&res).unwrap();
-    assert_eq!(polls_res.polls[0], poll_res);
+    let response: PollsResponse = from_binary(&res).unwrap();
+    assert_eq!(response.polls, vec![]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD + ARCHIVE_AFTER, 10000),
+        mock_info(TEST_VOTER_2, &[]),
+        archive(20),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollArchived {});
 }
 
 #[test]
-fn poll_with_none_execute_data_marked_as_executed() {
-    const POLL_START_HEIGHT: u64 = 1000;
-    const POLL_ID: u64 = 1;
-    let stake_amount = 1000;
+fn end_poll_by_poll_clock() {
+    const END_TIME: u64 = 10000 + DEFAULT_VOTING_PERIOD * 6;
 
-    let mut deps = mock_dependencies(&coins(1000, VOTING_TOKEN));
+    let mut deps = mock_dependencies(&[]);
     mock_instantiate(deps.as_mut());
     mock_register_voting_token(deps.as_mut());
-    let mut creator_env = mock_env_height(POLL_START_HEIGHT, 10000);
-    let mut creator_info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
+    mock_set_poll_clock(deps.as_mut(), PollClock::Time);
 
+    let info = mock_info(VOTING_TOKEN, &[]);
     let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info.clone(), msg).unwrap();
 
-    let execute_res = execute(
+    // open polls keep the clock they were created with
+    mock_set_poll_clock(deps.as_mut(), PollClock::Height);
+    let msg = create_poll_msg("test2".to_string(), "test2".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let response: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(response.end_height, DEFAULT_VOTING_PERIOD);
+    assert_eq!(response.end_time, END_TIME);
+    assert_eq!(response.clock, PollClock::Time);
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(10u128 + 2 * DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    mock_stake(deps.as_mut(), TEST_VOTER, 10);
+
+    // blocks were slower than estimated, voting is still open by time
+    let _res = execute(
         deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
-        msg,
+        mock_env_height(DEFAULT_VOTING_PERIOD + 100, END_TIME - 1),
+        mock_info(TEST_VOTER, &[]),
+        ExecuteMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            amount: Uint128::from(10u128),
+        },
     )
     .unwrap();
 
-    assert_create_poll_result(
-        1,
-        creator_env.block.height + DEFAULT_VOTING_PERIOD,
-        TEST_CREATOR,
-        execute_res,
-        deps.as_ref(),
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD + 100, END_TIME - 1),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 1 },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollVotingPeriod {});
+
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD + 100, END_TIME),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 1 },
+    )
+    .unwrap();
+
+    // the second poll ends by height whatever the time
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD - 1, END_TIME + 1000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 2 },
     );
+    assert_eq!(res.unwrap_err(), ContractError::PollVotingPeriod {});
+
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 2 },
+    )
+    .unwrap();
+}
+
+#[test]
+fn timelock_and_archive_by_poll_clock() {
+    const END_TIME: u64 = 10000 + DEFAULT_VOTING_PERIOD * 6;
+    const TIMELOCK_END: u64 = END_TIME + DEFAULT_TIMELOCK_PERIOD * 6;
+    const ARCHIVE_AFTER: u64 = 100;
+    const POLL_ID: u64 = 1;
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+    mock_set_poll_clock(deps.as_mut(), PollClock::Time);
+
+    let msg = update_config_msg(|update| {
+        update.archive_after = Some(ARCHIVE_AFTER);
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
 
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
         &[(
             &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+            &Uint128::from(10u128 + DEFAULT_PROPOSAL_DEPOSIT),
         )],
     )]);
+    mock_stake(deps.as_mut(), TEST_VOTER, 10);
 
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(0, 10000),
+        mock_info(TEST_VOTER, &[]),
+        ExecuteMsg::CastVote {
+            poll_id: POLL_ID,
+            vote: VoteOption::Yes,
+            amount: Uint128::from(10u128),
+        },
+    )
+    .unwrap();
+
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD, END_TIME),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: POLL_ID },
+    )
+    .unwrap();
+
+    // the timelock is converted to seconds, the height alone does not release it
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(
+            DEFAULT_VOTING_PERIOD + DEFAULT_TIMELOCK_PERIOD,
+            TIMELOCK_END - 1,
+        ),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::ExecutePoll { poll_id: POLL_ID },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::TimelockNotExpired {});
+
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD, TIMELOCK_END),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::ExecutePoll { poll_id: POLL_ID },
+    )
+    .unwrap();
+
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD, TIMELOCK_END),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::ExecutePollMsgs { poll_id: POLL_ID },
+    )
+    .unwrap();
+
+    // so is the archive delay
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(
+            DEFAULT_VOTING_PERIOD + ARCHIVE_AFTER,
+            END_TIME + ARCHIVE_AFTER * 6 - 1,
+        ),
+        mock_info(TEST_VOTER_2, &[]),
+        ExecuteMsg::ArchivePoll {
+            poll_id: POLL_ID,
+            limit: None,
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollNotArchivable {});
+
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD, END_TIME + ARCHIVE_AFTER * 6),
+        mock_info(TEST_VOTER_2, &[]),
+        ExecuteMsg::ArchivePoll {
+            poll_id: POLL_ID,
+            limit: None,
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn late_quorum_extends_voting_once() {
+    const WINDOW: u64 = 1000u64;
+    const EXTENSION: u64 = 500u64;
+
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let msg = update_config_msg(|update| {
+        update.late_quorum_window = Some(WINDOW);
+        update.late_quorum_extension = Some(EXTENSION);
     });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
 
     let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        stake_amount,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        stake_amount,
-        1,
-        execute_res,
-        deps.as_ref(),
-    );
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info.clone(), msg).unwrap();
+    let msg = create_poll_msg("test2".to_string(), "test2".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+
+    // 20 + 20 + 60 staked, quorum is 30
+    for (staker, amount, total_staked) in [
+        (TEST_VOTER, 20u128, 20u128),
+        (TEST_VOTER_2, 20, 40),
+        (TEST_VOTER_3, 60, 100),
+    ] {
+        deps.querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &Uint128::from(total_staked + 2 * DEFAULT_PROPOSAL_DEPOSIT),
+            )],
+        )]);
+        mock_stake(deps.as_mut(), staker, amount);
+    }
 
-    let msg = ExecuteMsg::CastVote {
-        poll_id: 1,
-        vote: VoteOption::Yes,
-        amount: Uint128::from(stake_amount),
+    let cast_vote = |deps: DepsMut, voter: &str, poll_id: u64, amount: u128, height: u64| {
+        execute(
+            deps,
+            mock_env_height(height, 10000),
+            mock_info(voter, &[]),
+            ExecuteMsg::CastVote {
+                poll_id,
+                vote: VoteOption::Yes,
+                amount: Uint128::from(amount),
+            },
+        )
+        .unwrap()
     };
-    let env = mock_env_height(POLL_START_HEIGHT, 10000);
-    let info = mock_info(TEST_VOTER, &[]);
-    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+    let query_poll = |deps: Deps, poll_id: u64| -> PollResponse {
+        let res = query(deps, mock_env(), QueryMsg::Poll { poll_id }).unwrap();
+        from_binary(&res).unwrap()
+    };
+
+    // quorum reached early does not extend voting
+    cast_vote(deps.as_mut(), TEST_VOTER_3, 2, 60, 100);
+    let poll = query_poll(deps.as_ref(), 2);
+    assert_eq!(poll.end_height, DEFAULT_VOTING_PERIOD);
+    assert!(!poll.extended);
+
+    // a vote within the window that does not reach quorum does not extend voting
+    cast_vote(
+        deps.as_mut(),
+        TEST_VOTER,
+        1,
+        20,
+        DEFAULT_VOTING_PERIOD - WINDOW + 1,
+    );
+    assert!(!query_poll(deps.as_ref(), 1).extended);
 
+    // reaching quorum within the window extends voting
+    let res = cast_vote(
+        deps.as_mut(),
+        TEST_VOTER_2,
+        1,
+        20,
+        DEFAULT_VOTING_PERIOD - 10,
+    );
+    assert!(res.attributes.contains(&attr(
+        "end_height",
+        (DEFAULT_VOTING_PERIOD + EXTENSION).to_string()
+    )));
+    let poll = query_poll(deps.as_ref(), 1);
+    assert_eq!(poll.end_height, DEFAULT_VOTING_PERIOD + EXTENSION);
     assert_eq!(
-        execute_res.attributes,
-        vec![
-            attr("action", "cast_vote"),
-            attr("poll_id", POLL_ID.to_string().as_str()),
-            attr("amount", "1000"),
-            attr("voter", TEST_VOTER),
-            attr("vote_option", "yes"),
-        ]
+        poll.end_time,
+        10000 + DEFAULT_VOTING_PERIOD * 6 + EXTENSION * 6
     );
+    assert!(poll.extended);
 
-    creator_info.sender = Addr::unchecked(TEST_CREATOR);
-    creator_env.block.height += DEFAULT_VOTING_PERIOD;
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 1 },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollVotingPeriod {});
 
-    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
-    let execute_res = execute(
+    // voting is extended only once
+    cast_vote(
         deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
-        msg,
+        TEST_VOTER_3,
+        1,
+        60,
+        DEFAULT_VOTING_PERIOD + EXTENSION - 10,
+    );
+    let poll = query_poll(deps.as_ref(), 1);
+    assert_eq!(poll.end_height, DEFAULT_VOTING_PERIOD + EXTENSION);
+
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD + EXTENSION, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 1 },
     )
     .unwrap();
+}
+
+fn query_progress(deps: Deps, poll_id: u64) -> PollProgressResponse {
+    let res = query(deps, mock_env(), QueryMsg::PollProgress { poll_id }).unwrap();
+    from_binary(&res).unwrap()
+}
+
+#[test]
+fn query_poll_progress() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info.clone(), msg).unwrap();
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(10u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    mock_stake(deps.as_mut(), TEST_VOTER, 10);
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(100u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    mock_stake(deps.as_mut(), TEST_VOTER_2, 90);
 
     assert_eq!(
-        execute_res.attributes,
-        vec![
-            attr("action", "end_poll"),
-            attr("poll_id", "1"),
-            attr("rejected_reason", ""),
-            attr("passed", "true"),
-        ]
-    );
-    assert_eq!(
-        execute_res.messages,
-        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: VOTING_TOKEN.to_string(),
-            msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                recipient: TEST_CREATOR.to_string(),
-                amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
-            })
-            .unwrap(),
-            funds: vec![],
-        }))]
+        query_progress(deps.as_ref(), 1),
+        PollProgressResponse {
+            quorum_required: Decimal::percent(DEFAULT_QUORUM),
+            quorum_current: Decimal::zero(),
+            threshold_required: Decimal::percent(DEFAULT_THRESHOLD),
+            yes: Uint128::zero(),
+            no: Uint128::zero(),
+            projected_status: PollStatus::Rejected,
+        }
     );
 
-    // End poll will withdraw deposit balance
+    let vote = |deps: DepsMut, voter: &str, poll_id: u64, vote: VoteOption, amount: u128| {
+        let msg = ExecuteMsg::CastVote {
+            poll_id,
+            vote,
+            amount: Uint128::from(amount),
+        };
+        let _res = execute(deps, mock_env_height(0, 10000), mock_info(voter, &[]), msg).unwrap();
+    };
+
+    // quorum not reached
+    vote(deps.as_mut(), TEST_VOTER, 1, VoteOption::Yes, 10);
+    let progress = query_progress(deps.as_ref(), 1);
+    assert_eq!(progress.quorum_current, Decimal::percent(10));
+    assert_eq!(progress.projected_status, PollStatus::Rejected);
+
+    // threshold not reached
+    vote(deps.as_mut(), TEST_VOTER_2, 1, VoteOption::No, 90);
+    let progress = query_progress(deps.as_ref(), 1);
+    assert_eq!(progress.quorum_current, Decimal::one());
+    assert_eq!(progress.yes, Uint128::from(10u128));
+    assert_eq!(progress.no, Uint128::from(90u128));
+    assert_eq!(progress.projected_status, PollStatus::Rejected);
+
+    // passing poll
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(100u128 + 2 * DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    vote(deps.as_mut(), TEST_VOTER_2, 2, VoteOption::Yes, 90);
+    let progress = query_progress(deps.as_ref(), 2);
+    assert_eq!(progress.quorum_current, Decimal::percent(90));
+    assert_eq!(progress.projected_status, PollStatus::Passed);
+
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD - 5, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::SnapshotPoll { poll_id: 2 },
+    )
+    .unwrap();
+
+    // the staked supply grows; only the snapshot poll keeps its quorum
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
         &[(
             &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from(stake_amount as u128),
+            &Uint128::from(1000u128 + 2 * DEFAULT_PROPOSAL_DEPOSIT),
         )],
     )]);
-
-    creator_env.block.height += DEFAULT_TIMELOCK_PERIOD;
-    let msg = ExecuteMsg::ExecutePoll { poll_id: 1 };
-    let execute_res = execute(deps.as_mut(), creator_env.clone(), creator_info, msg).unwrap();
-    assert_eq!(
-        execute_res.messages,
-        vec![SubMsg::reply_on_error(
-            CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: creator_env.contract.address.to_string(),
-                msg: to_binary(&ExecuteMsg::ExecutePollMsgs { poll_id: 1 }).unwrap(),
-                funds: vec![],
-            }),
-            1
-        )]
-    );
-
-    // Executes since empty polls are allowed
-    let msg = ExecuteMsg::ExecutePollMsgs { poll_id: 1 };
-    let contract_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
-    let execute_res = execute(deps.as_mut(), creator_env, contract_info, msg).unwrap();
-    assert_eq!(execute_res.messages, vec![]);
-    assert_eq!(
-        execute_res.attributes,
-        vec![attr("action", "execute_poll"), attr("poll_id", "1")]
-    );
-
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
-    let poll_res: PollResponse = from_binary(&res).unwrap();
-    assert_eq!(poll_res.status, PollStatus::Executed);
+    let progress = query_progress(deps.as_ref(), 1);
+    assert_eq!(progress.quorum_current, Decimal::percent(10));
+    assert_eq!(progress.projected_status, PollStatus::Rejected);
+    let progress = query_progress(deps.as_ref(), 2);
+    assert_eq!(progress.quorum_current, Decimal::percent(90));
+    assert_eq!(progress.projected_status, PollStatus::Passed);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 2 },
+    )
+    .unwrap();
+    assert_eq!(res.attributes[3], attr("passed", "true"));
 
     let res = query(
         deps.as_ref(),
         mock_env(),
-        QueryMsg::Polls {
-            filter: Some(PollStatus::Executed),
-            start_after: None,
-            limit: None,
-            order_by: Some(OrderBy::Desc),
-        },
-    )
-    .unwrap();
-    let polls_res: PollsResponse = from_binary(&res).unwrap();
-    assert_eq!(polls_res.polls[0], poll_res);
+        QueryMsg::PollProgress { poll_id: 2 },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollNotInProgress {});
 }
 
 #[test]
-fn snapshot_poll() {
-    let stake_amount = 1000;
-
-    let mut deps = mock_dependencies(&coins(100, VOTING_TOKEN));
+fn query_poll_weight_distribution() {
+    let mut deps = mock_dependencies(&[]);
     mock_instantiate(deps.as_mut());
     mock_register_voting_token(deps.as_mut());
 
     let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
-    let mut creator_env = mock_env();
-    let creator_info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(
-        deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
-        msg,
-    )
-    .unwrap();
-    assert_eq!(
-        execute_res.attributes,
-        vec![
-            attr("action", "create_poll"),
-            attr("creator", TEST_CREATOR),
-            attr("poll_id", "1"),
-            attr("end_height", "32345"),
-        ]
-    );
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info.clone(), msg).unwrap();
+
+    // one voter below 1k ANC, one right at 1k, one in 10k-100k and two above 100k
+    let amounts = [
+        500_000_000u128,
+        1_000_000_000,
+        50_000_000_000,
+        100_000_000_000,
+        200_000_000_000,
+    ];
+    let mut total_staked = 0u128;
+    for (i, amount) in amounts.iter().enumerate() {
+        let voter = format!("voter{:04}", i);
+        total_staked += amount;
+        deps.querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &Uint128::from(total_staked + DEFAULT_PROPOSAL_DEPOSIT),
+            )],
+        )]);
+        mock_stake(deps.as_mut(), &voter, *amount);
+
+        let msg = ExecuteMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            amount: Uint128::from(*amount),
+        };
+        let _res = execute(
+            deps.as_mut(),
+            mock_env_height(0, 10000),
+            mock_info(&voter, &[]),
+            msg,
+        )
+        .unwrap();
+    }
 
-    //must not be executed
-    let snapshot_err = execute(
-        deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
-        ExecuteMsg::SnapshotPoll { poll_id: 1 },
+    let expected = PollWeightDistributionResponse {
+        buckets: vec![
+            WeightBucketResponse {
+                min: Uint128::zero(),
+                max: Some(Uint128::from(1_000_000_000u128)),
+                voters: 1,
+            },
+            WeightBucketResponse {
+                min: Uint128::from(1_000_000_000u128),
+                max: Some(Uint128::from(10_000_000_000u128)),
+                voters: 1,
+            },
+            WeightBucketResponse {
+                min: Uint128::from(10_000_000_000u128),
+                max: Some(Uint128::from(100_000_000_000u128)),
+                voters: 1,
+            },
+            WeightBucketResponse {
+                min: Uint128::from(100_000_000_000u128),
+                max: None,
+                voters: 2,
+            },
+        ],
+    };
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PollWeightDistribution { poll_id: 1 },
     )
-    .unwrap_err();
-    assert_eq!(ContractError::SnapshotHeight {}, snapshot_err);
+    .unwrap();
+    let distribution: PollWeightDistributionResponse = from_binary(&res).unwrap();
+    assert_eq!(distribution, expected);
 
-    // change time
-    creator_env.block.height = 32345 - 10;
+    // new boundaries only apply to polls created afterwards
+    let msg = update_config_msg(|update| {
+        update.vote_weight_buckets = Some(vec![Uint128::from(100u128)]);
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
 
-    deps.querier.with_token_balances(&[(
-        &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
-        )],
-    )]);
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
 
-    let fix_res = execute(
-        deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
-        ExecuteMsg::SnapshotPoll { poll_id: 1 },
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PollWeightDistribution { poll_id: 1 },
     )
     .unwrap();
+    let distribution: PollWeightDistributionResponse = from_binary(&res).unwrap();
+    assert_eq!(distribution, expected);
 
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PollWeightDistribution { poll_id: 2 },
+    )
+    .unwrap();
+    let distribution: PollWeightDistributionResponse = from_binary(&res).unwrap();
     assert_eq!(
-        fix_res.attributes,
+        distribution.buckets,
         vec![
-            attr("action", "snapshot_poll"),
-            attr("poll_id", "1"),
-            attr("staked_amount", stake_amount.to_string().as_str()),
+            WeightBucketResponse {
+                min: Uint128::zero(),
+                max: Some(Uint128::from(100u128)),
+                voters: 0,
+            },
+            WeightBucketResponse {
+                min: Uint128::from(100u128),
+                max: None,
+                voters: 0,
+            },
         ]
     );
-
-    //must not be executed
-    let snapshot_error = execute(
-        deps.as_mut(),
-        creator_env,
-        creator_info,
-        ExecuteMsg::SnapshotPoll { poll_id: 1 },
-    )
-    .unwrap_err();
-    assert_eq!(ContractError::SnapshotAlreadyOccurred {}, snapshot_error);
 }
 
 #[test]
-fn happy_days_cast_vote_with_snapshot() {
+fn query_poll_tally_digest() {
     let mut deps = mock_dependencies(&[]);
     mock_instantiate(deps.as_mut());
     mock_register_voting_token(deps.as_mut());
 
-    let env = mock_env_height(0, 10000);
     let info = mock_info(VOTING_TOKEN, &[]);
-    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    for title in ["first", "second"] {
+        let msg = create_poll_msg(title.to_string(), title.to_string(), None, None);
+        let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info.clone(), msg).unwrap();
+    }
 
-    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
-    assert_create_poll_result(
-        1,
-        DEFAULT_VOTING_PERIOD,
-        TEST_CREATOR,
-        execute_res,
-        deps.as_ref(),
+    let digest = |deps: Deps| -> PollTallyDigestResponse {
+        let res = query(
+            deps,
+            mock_env(),
+            QueryMsg::PollTallyDigest {
+                poll_ids: vec![1, 2],
+            },
+        )
+        .unwrap();
+        from_binary(&res).unwrap()
+    };
+    assert_eq!(
+        digest(deps.as_ref()).digests,
+        vec![
+            PollTallyDigestResponseItem {
+                poll_id: 1,
+                yes: Uint128::zero(),
+                no: Uint128::zero(),
+                status: PollStatus::InProgress,
+                last_vote_height: 0,
+            },
+            PollTallyDigestResponseItem {
+                poll_id: 2,
+                yes: Uint128::zero(),
+                no: Uint128::zero(),
+                status: PollStatus::InProgress,
+                last_vote_height: 0,
+            },
+        ]
     );
 
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
         &[(
             &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from(11u128 + DEFAULT_PROPOSAL_DEPOSIT),
+            &Uint128::from(30u128 + 2 * DEFAULT_PROPOSAL_DEPOSIT),
         )],
     )]);
+    mock_stake(deps.as_mut(), TEST_VOTER, 10);
+    mock_stake(deps.as_mut(), TEST_VOTER_2, 20);
 
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(11u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
-    });
-
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        11,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        11,
-        1,
-        execute_res,
-        deps.as_ref(),
-    );
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(10u128),
+    };
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(100, 10000),
+        mock_info(TEST_VOTER, &[]),
+        msg,
+    )
+    .unwrap();
 
-    //cast_vote without snapshot
-    let env = mock_env_height(0, 10000);
-    let info = mock_info(TEST_VOTER, &coins(11, VOTING_TOKEN));
-    let amount = 10u128;
+    let digests = digest(deps.as_ref()).digests;
+    assert_eq!(digests[0].yes, Uint128::from(10u128));
+    assert_eq!(digests[0].last_vote_height, 100);
+    assert_eq!(digests[1].last_vote_height, 0);
 
     let msg = ExecuteMsg::CastVote {
         poll_id: 1,
-        vote: VoteOption::Yes,
-        amount: Uint128::from(amount),
+        vote: VoteOption::No,
+        amount: Uint128::from(20u128),
     };
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(200, 10000),
+        mock_info(TEST_VOTER_2, &[]),
+        msg,
+    )
+    .unwrap();
 
-    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
-    assert_cast_vote_success(TEST_VOTER, amount, 1, VoteOption::Yes, execute_res);
+    let digests = digest(deps.as_ref()).digests;
+    assert_eq!(digests[0].no, Uint128::from(20u128));
+    assert_eq!(digests[0].last_vote_height, 200);
+    // polls nobody voted on keep their digest
+    assert_eq!(
+        digests[1],
+        PollTallyDigestResponseItem {
+            poll_id: 2,
+            yes: Uint128::zero(),
+            no: Uint128::zero(),
+            status: PollStatus::InProgress,
+            last_vote_height: 0,
+        }
+    );
 
-    // balance be double
-    deps.querier.with_token_balances(&[(
-        &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from(22u128 + DEFAULT_PROPOSAL_DEPOSIT),
-        )],
-    )]);
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PollTallyDigest {
+            poll_ids: vec![1, 3],
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollNotFound {});
 
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
-    let value: PollResponse = from_binary(&res).unwrap();
-    assert_eq!(value.staked_amount, None);
-    let end_height = value.end_height;
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PollTallyDigest {
+            poll_ids: vec![1; 21],
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::TooManyPolls(20));
+}
 
-    //cast another vote
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER_2.to_string(),
-        amount: Uint128::from(11u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
-    });
+#[test]
+fn end_poll_with_weight_modes() {
+    const WHALE: &str = TEST_VOTER;
+    const SMALL_VOTERS: [&str; 4] = [TEST_VOTER_2, TEST_VOTER_3, "voter4", "voter5"];
 
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let _execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
 
-    // another voter cast a vote
-    let msg = ExecuteMsg::CastVote {
-        poll_id: 1,
-        vote: VoteOption::Yes,
-        amount: Uint128::from(10u128),
-    };
-    let env = mock_env_height(end_height - 9, 10000);
-    let info = mock_info(TEST_VOTER_2, &[]);
-    let execute_res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-    assert_cast_vote_success(TEST_VOTER_2, amount, 1, VoteOption::Yes, execute_res);
+    // the same votes are cast on one poll per weight mode
+    let weight_modes = [
+        WeightMode::Linear,
+        WeightMode::Capped {
+            max_weight: Uint128::from(50u128),
+        },
+        WeightMode::SquareRoot,
+    ];
+    for weight_mode in weight_modes.iter() {
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: TEST_CREATOR.to_string(),
+            amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+            msg: to_binary(&Cw20HookMsg::CreatePoll {
+                title: "test".to_string(),
+                description: "test".to_string(),
+                link: None,
+                execute_msgs: None,
+                spend: None,
+                weight_mode: Some(weight_mode.clone()),
+            })
+            .unwrap(),
+        });
+        let _res = execute(
+            deps.as_mut(),
+            mock_env_height(0, 10000),
+            mock_info(VOTING_TOKEN, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+
+    let deposits = 3 * DEFAULT_PROPOSAL_DEPOSIT;
+    let mut stakes = vec![(WHALE, VoteOption::Yes, 900u128)];
+    for voter in SMALL_VOTERS.iter() {
+        stakes.push((voter, VoteOption::No, 25u128));
+    }
 
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
-    let value: PollResponse = from_binary(&res).unwrap();
-    assert_eq!(value.staked_amount, Some(Uint128::new(22)));
+    let mut total_staked = 0u128;
+    for (voter, _, amount) in stakes.iter() {
+        total_staked += amount;
+        deps.querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &Uint128::from(total_staked + deposits),
+            )],
+        )]);
+        mock_stake(deps.as_mut(), voter, *amount);
+    }
 
-    // snanpshot poll will not go through
-    let snap_error = execute(
-        deps.as_mut(),
-        env,
-        info,
-        ExecuteMsg::SnapshotPoll { poll_id: 1 },
-    )
-    .unwrap_err();
-    assert_eq!(ContractError::SnapshotAlreadyOccurred {}, snap_error);
+    for poll_id in 1..4u64 {
+        for (voter, vote, amount) in stakes.iter() {
+            let msg = ExecuteMsg::CastVote {
+                poll_id,
+                vote: vote.clone(),
+                amount: Uint128::from(*amount),
+            };
+            let _res = execute(
+                deps.as_mut(),
+                mock_env_height(0, 10000),
+                mock_info(voter, &[]),
+                msg,
+            )
+            .unwrap();
+        }
+    }
 
-    // balance be double
-    deps.querier.with_token_balances(&[(
-        &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from(33u128 + DEFAULT_PROPOSAL_DEPOSIT),
-        )],
-    )]);
+    // whale dominance is dampened by the capped and square root modes
+    let poll: Poll = poll_store(&mut deps.storage)
+        .load(&3u64.to_be_bytes())
+        .unwrap();
+    assert_eq!(poll.yes_votes, Uint128::from(900u128));
+    assert_eq!(poll.no_votes, Uint128::from(100u128));
+    assert_eq!(poll.weighted_yes_votes, Uint128::from(30u128));
+    assert_eq!(poll.weighted_no_votes, Uint128::from(20u128));
 
-    // another voter cast a vote but the snapshot is already occurred
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER_3.to_string(),
-        amount: Uint128::from(11u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
-    });
+    let poll: Poll = poll_store(&mut deps.storage)
+        .load(&2u64.to_be_bytes())
+        .unwrap();
+    assert_eq!(poll.weighted_yes_votes, Uint128::from(50u128));
+    assert_eq!(poll.weighted_no_votes, Uint128::from(100u128));
 
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let _execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    let msg = ExecuteMsg::CastVote {
-        poll_id: 1,
-        vote: VoteOption::Yes,
-        amount: Uint128::from(10u128),
-    };
-    let env = mock_env_height(end_height - 8, 10000);
-    let info = mock_info(TEST_VOTER_3, &[]);
-    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
-    assert_cast_vote_success(TEST_VOTER_3, amount, 1, VoteOption::Yes, execute_res);
+    let expected = vec![
+        (1u64, "true", ""),
+        (2u64, "false", "Threshold not reached"),
+        (3u64, "true", ""),
+    ];
+    for (remaining_polls, (poll_id, passed, rejected_reason)) in (0..3u128).rev().zip(expected) {
+        let res = execute(
+            deps.as_mut(),
+            mock_env_height(DEFAULT_VOTING_PERIOD, 10000),
+            mock_info(TEST_CREATOR, &[]),
+            ExecuteMsg::EndPoll { poll_id },
+        )
+        .unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "end_poll"),
+                attr("poll_id", poll_id.to_string()),
+                attr("rejected_reason", rejected_reason),
+                attr("passed", passed),
+            ]
+        );
 
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+        // the refunded deposit leaves the contract
+        deps.querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &Uint128::from(total_staked + remaining_polls * DEFAULT_PROPOSAL_DEPOSIT),
+            )],
+        )]);
+    }
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 2 }).unwrap();
     let value: PollResponse = from_binary(&res).unwrap();
-    assert_eq!(value.staked_amount, Some(Uint128::new(22)));
+    assert_eq!(
+        value.weight_mode,
+        WeightMode::Capped {
+            max_weight: Uint128::from(50u128)
+        }
+    );
 }
 
 #[test]
-fn fails_end_poll_quorum_inflation_without_snapshot_poll() {
-    const POLL_START_HEIGHT: u64 = 1000;
-    const POLL_ID: u64 = 1;
-    let stake_amount = 1000;
-
-    let mut deps = mock_dependencies(&coins(1000, VOTING_TOKEN));
+fn fails_create_poll_weight_mode_with_messages() {
+    let mut deps = mock_dependencies(&[]);
     mock_instantiate(deps.as_mut());
     mock_register_voting_token(deps.as_mut());
 
-    let mut creator_env = mock_env_height(POLL_START_HEIGHT, 10000);
-    let mut creator_info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
-
-    let exec_msg_bz = to_binary(&Cw20ExecuteMsg::Burn {
-        amount: Uint128::new(123),
-    })
-    .unwrap();
+    let create_poll = |deps: DepsMut,
+                       execute_msgs: Option<Vec<PollExecuteMsg>>,
+                       spend: Option<PollSpendMsg>,
+                       weight_mode: WeightMode| {
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: TEST_CREATOR.to_string(),
+            amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+            msg: to_binary(&Cw20HookMsg::CreatePoll {
+                title: "test".to_string(),
+                description: "test".to_string(),
+                link: None,
+                execute_msgs,
+                spend,
+                weight_mode: Some(weight_mode),
+            })
+            .unwrap(),
+        });
+        execute(
+            deps,
+            mock_env_height(0, 10000),
+            mock_info(VOTING_TOKEN, &[]),
+            msg,
+        )
+    };
+    let execute_msgs = vec![PollExecuteMsg {
+        order: 1u64,
+        contract: VOTING_TOKEN.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Burn {
+            amount: Uint128::new(123),
+        })
+        .unwrap(),
+    }];
+    let spend = PollSpendMsg {
+        recipient: TEST_VOTER.to_string(),
+        amount: Uint128::from(100u128),
+    };
+    let capped = WeightMode::Capped {
+        max_weight: Uint128::from(50u128),
+    };
 
-    //add two messages
-    let execute_msgs: Vec<PollExecuteMsg> = vec![
-        PollExecuteMsg {
-            order: 1u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz.clone(),
-        },
-        PollExecuteMsg {
-            order: 2u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz,
-        },
-    ];
+    for weight_mode in [capped.clone(), WeightMode::SquareRoot] {
+        let res = create_poll(
+            deps.as_mut(),
+            Some(execute_msgs.clone()),
+            None,
+            weight_mode.clone(),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::WeightModeNotAllowed {});
 
-    let msg = create_poll_msg(
-        "test".to_string(),
-        "test".to_string(),
-        None,
-        Some(execute_msgs),
-    );
+        let res = create_poll(deps.as_mut(), None, Some(spend.clone()), weight_mode);
+        assert_eq!(res.unwrap_err(), ContractError::WeightModeNotAllowed {});
+    }
 
-    let execute_res = execute(
-        deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
-        msg,
-    )
-    .unwrap();
+    // text polls and linear polls with messages are accepted
+    create_poll(deps.as_mut(), None, None, capped).unwrap();
+    create_poll(deps.as_mut(), Some(execute_msgs), None, WeightMode::Linear).unwrap();
+}
 
-    assert_create_poll_result(
-        1,
-        creator_env.block.height + DEFAULT_VOTING_PERIOD,
-        TEST_CREATOR,
-        execute_res,
-        deps.as_ref(),
-    );
+#[test]
+fn migrate_legacy_config() {
+    let mut deps = mock_dependencies(&[]);
+    let legacy_config = LegacyConfig {
+        owner: deps.api.addr_canonicalize(TEST_CREATOR).unwrap(),
+        anchor_token: deps.api.addr_canonicalize(VOTING_TOKEN).unwrap(),
+        quorum: Decimal::percent(DEFAULT_QUORUM),
+        threshold: Decimal::percent(DEFAULT_THRESHOLD),
+        voting_period: DEFAULT_VOTING_PERIOD,
+        timelock_period: DEFAULT_TIMELOCK_PERIOD,
+        expiration_period: 0u64,
+        proposal_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+        snapshot_period: DEFAULT_FIX_PERIOD,
+    };
+    Singleton::new(deps.as_mut().storage, KEY_CONFIG)
+        .save(&legacy_config)
+        .unwrap();
+    let contract_addr = deps.api.addr_canonicalize(MOCK_CONTRACT_ADDR).unwrap();
+    Singleton::new(deps.as_mut().storage, KEY_STATE)
+        .save(&LegacyState {
+            contract_addr,
+            poll_count: 53,
+            total_share: Uint128::from(1000u128),
+            total_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+        })
+        .unwrap();
+    poll_indexer_store(deps.as_mut().storage, &PollStatus::InProgress)
+        .save(&53u64.to_be_bytes(), &true)
+        .unwrap();
+    let creator = deps.api.addr_canonicalize(TEST_CREATOR).unwrap();
+    poll_store(deps.as_mut().storage)
+        .save(
+            &53u64.to_be_bytes(),
+            &Poll {
+                id: 53u64,
+                creator: creator.clone(),
+                status: PollStatus::InProgress,
+                yes_votes: Uint128::zero(),
+                no_votes: Uint128::zero(),
+                end_height: DEFAULT_VOTING_PERIOD,
+                title: "title".to_string(),
+                description: "description".to_string(),
+                deposit_amount: Uint128::zero(),
+                link: None,
+                execute_data: None,
+                total_balance_at_end_poll: None,
+                staked_amount: None,
+                sponsor_weight: Uint128::zero(),
+                unlock_pending: false,
+                amended: false,
+                weight_mode: WeightMode::Linear,
+                weighted_yes_votes: Uint128::zero(),
+                weighted_no_votes: Uint128::zero(),
+                end_time: 0,
+                clock: PollClock::Height,
+                vote_leaf_count: 0,
+                vote_root: None,
+                extended: false,
+                participation_reward: Uint128::zero(),
+                participation_distributed: Uint128::zero(),
+                weight_buckets: vec![],
+                weight_bucket_voters: vec![],
+                last_vote_height: 0,
+                quorum_failed_at: None,
+                resubmitted_from: None,
+                resubmitted_as: None,
+                resubmit_depth: 0,
+                spend: None,
+            },
+        )
+        .unwrap();
+    for poll_id in 1..53u64 {
+        poll_indexer_store(deps.as_mut().storage, &PollStatus::Passed)
+            .save(&poll_id.to_be_bytes(), &true)
+            .unwrap();
+    }
 
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
         &[(
             &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+            &Uint128::from(1200u128 + DEFAULT_PROPOSAL_DEPOSIT),
         )],
     )]);
 
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
-    });
+    let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
 
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        stake_amount,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        stake_amount,
-        1,
-        execute_res,
-        deps.as_ref(),
+    // polls in progress count against the open poll limit of their creator
+    assert_eq!(
+        read_creator_polls(&deps.storage, &creator, 10).unwrap(),
+        vec![53u64]
     );
 
-    let msg = ExecuteMsg::CastVote {
-        poll_id: 1,
-        vote: VoteOption::Yes,
-        amount: Uint128::from(stake_amount),
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(TEST_CREATOR, config.owner.as_str());
+    assert_eq!(VOTING_TOKEN, config.anchor_token.as_str());
+    assert_eq!(DEFAULT_PROPOSAL_DEPOSIT, config.proposal_deposit.u128());
+    assert_eq!(Uint128::zero(), config.sponsorship_threshold);
+    assert_eq!(0u64, config.sponsorship_period);
+    assert_eq!(4096u64, config.max_execute_msg_size);
+    assert_eq!(16u64, config.max_execute_msgs);
+    assert_eq!(Uint128::zero(), config.min_stake_amount);
+    assert_eq!(Vec::<String>::new(), config.stake_depositors);
+    assert_eq!(Uint128::zero(), config.max_spend_per_poll);
+    assert_eq!(5_256_000u64, config.archive_after);
+    assert_eq!(PollClock::Height, config.poll_clock);
+    assert_eq!(6u64, config.block_time);
+    assert_eq!(0u64, config.late_quorum_window);
+    assert_eq!(0u64, config.late_quorum_extension);
+    assert_eq!(Decimal::zero(), config.participation_reward_ratio);
+    assert_eq!(Vec::<String>::new(), config.execution_denylist);
+    assert_eq!(604_800u64, config.epoch_length);
+    assert_eq!(0u32, config.max_open_polls_per_creator);
+    assert_eq!(Uint128::zero(), config.min_creator_stake);
+    assert_eq!(3, config.vote_weight_buckets.len());
+    assert_eq!(0u64, config.resubmit_window);
+    assert_eq!(DEFAULT_PROPOSAL_DEPOSIT, config.resubmit_deposit.u128());
+    assert_eq!(1u32, config.max_resubmit_depth);
+    assert_eq!(None, config.deposit_cap);
+    assert!(config.atomic_batch);
+    assert_eq!(Decimal::percent(50), config.expired_deposit_refund_ratio);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::ProtocolAddresses {}).unwrap();
+    let addresses: ProtocolAddressesResponse = from_binary(&res).unwrap();
+    assert_eq!(VOTING_TOKEN, addresses.anchor_token.as_str());
+    assert_eq!(None, addresses.community_fund);
+    assert_eq!(None, addresses.voting_escrow);
+
+    // poll counters are backfilled from the poll indexer
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(53u64, state.poll_count);
+    assert_eq!(1u64, state.polls_in_progress);
+    assert_eq!(
+        (1..51u64).collect::<Vec<u64>>(),
+        state.polls_awaiting_execution
+    );
+    assert!(state.truncated);
+
+    // the staked principal starts from the staked balance
+    let state: State = state_read(deps.as_ref().storage).load().unwrap();
+    assert_eq!(state.total_principal, Uint128::from(1200u128));
+}
+
+#[test]
+fn fails_migrate_invalid_legacy_config() {
+    let mut deps = mock_dependencies(&[]);
+    let legacy_config = LegacyConfig {
+        owner: deps.api.addr_canonicalize(TEST_CREATOR).unwrap(),
+        anchor_token: deps.api.addr_canonicalize(VOTING_TOKEN).unwrap(),
+        quorum: Decimal::percent(DEFAULT_QUORUM),
+        threshold: Decimal::percent(DEFAULT_THRESHOLD),
+        voting_period: DEFAULT_VOTING_PERIOD,
+        timelock_period: DEFAULT_TIMELOCK_PERIOD,
+        expiration_period: DEFAULT_TIMELOCK_PERIOD - 1,
+        proposal_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+        snapshot_period: DEFAULT_FIX_PERIOD,
     };
-    let env = mock_env_height(POLL_START_HEIGHT, 10000);
-    let info = mock_info(TEST_VOTER, &[]);
-    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+    Singleton::new(deps.as_mut().storage, KEY_CONFIG)
+        .save(&legacy_config)
+        .unwrap();
 
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {});
     assert_eq!(
-        execute_res.attributes,
-        vec![
-            attr("action", "cast_vote"),
-            attr("poll_id", POLL_ID.to_string().as_str()),
-            attr("amount", "1000"),
-            attr("voter", TEST_VOTER),
-            attr("vote_option", "yes"),
-        ]
+        res.unwrap_err(),
+        ContractError::InvalidConfig(
+            "timelock_period".to_string(),
+            "at most expiration_period".to_string()
+        )
     );
 
-    creator_env.block.height += DEFAULT_VOTING_PERIOD - 10;
+    // a percentage given where a ratio is expected
+    let legacy_config = LegacyConfig {
+        expiration_period: 0u64,
+        quorum: Decimal::percent(3000),
+        ..legacy_config
+    };
+    Singleton::new(deps.as_mut().storage, KEY_CONFIG)
+        .save(&legacy_config)
+        .unwrap();
 
-    // did not SnapshotPoll
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {});
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::InvalidConfig(
+            "quorum".to_string(),
+            "greater than 0 and at most 1".to_string()
+        )
+    );
+}
 
-    // staked amount get increased 10 times
-    deps.querier.with_token_balances(&[(
-        &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from(((10 * stake_amount) + DEFAULT_PROPOSAL_DEPOSIT) as u128),
-        )],
-    )]);
+#[test]
+fn resubmit_poll() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
 
-    //cast another vote
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER_2.to_string(),
-        amount: Uint128::from(8 * stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+    let msg = update_config_msg(|update| {
+        update.resubmit_window = Some(100);
+        update.resubmit_deposit = Some(Uint128::from(DEFAULT_PROPOSAL_DEPOSIT / 2));
     });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
 
+    let execute_msgs = vec![PollExecuteMsg {
+        order: 1u64,
+        contract: VOTING_TOKEN.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Burn {
+            amount: Uint128::new(123),
+        })
+        .unwrap(),
+    }];
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(execute_msgs.clone()),
+    );
     let info = mock_info(VOTING_TOKEN, &[]);
-    let _execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info.clone(), msg).unwrap();
 
-    // another voter cast a vote
-    let msg = ExecuteMsg::CastVote {
-        poll_id: 1,
-        vote: VoteOption::Yes,
-        amount: Uint128::from(stake_amount),
+    let resubmit_msg = |sender: &str, amount: u128, poll_id: u64| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: sender.to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::ResubmitPoll { poll_id }).unwrap(),
+        })
     };
-    let env = mock_env_height(creator_env.block.height, 10000);
-    let info = mock_info(TEST_VOTER_2, &[]);
-    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
+    let resubmit_deposit = DEFAULT_PROPOSAL_DEPOSIT / 2;
+
+    // only polls which failed quorum can be resubmitted
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(10, 10000),
+        info.clone(),
+        resubmit_msg(TEST_CREATOR, resubmit_deposit, 1),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollNotResubmittable {});
+
+    let end_height = DEFAULT_VOTING_PERIOD;
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(end_height, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 1 },
+    )
+    .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(end_height + 1, 10000),
+        info.clone(),
+        resubmit_msg(TEST_VOTER, resubmit_deposit, 1),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
 
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(end_height + 1, 10000),
+        info.clone(),
+        resubmit_msg(TEST_CREATOR, resubmit_deposit - 1, 1),
+    );
     assert_eq!(
-        execute_res.attributes,
+        res.unwrap_err(),
+        ContractError::InsufficientProposalDeposit(resubmit_deposit)
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(end_height + 99, 10000),
+        info.clone(),
+        resubmit_msg(TEST_CREATOR, resubmit_deposit, 1),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
         vec![
-            attr("action", "cast_vote"),
-            attr("poll_id", POLL_ID.to_string().as_str()),
-            attr("amount", "1000"),
-            attr("voter", TEST_VOTER_2),
-            attr("vote_option", "yes"),
+            attr("action", "resubmit_poll"),
+            attr("creator", TEST_CREATOR),
+            attr("poll_id", "2"),
+            attr("resubmitted_from", "1"),
+            attr(
+                "end_height",
+                (end_height + 99 + DEFAULT_VOTING_PERIOD).to_string()
+            ),
         ]
     );
 
-    creator_info.sender = Addr::unchecked(TEST_CREATOR);
-    creator_env.block.height += 10;
+    let query_poll = |deps: Deps, poll_id: u64| -> PollResponse {
+        let res = query(deps, mock_env(), QueryMsg::Poll { poll_id }).unwrap();
+        from_binary(&res).unwrap()
+    };
+    let poll = query_poll(deps.as_ref(), 2);
+    assert_eq!(poll.status, PollStatus::InProgress);
+    assert_eq!(poll.title, "test");
+    assert_eq!(poll.execute_data, Some(execute_msgs));
+    assert_eq!(poll.deposit_amount, Uint128::from(resubmit_deposit));
+    assert_eq!(poll.resubmitted_from, Some(1));
+    assert_eq!(query_poll(deps.as_ref(), 1).resubmitted_as, Some(2));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(end_height + 99, 10000),
+        info.clone(),
+        resubmit_msg(TEST_CREATOR, resubmit_deposit, 1),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::PollAlreadyResubmitted(2));
 
-    // quorum must reach
-    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
-    let execute_res = execute(deps.as_mut(), creator_env, creator_info, msg).unwrap();
+    // the resubmitted poll fails quorum again
+    let end_height = end_height + 99 + DEFAULT_VOTING_PERIOD;
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(end_height, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 2 },
+    )
+    .unwrap();
 
-    assert_eq!(
-        execute_res.attributes,
-        vec![
-            attr("action", "end_poll"),
-            attr("poll_id", "1"),
-            attr("rejected_reason", "Quorum not reached"),
-            attr("passed", "false"),
-        ]
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(end_height + 100, 10000),
+        info.clone(),
+        resubmit_msg(TEST_CREATOR, resubmit_deposit, 2),
     );
+    assert_eq!(res.unwrap_err(), ContractError::ResubmitWindowExpired {});
 
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
-    let value: PollResponse = from_binary(&res).unwrap();
-    assert_eq!(
-        10 * stake_amount,
-        value.total_balance_at_end_poll.unwrap().u128()
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(end_height + 1, 10000),
+        info,
+        resubmit_msg(TEST_CREATOR, resubmit_deposit, 2),
     );
+    assert_eq!(res.unwrap_err(), ContractError::ResubmitDepthExceeded(1));
 }
 
 #[test]
-fn happy_days_end_poll_with_controlled_quorum() {
-    const POLL_START_HEIGHT: u64 = 1000;
-    const POLL_ID: u64 = 1;
-    let stake_amount = 1000;
-
-    let mut deps = mock_dependencies(&coins(1000, VOTING_TOKEN));
+fn resubmit_spend_poll() {
+    let mut deps = mock_dependencies(&[]);
     mock_instantiate(deps.as_mut());
     mock_register_voting_token(deps.as_mut());
 
-    let mut creator_env = mock_env_height(POLL_START_HEIGHT, 10000);
-    let mut creator_info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
+    let set_community_fund = |deps: DepsMut, community_fund: &str| {
+        let msg = ExecuteMsg::UpdateProtocolAddresses(ProtocolAddressesMsg {
+            community_fund: Some(Some(community_fund.to_string())),
+            ..ProtocolAddressesMsg::default()
+        });
+        execute(deps, mock_env(), mock_info(MOCK_CONTRACT_ADDR, &[]), msg).unwrap();
+    };
+    let set_max_spend = |deps: DepsMut, amount: u128| {
+        let msg = update_config_msg(|update| {
+            update.max_spend_per_poll = Some(Uint128::from(amount));
+            update.resubmit_window = Some(100);
+        });
+        execute(deps, mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+    };
+    set_community_fund(deps.as_mut(), "community");
+    set_max_spend(deps.as_mut(), 1000);
 
-    let exec_msg_bz = to_binary(&Cw20ExecuteMsg::Burn {
-        amount: Uint128::new(123),
-    })
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let msg = create_spend_poll_msg(None, TEST_VOTER, 1000);
+    let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info.clone(), msg).unwrap();
+
+    let _res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD, 10000),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 1 },
+    )
     .unwrap();
 
-    //add two messages
-    let execute_msgs: Vec<PollExecuteMsg> = vec![
-        PollExecuteMsg {
-            order: 1u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz.clone(),
-        },
-        PollExecuteMsg {
-            order: 2u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz,
-        },
-    ];
+    let resubmit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_CREATOR.to_string(),
+        amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+        msg: to_binary(&Cw20HookMsg::ResubmitPoll { poll_id: 1 }).unwrap(),
+    });
 
-    let msg = create_poll_msg(
-        "test".to_string(),
-        "test".to_string(),
-        None,
-        Some(execute_msgs),
+    // the spend is checked against the cap at the time of the resubmission
+    set_max_spend(deps.as_mut(), 500);
+    let res = execute(
+        deps.as_mut(),
+        mock_env_height(DEFAULT_VOTING_PERIOD + 1, 10000),
+        info.clone(),
+        resubmit_msg.clone(),
     );
+    assert_eq!(res.unwrap_err(), ContractError::SpendLimitExceeded(500));
 
-    let execute_res = execute(
+    // and sent from the current community fund
+    set_max_spend(deps.as_mut(), 1000);
+    set_community_fund(deps.as_mut(), "community2");
+    let _res = execute(
         deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
-        msg,
+        mock_env_height(DEFAULT_VOTING_PERIOD + 1, 10000),
+        info,
+        resubmit_msg,
     )
     .unwrap();
 
-    assert_create_poll_result(
-        1,
-        creator_env.block.height + DEFAULT_VOTING_PERIOD,
-        TEST_CREATOR,
-        execute_res,
-        deps.as_ref(),
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 2 }).unwrap();
+    let poll: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        poll.execute_data,
+        Some(vec![PollExecuteMsg {
+            order: 1u64,
+            contract: "community2".to_string(),
+            msg: to_binary(&CommunityExecuteMsg::Spend {
+                recipient: TEST_VOTER.to_string(),
+                amount: Uint128::from(1000u128),
+            })
+            .unwrap(),
+        }])
     );
+}
+
+#[test]
+fn cast_votes() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    for title in ["first", "second", "third"] {
+        let msg = create_poll_msg(title.to_string(), title.to_string(), None, None);
+        let _res = execute(deps.as_mut(), mock_env_height(0, 10000), info.clone(), msg).unwrap();
+    }
 
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
         &[(
             &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+            &Uint128::from(100u128 + 3 * DEFAULT_PROPOSAL_DEPOSIT),
         )],
     )]);
+    mock_stake(deps.as_mut(), TEST_VOTER, 100);
 
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
-    });
-
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        stake_amount,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        stake_amount,
-        1,
-        execute_res,
-        deps.as_ref(),
-    );
-
-    let msg = ExecuteMsg::CastVote {
-        poll_id: 1,
-        vote: VoteOption::Yes,
-        amount: Uint128::from(stake_amount),
+    let vote = |poll_id: u64, vote: VoteOption, amount: u128| PollVoteMsg {
+        poll_id,
+        vote,
+        amount: Uint128::from(amount),
+    };
+    let cast_votes = |deps: DepsMut, votes: Vec<PollVoteMsg>| {
+        execute(
+            deps,
+            mock_env_height(10, 10000),
+            mock_info(TEST_VOTER, &[]),
+            ExecuteMsg::CastVotes { votes },
+        )
     };
-    let env = mock_env_height(POLL_START_HEIGHT, 10000);
-    let info = mock_info(TEST_VOTER, &[]);
-    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
 
     assert_eq!(
-        execute_res.attributes,
-        vec![
-            attr("action", "cast_vote"),
-            attr("poll_id", POLL_ID.to_string().as_str()),
-            attr("amount", "1000"),
-            attr("voter", TEST_VOTER),
-            attr("vote_option", "yes"),
-        ]
+        cast_votes(deps.as_mut(), vec![]).unwrap_err(),
+        ContractError::DataShouldBeGiven {}
+    );
+    assert_eq!(
+        cast_votes(deps.as_mut(), vec![vote(1, VoteOption::Yes, 1); 11]).unwrap_err(),
+        ContractError::TooManyVotes(10)
     );
 
-    creator_env.block.height += DEFAULT_VOTING_PERIOD - 10;
-
-    // send SnapshotPoll
-    let fix_res = execute(
+    // the same stake backs the votes on both polls
+    let res = cast_votes(
         deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
-        ExecuteMsg::SnapshotPoll { poll_id: 1 },
+        vec![vote(1, VoteOption::Yes, 100), vote(2, VoteOption::No, 100)],
     )
     .unwrap();
-
     assert_eq!(
-        fix_res.attributes,
+        res.attributes,
         vec![
-            attr("action", "snapshot_poll"),
+            attr("action", "cast_votes"),
+            attr("voter", TEST_VOTER),
             attr("poll_id", "1"),
-            attr("staked_amount", stake_amount.to_string().as_str()),
+            attr("result", "yes"),
+            attr("poll_id", "2"),
+            attr("result", "no"),
         ]
     );
 
-    // staked amount get increased 10 times
-    deps.querier.with_token_balances(&[(
-        &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from(((10 * stake_amount) + DEFAULT_PROPOSAL_DEPOSIT) as u128),
-        )],
-    )]);
-
-    //cast another vote
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER_2.to_string(),
-        amount: Uint128::from(8 * stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
-    });
-
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let _execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let msg = ExecuteMsg::CastVote {
-        poll_id: 1,
-        vote: VoteOption::Yes,
-        amount: Uint128::from(8 * stake_amount),
-    };
-    let env = mock_env_height(creator_env.block.height, 10000);
-    let info = mock_info(TEST_VOTER_2, &[]);
-    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
-
+    // an atomic batch fails on the first failing vote
     assert_eq!(
-        execute_res.attributes,
-        vec![
-            attr("action", "cast_vote"),
-            attr("poll_id", POLL_ID.to_string().as_str()),
-            attr("amount", "8000"),
-            attr("voter", TEST_VOTER_2),
-            attr("vote_option", "yes"),
-        ]
+        cast_votes(
+            deps.as_mut(),
+            vec![vote(4, VoteOption::Yes, 10), vote(3, VoteOption::Yes, 10)],
+        )
+        .unwrap_err(),
+        ContractError::PollNotFound {}
     );
 
-    creator_info.sender = Addr::unchecked(TEST_CREATOR);
-    creator_env.block.height += 10;
-
-    // quorum must reach
-    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
-    let execute_res = execute(deps.as_mut(), creator_env, creator_info, msg).unwrap();
+    let msg = update_config_msg(|update| {
+        update.atomic_batch = Some(false);
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
 
+    let res = cast_votes(
+        deps.as_mut(),
+        vec![
+            vote(1, VoteOption::Yes, 10),
+            vote(3, VoteOption::Yes, 100),
+            vote(4, VoteOption::No, 10),
+            vote(3, VoteOption::No, 10),
+        ],
+    )
+    .unwrap();
     assert_eq!(
-        execute_res.attributes,
+        res.attributes,
         vec![
-            attr("action", "end_poll"),
+            attr("action", "cast_votes"),
+            attr("voter", TEST_VOTER),
             attr("poll_id", "1"),
-            attr("rejected_reason", ""),
-            attr("passed", "true"),
+            attr("result", "failed: User has already voted"),
+            attr("poll_id", "3"),
+            attr("result", "yes"),
+            attr("poll_id", "4"),
+            attr("result", "failed: Poll does not exist"),
+            attr("poll_id", "3"),
+            attr("result", "failed: User has already voted"),
         ]
     );
-    assert_eq!(
-        execute_res.messages,
-        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: VOTING_TOKEN.to_string(),
-            msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                recipient: TEST_CREATOR.to_string(),
-                amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
-            })
-            .unwrap(),
-            funds: vec![],
-        }))]
-    );
 
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 1 }).unwrap();
-    let value: PollResponse = from_binary(&res).unwrap();
-    assert_eq!(
-        stake_amount,
-        value.total_balance_at_end_poll.unwrap().u128()
-    );
+    let poll: Poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+    assert_eq!(poll.yes_votes, Uint128::from(100u128));
+    let poll: Poll = poll_read(&deps.storage).load(&3u64.to_be_bytes()).unwrap();
+    assert_eq!(poll.yes_votes, Uint128::from(100u128));
+    assert_eq!(poll.no_votes, Uint128::zero());
 
-    assert_eq!(value.yes_votes.u128(), 9 * stake_amount);
+    // skipped votes leave no trace in the epoch statistics
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::EpochStats {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let epoch = from_binary::<EpochStatsResponse>(&res)
+        .unwrap()
+        .epochs
+        .pop()
+        .unwrap();
+    assert_eq!(epoch.yes_votes, Uint128::from(200u128));
+    assert_eq!(epoch.no_votes, Uint128::from(100u128));
 
-    // actual staked amount is 10 times bigger than staked amount
-    let actual_staked_weight = query_token_balance(
-        &deps.as_ref().querier,
-        Addr::unchecked(VOTING_TOKEN),
-        Addr::unchecked(MOCK_CONTRACT_ADDR),
+    // the stake is locked by the largest vote, not the sum of them
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::WithdrawStatus {
+            address: TEST_VOTER.to_string(),
+        },
     )
-    .unwrap()
-    .checked_sub(Uint128::from(DEFAULT_PROPOSAL_DEPOSIT))
     .unwrap();
+    let status: WithdrawStatusResponse = from_binary(&res).unwrap();
+    assert_eq!(status.locks.len(), 3);
+    assert_eq!(status.locked_amount, Uint128::from(100u128));
 
-    assert_eq!(actual_staked_weight.u128(), (10 * stake_amount))
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_VOTER, &[]),
+        ExecuteMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(1u128)),
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::InvalidWithdrawAmount {});
 }
@@ -1,5 +1,5 @@
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 use cosmwasm_std::Order;
 
@@ -19,3 +19,14 @@ impl From<OrderBy> for Order {
         }
     }
 }
+
+/// deserialize_some tells a field set to null apart from a missing one,
+/// for optional fields where null clears the stored value.
+/// Use with `#[serde(default, deserialize_with = "deserialize_some")]`.
+pub fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Deserialize::deserialize(deserializer).map(Some)
+}
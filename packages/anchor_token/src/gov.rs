@@ -4,7 +4,7 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-use crate::common::OrderBy;
+use crate::common::{deserialize_some, OrderBy};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
@@ -18,6 +18,7 @@ pub struct InstantiateMsg {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     Receive(Cw20ReceiveMsg),
     ExecutePollMsgs {
@@ -26,20 +27,18 @@ pub enum ExecuteMsg {
     RegisterContracts {
         anchor_token: String,
     },
-    UpdateConfig {
-        owner: Option<String>,
-        quorum: Option<Decimal>,
-        threshold: Option<Decimal>,
-        voting_period: Option<u64>,
-        timelock_period: Option<u64>,
-        proposal_deposit: Option<Uint128>,
-        snapshot_period: Option<u64>,
-    },
+    /// UpdateConfig changes the given fields of the config, fields left out are kept
+    UpdateConfig(ConfigUpdate),
     CastVote {
         poll_id: u64,
         vote: VoteOption,
         amount: Uint128,
     },
+    /// CastVotes casts up to 10 votes in order, see atomic_batch in the config
+    /// for how a failing vote is handled
+    CastVotes {
+        votes: Vec<PollVoteMsg>,
+    },
     WithdrawVotingTokens {
         amount: Option<Uint128>,
     },
@@ -52,21 +51,123 @@ pub enum ExecuteMsg {
     SnapshotPoll {
         poll_id: u64,
     },
+    /// SponsorPoll backs a pending poll with the sender's staked weight
+    SponsorPoll {
+        poll_id: u64,
+    },
+    /// ContinueUnlock releases the next batch of votes locked by an ended poll
+    ContinueUnlock {
+        poll_id: u64,
+    },
+    /// RegisterRewardToken allows a cw20 token to distribute rewards to stakers
+    RegisterRewardToken {
+        token: String,
+    },
+    /// ClaimRewards sends the pending rewards of one or all reward tokens
+    ClaimRewards {
+        token: Option<String>,
+    },
+    /// ArchivePoll clears up to `limit` voter and sponsor entries of a finished poll
+    /// and replaces the poll with a compact summary once none are left
+    ArchivePoll {
+        poll_id: u64,
+        limit: Option<u32>,
+    },
+    /// UpdateExecutionDenylist replaces the contracts polls may not execute messages on.
+    /// Only a passed poll can update it.
+    UpdateExecutionDenylist {
+        denylist: Vec<String>,
+    },
+    /// UpdateProtocolAddresses replaces the given addresses of the protocol registry.
+    /// Only a passed poll can update it.
+    UpdateProtocolAddresses(ProtocolAddressesMsg),
+    /// AmendPoll lets the creator fix the description and link before any votes are cast
+    AmendPoll {
+        poll_id: u64,
+        description: String,
+        link: Option<String>,
+    },
+}
+
+/// Config fields to change on UpdateConfig
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct ConfigUpdate {
+    pub owner: Option<String>,
+    pub quorum: Option<Decimal>,
+    pub threshold: Option<Decimal>,
+    pub voting_period: Option<u64>,
+    pub timelock_period: Option<u64>,
+    pub proposal_deposit: Option<Uint128>,
+    pub snapshot_period: Option<u64>,
+    pub sponsorship_threshold: Option<Uint128>,
+    pub sponsorship_period: Option<u64>,
+    pub max_execute_msg_size: Option<u64>,
+    pub max_execute_msgs: Option<u64>,
+    pub min_stake_amount: Option<Uint128>,
+    pub stake_depositors: Option<Vec<String>>,
+    pub max_spend_per_poll: Option<Uint128>,
+    pub archive_after: Option<u64>,
+    pub poll_clock: Option<PollClock>,
+    pub block_time: Option<u64>,
+    pub late_quorum_window: Option<u64>,
+    pub late_quorum_extension: Option<u64>,
+    pub participation_reward_ratio: Option<Decimal>,
+    pub epoch_length: Option<u64>,
+    pub max_open_polls_per_creator: Option<u32>,
+    pub min_creator_stake: Option<Uint128>,
+    pub vote_weight_buckets: Option<Vec<Uint128>>,
+    pub resubmit_window: Option<u64>,
+    pub resubmit_deposit: Option<Uint128>,
+    pub max_resubmit_depth: Option<u32>,
+    /// null removes the cap
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub deposit_cap: Option<Option<Uint128>>,
+    pub atomic_batch: Option<bool>,
+    pub expired_deposit_refund_ratio: Option<Decimal>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Cw20HookMsg {
     /// StakeVotingTokens a user can stake their mirror token to receive rewards
-    /// or do vote on polls
-    StakeVotingTokens {},
+    /// or do vote on polls. Allowed depositors can stake on behalf of another staker
+    StakeVotingTokens { staker: Option<String> },
     /// CreatePoll need to receive deposit from a proposer
     CreatePoll {
         title: String,
         description: String,
         link: Option<String>,
         execute_msgs: Option<Vec<PollExecuteMsg>>,
+        spend: Option<PollSpendMsg>,
+        weight_mode: Option<WeightMode>,
     },
+    /// DistributeRewards splits the received reward tokens between stakers by share
+    DistributeRewards {},
+    /// ResubmitPoll creates a copy of a poll of the sender which was rejected
+    /// for missing quorum, within the resubmit window
+    ResubmitPoll { poll_id: u64 },
+}
+
+/// PollSpendMsg sends `amount` of ANC from the community fund to `recipient`
+/// after all other execute messages of the poll
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PollSpendMsg {
+    pub recipient: String,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PollVoteMsg {
+    pub poll_id: u64,
+    pub vote: VoteOption,
+    pub amount: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -82,12 +183,22 @@ pub struct PollExecuteMsg {
 pub enum QueryMsg {
     Config {},
     State {},
+    ProtocolAddresses {},
     Staker {
         address: String,
     },
+    /// WithdrawStatus explains how much of a stake can be withdrawn now and until when
+    /// the rest is locked by votes
+    WithdrawStatus {
+        address: String,
+    },
     Poll {
         poll_id: u64,
     },
+    /// ArchivedPoll returns the summary kept for a poll after ArchivePoll
+    ArchivedPoll {
+        poll_id: u64,
+    },
     Polls {
         filter: Option<PollStatus>,
         start_after: Option<u64>,
@@ -100,6 +211,53 @@ pub enum QueryMsg {
         limit: Option<u32>,
         order_by: Option<OrderBy>,
     },
+    StakingMetrics {},
+    Sponsors {
+        poll_id: u64,
+        start_after: Option<String>,
+        limit: Option<u32>,
+        order_by: Option<OrderBy>,
+    },
+    PollExecuteMsgs {
+        poll_id: u64,
+    },
+    /// SimulatePollExecution checks the poll execute messages without executing them
+    SimulatePollExecution {
+        poll_id: u64,
+    },
+    PollProgress {
+        poll_id: u64,
+    },
+    /// PollWeightDistribution counts the voters of a poll by vote amount
+    PollWeightDistribution {
+        poll_id: u64,
+    },
+    /// PollTallyDigest returns the tally of up to 20 polls, to tell which changed since
+    PollTallyDigest {
+        poll_ids: Vec<u64>,
+    },
+    StakerRewards {
+        address: String,
+    },
+    /// EpochStats returns the statistics of past and current epochs, oldest first
+    EpochStats {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// ConfigHistory returns the retained configuration changes, oldest first
+    ConfigHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// ShareRateHistory returns the latest daily share rate checkpoints, newest first
+    ShareRateHistory {
+        limit: Option<u32>,
+    },
+    /// PollVoteProof returns the merkle proof of a vote on an ended poll
+    PollVoteProof {
+        poll_id: u64,
+        voter: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
@@ -112,6 +270,69 @@ pub struct ConfigResponse {
     pub timelock_period: u64,
     pub proposal_deposit: Uint128,
     pub snapshot_period: u64,
+    pub sponsorship_threshold: Uint128,
+    pub sponsorship_period: u64,
+    pub max_execute_msg_size: u64,
+    pub max_execute_msgs: u64,
+    pub min_stake_amount: Uint128,
+    pub stake_depositors: Vec<String>,
+    pub max_spend_per_poll: Uint128,
+    pub archive_after: u64,
+    pub poll_clock: PollClock,
+    pub block_time: u64,
+    pub late_quorum_window: u64,
+    pub late_quorum_extension: u64,
+    pub participation_reward_ratio: Decimal,
+    pub execution_denylist: Vec<String>,
+    pub epoch_length: u64,
+    pub max_open_polls_per_creator: u32,
+    pub min_creator_stake: Uint128,
+    pub vote_weight_buckets: Vec<Uint128>,
+    pub resubmit_window: u64,
+    pub resubmit_deposit: Uint128,
+    pub max_resubmit_depth: u32,
+    pub deposit_cap: Option<Uint128>,
+    pub atomic_batch: bool,
+    pub expired_deposit_refund_ratio: Decimal,
+}
+
+/// Addresses of the other protocol contracts gov knows about.
+/// On update, addresses left out are kept and null removes one.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct ProtocolAddressesMsg {
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub community_fund: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub collector: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub voting_escrow: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub gauge_controller: Option<Option<String>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProtocolAddressesResponse {
+    pub anchor_token: String,
+    pub community_fund: Option<String>,
+    pub collector: Option<String>,
+    pub voting_escrow: Option<String>,
+    pub gauge_controller: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
@@ -119,6 +340,67 @@ pub struct StateResponse {
     pub poll_count: u64,
     pub total_share: Uint128,
     pub total_deposit: Uint128,
+    pub polls_in_progress: u64,
+    pub polls_awaiting_execution: Vec<u64>,
+    pub truncated: bool, // true when more passed polls are awaiting execution than listed
+    pub participation_pool: Uint128,
+    pub participation_rewards: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct StakingMetricsResponse {
+    pub total_staked: Uint128,
+    pub total_share: Uint128,
+    pub share_exchange_rate: Decimal,
+    pub total_deposited_in_polls: Uint128,
+    pub anc_balance: Uint128,
+    /// Amount which can still be staked, none without a deposit cap
+    pub remaining_capacity: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct EpochStatsResponseItem {
+    pub start_time: u64, // seconds
+    pub end_time: u64,
+    pub total_staked: Uint128, // at the start of the epoch
+    pub polls_created: u64,
+    pub polls_ended: u64,
+    pub yes_votes: Uint128, // balance
+    pub no_votes: Uint128,
+    pub unique_voters: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct EpochStatsResponse {
+    pub epochs: Vec<EpochStatsResponseItem>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct ConfigHistoryResponseItem {
+    pub id: u64,
+    pub height: u64,
+    pub time: u64,
+    pub sender: String,
+    pub poll_id: Option<u64>, // set when the change was executed by a poll
+    pub changed_fields: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct ConfigHistoryResponse {
+    pub changes: Vec<ConfigHistoryResponseItem>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct ShareRateCheckpointResponse {
+    pub time: u64, // seconds
+    pub total_balance: Uint128,
+    pub total_share: Uint128,
+    pub share_rate: Decimal, // staked balance per share
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct ShareRateHistoryResponse {
+    pub checkpoints: Vec<ShareRateCheckpointResponse>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
@@ -136,6 +418,128 @@ pub struct PollResponse {
     pub no_votes: Uint128,  // balance
     pub staked_amount: Option<Uint128>,
     pub total_balance_at_end_poll: Option<Uint128>,
+    pub amended: bool,
+    pub weight_mode: WeightMode,
+    pub end_time: u64, // seconds, zero for polls created before end times were recorded
+    pub clock: PollClock,
+    /// Hex encoded merkle root of the votes, set once all votes are unlocked
+    pub vote_root: Option<String>,
+    /// Whether voting was extended because quorum was reached late
+    pub extended: bool,
+    pub resubmitted_from: Option<u64>,
+    pub resubmitted_as: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct ArchivedPollResponse {
+    pub id: u64,
+    pub status: PollStatus,
+    pub end_height: u64,
+    pub yes_votes: Uint128, // balance
+    pub no_votes: Uint128,  // balance
+    /// Hex encoded sha256 hash of the JSON encoded execute messages
+    pub execute_hash: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct PollExecuteMsgsResponseItem {
+    pub order: u64,
+    pub contract: String,
+    pub msg: Binary,
+    /// JSON form of `msg`, only given when the target is a known protocol contract
+    pub decoded_msg: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct PollExecuteMsgsResponse {
+    pub execute_msgs: Vec<PollExecuteMsgsResponseItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionVerdict {
+    /// Passed every check that can be done without executing
+    Valid,
+    /// Would fail when executed
+    Invalid,
+    /// Targets a contract the gov contract does not know, so it can not be checked
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct SimulatePollExecutionResponseItem {
+    pub order: u64,
+    pub contract: String,
+    pub verdict: ExecutionVerdict,
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct SimulatePollExecutionResponse {
+    pub execute_msgs: Vec<SimulatePollExecutionResponseItem>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct StakerRewardsResponseItem {
+    pub token: String,
+    pub pending_reward: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct StakerRewardsResponse {
+    pub rewards: Vec<StakerRewardsResponseItem>,
+}
+
+/// PollVoteProofResponse proves a vote against the vote root of the poll.
+/// Leaves are sha256("{voter}:{vote}:{balance}") and each proof step hashes
+/// the pair of hashes with the smaller one first.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct PollVoteProofResponse {
+    pub voter: String,
+    pub vote: VoteOption,
+    pub balance: Uint128,
+    pub leaf: String,
+    pub proof: Vec<String>,
+    pub root: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct PollProgressResponse {
+    pub quorum_required: Decimal,
+    pub quorum_current: Decimal,
+    pub threshold_required: Decimal,
+    pub yes: Uint128,
+    pub no: Uint128,
+    /// Status the poll would get if it was ended now
+    pub projected_status: PollStatus,
+}
+
+/// Voters with a vote amount from min (inclusive) to max (exclusive)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct WeightBucketResponse {
+    pub min: Uint128,
+    pub max: Option<Uint128>,
+    pub voters: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct PollWeightDistributionResponse {
+    pub buckets: Vec<WeightBucketResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct PollTallyDigestResponseItem {
+    pub poll_id: u64,
+    pub yes: Uint128,
+    pub no: Uint128,
+    pub status: PollStatus,
+    /// Height of the last vote, 0 if there are none or the poll predates the field
+    pub last_vote_height: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct PollTallyDigestResponse {
+    pub digests: Vec<PollTallyDigestResponseItem>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
@@ -153,6 +557,29 @@ pub struct StakerResponse {
     pub balance: Uint128,
     pub share: Uint128,
     pub locked_balance: Vec<(u64, VoterInfo)>,
+    pub sponsored_balance: Vec<(u64, Uint128)>, // pending polls sponsored
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct WithdrawLockResponseItem {
+    pub poll_id: u64,
+    pub amount: Uint128,
+    pub end_height: u64,
+    pub end_time: Option<u64>, // only for polls on the time clock
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct WithdrawStatusResponse {
+    pub balance: Uint128,
+    /// Largest vote on an in-progress poll or sponsorship of a pending poll,
+    /// locks on several polls lock the same tokens
+    pub locked_amount: Uint128,
+    pub withdrawable_amount: Uint128,
+    pub locks: Vec<WithdrawLockResponseItem>,
+    /// End of voting on the last locking poll of each clock, None when no poll on
+    /// that clock locks tokens. The tokens are released once those polls are ended.
+    pub earliest_full_withdrawal_height: Option<u64>,
+    pub earliest_full_withdrawal_time: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
@@ -167,6 +594,17 @@ pub struct VotersResponse {
     pub voters: Vec<VotersResponseItem>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct SponsorsResponseItem {
+    pub sponsor: String,
+    pub weight: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct SponsorsResponse {
+    pub sponsors: Vec<SponsorsResponseItem>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct VoterInfo {
     pub vote: VoteOption,
@@ -176,11 +614,12 @@ pub struct VoterInfo {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum PollStatus {
+    Pending,
     InProgress,
     Passed,
     Rejected,
     Executed,
-    Expired, // Depricated
+    Expired, // Pending poll which was not sponsored in time
     Failed,
 }
 
@@ -190,6 +629,29 @@ impl fmt::Display for PollStatus {
     }
 }
 
+/// PollClock sets which end of voting is authoritative. The other one is an estimate
+/// derived from the configured average block time.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PollClock {
+    Height,
+    Time,
+}
+
+/// WeightMode sets how much a vote counts towards the poll threshold.
+/// Quorum is always measured on the voted balances. Splitting a stake across
+/// addresses defeats the non-linear modes, so they are limited to text polls.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WeightMode {
+    Linear,
+    Capped {
+        max_weight: Uint128,
+    },
+    /// Integer square root of the voted micro-denominated balance, rounded down
+    SquareRoot,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum VoteOption {
@@ -206,3 +668,7 @@ impl fmt::Display for VoteOption {
         }
     }
 }
+
+/// We currently take no arguments for migrations
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}